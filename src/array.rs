@@ -0,0 +1,139 @@
+use crate::{Error, Result, Store};
+use json_dotpath::{DotPaths, Error as JsonDotPathError};
+use serde::Serialize;
+use serde_json::Value;
+
+impl Store {
+    /// Appends a value to the array at the given path, creating the array if it doesn't exist.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("push-app").unwrap();
+    /// store.push("recent_files", "a.txt").unwrap();
+    /// store.push("recent_files", "b.txt").unwrap();
+    /// store.insert_at("recent_files", 0, "c.txt").unwrap();
+    /// assert_eq!(store.pop("recent_files").unwrap().unwrap(), "b.txt");
+    /// assert_eq!(store.remove_at("recent_files", 0).unwrap().unwrap(), "c.txt");
+    /// assert_eq!(store.get("recent_files").unwrap().unwrap(), serde_json::json!(["a.txt"]));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path or the value at it is not an array.
+    pub fn push<T>(&self, path: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        self.modify(|parsed_json| {
+            array_at(parsed_json, path, true)?
+                .ok_or(JsonDotPathError::BadPathElement)?
+                .push(json_data);
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the last value of the array at the given path,
+    /// `None` if the array is empty or doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path or the value at it is not an array.
+    pub fn pop(&self, path: &str) -> Result<Option<Value>> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        self.modify(|parsed_json| Ok(array_at(parsed_json, path, false)?.and_then(Vec::pop)))
+    }
+
+    /// Inserts a value into the array at the given path at `index`, shifting the values after it.
+    /// The array is created if it doesn't exist.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path or the value at it is not an array.
+    /// * `index` is greater than the length of the array.
+    pub fn insert_at<T>(&self, path: &str, index: usize, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        self.modify(|parsed_json| {
+            let array =
+                array_at(parsed_json, path, true)?.ok_or(JsonDotPathError::BadPathElement)?;
+            if index > array.len() {
+                return Err(JsonDotPathError::BadIndex(index).into());
+            }
+            array.insert(index, json_data);
+            Ok(())
+        })
+    }
+
+    /// Removes and returns the value at `index` of the array at the given path, shifting the
+    /// values after it. Returns `None` if the array doesn't exist or `index` is out of bounds.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path or the value at it is not an array.
+    pub fn remove_at(&self, path: &str, index: usize) -> Result<Option<Value>> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        self.modify(|parsed_json| {
+            Ok(array_at(parsed_json, path, false)?
+                .filter(|array| index < array.len())
+                .map(|array| array.remove(index)))
+        })
+    }
+}
+
+/// Returns the array at the given path, `None` if there is no value at the path.
+/// With `create`, a missing value is replaced by an empty array instead.
+///
+/// # Errors
+///
+/// Errors if `path` is not a valid dot path or the value at it is not an array.
+fn array_at<'v>(
+    value: &'v mut Value,
+    path: &str,
+    create: bool,
+) -> Result<Option<&'v mut Vec<Value>>> {
+    if !create && DotPaths::dot_get::<Value>(value, path)?.is_none() {
+        return Ok(None);
+    }
+    let target = DotPaths::dot_get_mut(value, path)?;
+    if target.is_null() {
+        *target = Value::Array(Vec::new());
+    }
+    match target {
+        Value::Array(array) => Ok(Some(array)),
+        _ => Err(JsonDotPathError::BadPathElement.into()),
+    }
+}
@@ -0,0 +1,330 @@
+use crate::{error::IoContext, trace::trace_info, Error, IoOp, Result, Store};
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::SystemTime,
+};
+
+/// Controls how many previous versions of the store file are kept as backups.
+///
+/// Before every write the current store file is copied to `<store file>.bak.1`,
+/// shifting older backups up to `<store file>.bak.<keep>`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BackupPolicy {
+    /// The number of backups to keep, `0` disables backups.
+    pub keep: usize,
+}
+
+/// A backup of the store file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backup {
+    /// The generation of the backup, where `1` is the newest.
+    pub generation: usize,
+    /// The path to the backup file.
+    pub path: PathBuf,
+    /// When the backed up version of the store was last written.
+    pub modified: SystemTime,
+}
+
+/// The outcome of [`Store::open_or_recover`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Recovery {
+    /// The store was readable, or does not exist yet, and was left untouched.
+    Healthy,
+    /// The store was corrupt and the backup with the given generation was restored.
+    RestoredBackup(usize),
+    /// The store was corrupt without a valid backup and was re-initialized.
+    Reinitialized,
+}
+
+impl Store {
+    /// Sets the backup policy of the store, backups are disabled by default.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{BackupPolicy, Store};
+    /// let mut store = Store::new("backup-app").unwrap();
+    /// store.set_backup(BackupPolicy { keep: 3 });
+    /// store.set("a", 1).unwrap();
+    /// store.set("a", 2).unwrap();
+    /// assert!(store.get_backup_path(1).exists());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_backup(&mut self, policy: BackupPolicy) {
+        self.backup = policy;
+    }
+
+    pub fn get_backup(&self) -> BackupPolicy {
+        self.backup
+    }
+
+    /// Get the path to the backup with the given generation, where `1` is the newest.
+    pub fn get_backup_path(&self, generation: usize) -> PathBuf {
+        let mut backup_path: OsString = self.get_store_path().into();
+        backup_path.push(format!(".bak.{}", generation));
+        backup_path.into()
+    }
+
+    /// Returns the backups of the store file, newest first.
+    ///
+    /// Backups beyond the current policy's limit are included until the next write.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory or a backup's metadata cannot be read.
+    pub fn list_backups(&self) -> Result<Vec<Backup>> {
+        if !self.store_dir_exists() {
+            return Ok(Vec::new());
+        }
+        let store_path = self.get_store_path();
+        let prefix = match store_path.file_name().and_then(|name| name.to_str()) {
+            Some(name) => format!("{}.bak.", name),
+            None => return Ok(Vec::new()),
+        };
+        let mut backups = Vec::new();
+        let store_dir_path = self.get_store_dir_path();
+        for entry in fs::read_dir(&store_dir_path).context(IoOp::ReadDir, &store_dir_path)? {
+            let entry = entry.context(IoOp::ReadDir, &store_dir_path)?;
+            let generation = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|generation| generation.parse().ok());
+            if let Some(generation) = generation {
+                backups.push(Backup {
+                    generation,
+                    path: entry.path(),
+                    modified: entry
+                        .metadata()
+                        .and_then(|metadata| metadata.modified())
+                        .context(IoOp::Metadata, &entry.path())?,
+                });
+            }
+        }
+        backups.sort_by_key(|backup| backup.generation);
+        Ok(backups)
+    }
+
+    /// Restores the store file from the backup with the given generation, where `1` is the newest.
+    ///
+    /// The current store file is backed up first, so a restore can itself be undone.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{BackupPolicy, Store};
+    /// let mut store = Store::new("restore-backup-app").unwrap();
+    /// store.set_backup(BackupPolicy { keep: 3 });
+    /// store.set("theme", "light").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// store.restore_from_backup(1).unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "light");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The backup does not exist.
+    /// * The backup cannot be read.
+    /// * The store file fails to be written to.
+    pub fn restore_from_backup(&self, generation: usize) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let backup_path = self.get_backup_path(generation);
+        if !backup_path.exists() {
+            return Err(Error::NotFound);
+        }
+        let backup_data = fs::read(&backup_path).context(IoOp::Read, &backup_path)?;
+        self.restore_store(&backup_data)
+    }
+
+    /// Replaces the store file with a backup, discarding the journal and the shards of
+    /// the replaced store file, as backups hold the whole store. Must be called with the
+    /// store's lock file held.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file fails to be written to or a shard cannot be removed.
+    fn restore_store(&self, backup_data: &[u8]) -> Result<()> {
+        #[cfg(feature = "sharding")]
+        self.remove_stale_shards()?;
+        // The journal is removed along with the store file it was written against.
+        self.write_file(backup_data)
+    }
+
+    /// Get the path the corrupt store file is moved to by [`open_or_recover`](Store::open_or_recover).
+    pub fn get_corrupt_path(&self) -> PathBuf {
+        let mut corrupt_path: OsString = self.get_store_path().into();
+        corrupt_path.push(".corrupt");
+        corrupt_path.into()
+    }
+
+    /// Checks that the store can be read and recovers it if it is corrupt.
+    ///
+    /// A corrupt store file is moved to [`get_corrupt_path`](Store::get_corrupt_path)
+    /// for inspection, and replaced with the newest backup that can be read.
+    /// If there is no such backup, the store is re-initialized.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store cannot be read for a reason other than corruption, such as a wrong key.
+    /// * The store file is encrypted or compressed but the store is not configured to read it,
+    ///   with [`Error::FormatMismatch`].
+    /// * The store is corrupt and read-only, in which case the store file is left in place.
+    /// * The corrupt store file cannot be moved.
+    /// * The backup cannot be restored or the store cannot be re-initialized.
+    pub fn open_or_recover(&self) -> Result<Recovery> {
+        if !self.is_corrupt()? {
+            return Ok(Recovery::Healthy);
+        }
+        // Checked again under the lock, as another process may have recovered the store
+        // in the meantime.
+        let _lock = self.lock_store_file()?;
+        if !self.is_corrupt()? {
+            return Ok(Recovery::Healthy);
+        }
+        let store_path = self.get_store_path();
+        fs::rename(&store_path, self.get_corrupt_path()).context(IoOp::Rename, &store_path)?;
+        for backup in self.list_backups()? {
+            let backup_data = match fs::read(&backup.path) {
+                Ok(backup_data) => backup_data,
+                Err(_) => continue,
+            };
+            if self.parse_store_data(backup_data.clone()).is_ok() {
+                self.restore_store(&backup_data)?;
+                trace_info!(
+                    generation = backup.generation,
+                    "restored corrupt store from backup"
+                );
+                return Ok(Recovery::RestoredBackup(backup.generation));
+            }
+        }
+        self.init_store()?;
+        trace_info!("re-initialized corrupt store without a valid backup");
+        Ok(Recovery::Reinitialized)
+    }
+
+    /// Returns a boolean indicating whether the store file is corrupt, which is `false` if
+    /// it can be read or does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read for a reason other than corruption, or its
+    /// format does not match the store.
+    fn is_corrupt(&self) -> Result<bool> {
+        match self.get_store_as_parsed_json() {
+            Ok(_) | Err(Error::NotFound) => Ok(false),
+            Err(e) if !is_corruption(&e) => Err(e),
+            Err(_) => self.check_format().map(|()| true),
+        }
+    }
+
+    /// Checks that a store file which cannot be read is not a valid store in a format the
+    /// store is not configured to read, such as an encrypted store opened without a key.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be read or its format does not match the store.
+    fn check_format(&self) -> Result<()> {
+        let store_data = self.read_store_file()?;
+        let format = match store_data.as_slice() {
+            [b'B', b'L', b'N', b'D', ..] if !self.reads_encrypted() => "encrypted",
+            [0x1f, 0x8b, ..] if !self.reads_compressed() => "gzip compressed",
+            _ => return Ok(()),
+        };
+        Err(Error::FormatMismatch {
+            config: self.get_store_file_name(),
+            format,
+        })
+    }
+
+    /// Returns a boolean indicating whether the store decrypts its store file.
+    fn reads_encrypted(&self) -> bool {
+        #[cfg(feature = "crypto")]
+        if self.get_whole_store_key().is_some() {
+            return true;
+        }
+        false
+    }
+
+    /// Returns a boolean indicating whether the store decompresses its store file.
+    fn reads_compressed(&self) -> bool {
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return true;
+        }
+        false
+    }
+
+    /// Copies the current store file to the newest backup, shifting older
+    /// backups along and dropping those beyond the policy's limit.
+    ///
+    /// A store file with a journal is backed up with the journal's changes applied,
+    /// so the backup holds every change made to the store. A sharded store file is backed
+    /// up with its shards in place, as they are removed once no store file refers to them.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a backup cannot be moved or written, or the store file cannot be read
+    /// along with its journal and shards.
+    pub(crate) fn backup_store(&self) -> Result<()> {
+        if self.backup.keep == 0 || !self.store_exists() {
+            return Ok(());
+        }
+        let oldest = self.get_backup_path(self.backup.keep);
+        if oldest.exists() {
+            fs::remove_file(&oldest).context(IoOp::Remove, &oldest)?;
+        }
+        for generation in (1..self.backup.keep).rev() {
+            let backup_path = self.get_backup_path(generation);
+            if backup_path.exists() {
+                fs::rename(&backup_path, self.get_backup_path(generation + 1))
+                    .context(IoOp::Rename, &backup_path)?;
+            }
+        }
+        let backup_path = self.get_backup_path(1);
+        let journal = self.read_journal_file()?;
+        let store_data = self.read_store_file()?;
+        #[cfg(feature = "sharding")]
+        let whole = journal.is_some() || self.get_shards_path().exists();
+        #[cfg(not(feature = "sharding"))]
+        let whole = journal.is_some();
+        // A store file being initialized is still empty, and is backed up as it is.
+        let backup_data = match whole && !store_data.is_empty() {
+            true => {
+                let value = self.parse_journaled(store_data, journal.as_deref())?;
+                #[cfg(feature = "crypto")]
+                let value = self.encrypt_fields(value)?;
+                self.encode_file(&value)?
+            }
+            false => store_data,
+        };
+        self.file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&backup_path)
+            .context(IoOp::Create, &backup_path)?
+            .write_all(&backup_data)
+            .context(IoOp::Write, &backup_path)?;
+        Ok(())
+    }
+}
+
+/// Returns a boolean indicating whether the error was caused by a corrupt store file.
+fn is_corruption(error: &Error) -> bool {
+    match error {
+        Error::Corrupt { .. } | Error::ChecksumMismatch | Error::FromUTF8Error(_) => true,
+        // Compressed stores which fail to decompress.
+        Error::Io { source, .. } => matches!(
+            source.kind(),
+            io::ErrorKind::InvalidData | io::ErrorKind::InvalidInput | io::ErrorKind::UnexpectedEof
+        ),
+        _ => false,
+    }
+}
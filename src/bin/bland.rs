@@ -0,0 +1,300 @@
+//! A command line tool for inspecting and editing `bland` stores.
+//!
+//! Run `bland help` for usage information.
+use bland::Store;
+use serde_json::Value;
+use std::{
+    env,
+    io::{self, BufRead, Write},
+    path::PathBuf,
+    process,
+};
+
+const USAGE: &str = "Usage: bland [options] <command> <app> [args]
+
+Commands:
+    get <app> <path>            Print the value at the given path
+    set <app> <path> <value>    Set the value at the given path (JSON, or a plain string)
+    delete <app> <path>         Delete the value at the given path
+    list <app>                  Print the whole store
+    path <app>                  Print the path to the store file
+    help                        Print this message
+
+Options:
+    --dir <dir>                 Base directory of the store
+    --config <name>             Configuration name (default: config)
+    --extension <ext>           File extension (default: json)
+    --suffix <suffix>           Project name suffix (default: rs)
+    --no-suffix                 Do not append a suffix to the project name
+    --profile <profile>         Profile to use
+    --pretty                    Write the store in a human readable format";
+
+#[cfg(feature = "crypto")]
+const CRYPTO_USAGE: &str = "
+    --key                       Read the encryption key from BLAND_KEY, or standard input
+    --password                  Read the password from BLAND_PASSWORD, or standard input";
+
+#[cfg(not(feature = "crypto"))]
+const CRYPTO_USAGE: &str = "";
+
+#[cfg(feature = "compression")]
+const COMPRESSION_USAGE: &str = "
+    --compressed                The store is compressed";
+
+#[cfg(not(feature = "compression"))]
+const COMPRESSION_USAGE: &str = "";
+
+/// A secret the store is encrypted with.
+#[cfg(feature = "crypto")]
+enum Secret {
+    Key(String),
+    Password(String),
+}
+
+/// The options shared by every subcommand.
+#[derive(Default)]
+struct Options {
+    dir: Option<PathBuf>,
+    config: Option<String>,
+    extension: Option<String>,
+    suffix: Option<String>,
+    no_suffix: bool,
+    profile: Option<String>,
+    pretty: bool,
+    #[cfg(feature = "crypto")]
+    secret: Option<Secret>,
+    #[cfg(feature = "compression")]
+    compressed: bool,
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match run(args, &mut io::stdin().lock(), &mut io::stdout().lock()) {
+        Ok(()) => {}
+        Err(message) => {
+            eprintln!("bland: {}", message);
+            process::exit(1);
+        }
+    }
+}
+
+fn usage() -> String {
+    format!("{}{}{}", USAGE, CRYPTO_USAGE, COMPRESSION_USAGE)
+}
+
+/// Runs the command line tool, reading secrets from `input` and printing to `output`.
+#[cfg_attr(not(feature = "crypto"), allow(unused_variables))]
+fn run(args: Vec<String>, input: &mut dyn BufRead, output: &mut dyn Write) -> Result<(), String> {
+    let mut options = Options::default();
+    let mut positional = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .ok_or_else(|| format!("missing value for {}", name))
+        };
+        match arg.as_str() {
+            "--dir" => options.dir = Some(PathBuf::from(value("--dir")?)),
+            "--config" => options.config = Some(value("--config")?),
+            "--extension" => options.extension = Some(value("--extension")?),
+            "--suffix" => options.suffix = Some(value("--suffix")?),
+            "--no-suffix" => options.no_suffix = true,
+            "--profile" => options.profile = Some(value("--profile")?),
+            "--pretty" => options.pretty = true,
+            #[cfg(feature = "crypto")]
+            "--key" | "--password" if options.secret.is_some() => {
+                return Err("only one of --key and --password can be given".to_owned())
+            }
+            #[cfg(feature = "crypto")]
+            "--key" => {
+                let key = read_secret("BLAND_KEY", "encryption key", input)?;
+                options.secret = Some(Secret::Key(key));
+            }
+            #[cfg(feature = "crypto")]
+            "--password" => {
+                let password = read_secret("BLAND_PASSWORD", "password", input)?;
+                options.secret = Some(Secret::Password(password));
+            }
+            #[cfg(feature = "compression")]
+            "--compressed" => options.compressed = true,
+            "-h" | "--help" => positional.push("help".to_owned()),
+            _ if arg.starts_with("--") => {
+                return Err(format!("unknown option {}\n\n{}", arg, usage()))
+            }
+            _ => positional.push(arg),
+        }
+    }
+
+    let command = match positional.first() {
+        Some(command) => command.as_str(),
+        None => return Err(usage()),
+    };
+    if command == "help" {
+        return writeln!(output, "{}", usage()).map_err(|e| e.to_string());
+    }
+    let app = positional
+        .get(1)
+        .ok_or_else(|| format!("missing <app>\n\n{}", usage()))?;
+    let store = open_store(app, &options).map_err(|e| e.to_string())?;
+    let path = positional.get(2).map(String::as_str);
+
+    match (command, path, positional.get(3)) {
+        ("get", Some(path), None) => match store.get(path).map_err(|e| e.to_string())? {
+            Some(value) => print_value(output, &value)?,
+            None => return Err(format!("no value at {}", path)),
+        },
+        ("set", Some(path), Some(value)) => {
+            // Fall back to a plain string so `bland set app name foo` works without quoting.
+            let value = serde_json::from_str::<Value>(value)
+                .unwrap_or_else(|_| Value::String(value.to_owned()));
+            store.set(path, value).map_err(|e| e.to_string())?;
+        }
+        ("delete", Some(path), None) => match store.delete(path).map_err(|e| e.to_string())? {
+            Some(value) => print_value(output, &value)?,
+            None => return Err(format!("no value at {}", path)),
+        },
+        ("list", None, None) => {
+            let value = store.to_value().map_err(|e| e.to_string())?;
+            print_value(output, &value)?;
+        }
+        ("path", None, None) => {
+            writeln!(output, "{}", store.get_store_path().display()).map_err(|e| e.to_string())?
+        }
+        ("get", _, _) | ("set", _, _) | ("delete", _, _) | ("list", _, _) | ("path", _, _) => {
+            return Err(format!("invalid arguments for {}\n\n{}", command, usage()))
+        }
+        _ => return Err(format!("unknown command {}\n\n{}", command, usage())),
+    }
+    Ok(())
+}
+
+/// Opens the store of the given app using the command line options.
+fn open_store(app: &str, options: &Options) -> bland::Result<Store> {
+    let mut store = Store::new(app)?;
+    if let Some(dir) = &options.dir {
+        store.set_path(dir.clone());
+    }
+    if let Some(config) = &options.config {
+        store.set_config_name(config);
+    }
+    if let Some(extension) = &options.extension {
+        store.set_file_extension(extension);
+    }
+    if let Some(suffix) = &options.suffix {
+        store.set_project_suffix(Some(suffix));
+    }
+    if options.no_suffix {
+        store.set_project_suffix(None);
+    }
+    if let Some(profile) = &options.profile {
+        store.set_profile(profile);
+    }
+    store.set_pretty(options.pretty);
+    #[cfg(feature = "crypto")]
+    match &options.secret {
+        Some(Secret::Key(key)) => store.set_encryption_key(key)?,
+        Some(Secret::Password(password)) => store.set_password(password),
+        None => {}
+    }
+    #[cfg(feature = "compression")]
+    store.set_compressed(options.compressed);
+    Ok(store)
+}
+
+/// Reads a secret from the environment variable `var`, or else the first line of `input`,
+/// so it does not show up in the process list or the shell history.
+#[cfg(feature = "crypto")]
+fn read_secret(var: &str, name: &str, input: &mut dyn BufRead) -> Result<String, String> {
+    if let Some(secret) = env::var_os(var) {
+        return secret
+            .into_string()
+            .map_err(|_| format!("{} is not valid unicode", var));
+    }
+    let mut secret = String::new();
+    input
+        .read_line(&mut secret)
+        .map_err(|e| format!("failed to read the {}: {}", name, e))?;
+    let secret = secret.trim_end_matches(&['\r', '\n'][..]);
+    if secret.is_empty() {
+        return Err(format!(
+            "missing {}, set {} or write it to standard input",
+            name, var
+        ));
+    }
+    Ok(secret.to_owned())
+}
+
+fn print_value(output: &mut dyn Write, value: &Value) -> Result<(), String> {
+    match value {
+        Value::String(s) => writeln!(output, "{}", s),
+        _ => writeln!(
+            output,
+            "{}",
+            serde_json::to_string_pretty(value).unwrap_or_else(|_| value.to_string())
+        ),
+    }
+    .map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::run;
+    use bland::testing::TempStore;
+
+    /// Runs the command line tool on the given store with `input` as standard input,
+    /// returning what it printed.
+    fn bland(store: &TempStore, args: &[&str], input: &str) -> Result<String, String> {
+        let dir = store.path().display().to_string();
+        let args = ["--dir", &dir]
+            .iter()
+            .chain(args)
+            .map(|arg| arg.to_string())
+            .collect();
+        let mut output = Vec::new();
+        run(args, &mut input.as_bytes(), &mut output)?;
+        Ok(String::from_utf8(output).unwrap())
+    }
+
+    #[test]
+    fn get_set_list() {
+        let store = TempStore::new("cli_test").unwrap();
+        bland(&store, &["set", "cli_test", "theme", "dark"], "").unwrap();
+        bland(&store, &["set", "cli_test", "window.width", "800"], "").unwrap();
+        assert_eq!(store.get("window.width").unwrap().unwrap(), 800);
+        assert_eq!(
+            bland(&store, &["get", "cli_test", "theme"], "").unwrap(),
+            "dark\n"
+        );
+        assert_eq!(
+            bland(&store, &["get", "cli_test", "window"], "").unwrap(),
+            "{\n  \"width\": 800\n}\n"
+        );
+        assert!(bland(&store, &["get", "cli_test", "missing"], "").is_err());
+        let list = bland(&store, &["list", "cli_test"], "").unwrap();
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&list).unwrap(),
+            store.to_value().unwrap()
+        );
+        assert_eq!(
+            bland(&store, &["path", "cli_test"], "").unwrap(),
+            format!("{}\n", store.get_store_path().display())
+        );
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn password() {
+        let mut store = TempStore::new("cli_password_test").unwrap();
+        let set = ["--password", "set", "cli_password_test", "token", "secret"];
+        bland(&store, &set, "hunter2\n").unwrap();
+        assert!(store.get("token").is_err());
+        store.set_password("hunter2");
+        assert_eq!(store.get("token").unwrap().unwrap(), "secret");
+
+        let get = ["--password", "get", "cli_password_test", "token"];
+        assert_eq!(bland(&store, &get, "hunter2\n").unwrap(), "secret\n");
+        assert!(bland(&store, &get, "wrong\n").is_err());
+        assert!(bland(&store, &get, "").is_err());
+        assert!(bland(&store, &["--key", "--password"], "hunter2\nhunter2\n").is_err());
+    }
+}
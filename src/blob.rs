@@ -0,0 +1,194 @@
+use crate::{error::IoContext, Error, IoOp, Result, Store};
+use json_dotpath::DotPaths;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{collections::BTreeSet, fmt::Write as _, fs, io, path::PathBuf};
+
+/// The key of the object referring to a blob in the store.
+const BLOB_KEY: &str = "$blob";
+
+impl Store {
+    /// Returns the path to the directory blobs are kept in, next to the store file.
+    pub fn get_blobs_path(&self) -> PathBuf {
+        self.get_store_path().with_extension("blobs")
+    }
+
+    /// Stores binary data as a file in the store's blobs directory and sets the value at the
+    /// given path to a reference to it, `{ "$blob": "<SHA-256 of the data>" }`. Large binary
+    /// values are kept out of the store file this way, rather than encoded into it.
+    ///
+    /// Blobs are named by their contents, so storing the same data twice keeps one file.
+    /// Blobs no longer referred to are removed by [`gc_blobs`](Store::gc_blobs).
+    ///
+    /// **NOTE:** Blobs are written as they are, even if the store is encrypted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("blob-app").unwrap();
+    /// store.put_blob("avatars.user1", &[0x89, b'P', b'N', b'G']).unwrap();
+    /// assert_eq!(store.get_blob("avatars.user1").unwrap().unwrap(), [0x89, b'P', b'N', b'G']);
+    /// assert!(store.get("avatars.user1.$blob").unwrap().is_some());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store would exceed its [maximum size](Store::set_max_size) with the blob, with
+    ///   [`Error::QuotaExceeded`].
+    /// * The blob cannot be written.
+    /// * The store fails to be created or written to.
+    /// * `path` is not a valid dot path.
+    pub fn put_blob(&self, path: &str, data: &[u8]) -> Result<()> {
+        let hash = blob_hash(data);
+        // The blob is written under the store's lock, so it cannot be collected by
+        // `gc_blobs` before the store refers to it.
+        self.modify(|parsed_json| {
+            let blobs_path = self.get_blobs_path();
+            let blob_path = blobs_path.join(&hash);
+            if !blob_path.exists() {
+                self.check_blob_size(data.len())?;
+                self.dir_builder()
+                    .recursive(true)
+                    .create(&blobs_path)
+                    .context(IoOp::CreateDir, &blobs_path)?;
+                self.replace_file(&blob_path, data)?;
+            }
+            let mut reference = Map::new();
+            reference.insert(BLOB_KEY.to_string(), Value::String(hash));
+            parsed_json
+                .dot_set(path, Value::Object(reference))
+                .map_err(Error::from)
+        })
+    }
+
+    /// Returns the binary data stored with [`put_blob`](Store::put_blob) at the given path,
+    /// or `None` if there is no value at the path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file or the blob.
+    /// * The store cannot be deserialized.
+    /// * The value at the path is not a reference to a blob, with [`Error::InvalidValue`].
+    /// * The blob's contents do not match its hash, with [`Error::ChecksumMismatch`].
+    /// * `path` is not a valid dot path.
+    pub fn get_blob(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let hash = match self.get_resolved_json()?.dot_get::<Value>(path)? {
+            Some(value) => match blob_reference(&value) {
+                Some(hash) => hash.to_string(),
+                None => {
+                    return Err(Error::InvalidValue {
+                        path: path.to_string(),
+                        reason: "not a reference to a blob".to_string(),
+                    })
+                }
+            },
+            None => return Ok(None),
+        };
+        let blob_path = self.get_blobs_path().join(&hash);
+        let data = fs::read(&blob_path).context(IoOp::Read, &blob_path)?;
+        if blob_hash(&data) != hash {
+            return Err(Error::ChecksumMismatch);
+        }
+        Ok(Some(data))
+    }
+
+    /// Returns the total size in bytes of the store's blobs.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the blobs directory cannot be read.
+    pub(crate) fn get_blobs_size(&self) -> Result<usize> {
+        let blobs_path = self.get_blobs_path();
+        let entries = match fs::read_dir(&blobs_path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).context(IoOp::ReadDir, &blobs_path),
+        };
+        let mut size = 0;
+        for entry in entries {
+            let entry = entry.context(IoOp::ReadDir, &blobs_path)?;
+            size += entry
+                .metadata()
+                .context(IoOp::Metadata, &entry.path())?
+                .len() as usize;
+        }
+        Ok(size)
+    }
+
+    /// Removes the blobs which are no longer referred to by the store, returning the number
+    /// of blobs removed. References in included files and in every environment's section
+    /// are kept.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file or an included file.
+    /// * The store cannot be deserialized.
+    /// * The blobs directory cannot be read or a blob cannot be removed.
+    pub fn gc_blobs(&self) -> Result<usize> {
+        let _lock = self.lock_store_file()?;
+        let mut referenced = BTreeSet::new();
+        let value = self.merge_includes(self.get_store_as_parsed_json()?)?;
+        collect_blob_references(&value, &mut referenced);
+        let blobs_path = self.get_blobs_path();
+        let entries = match fs::read_dir(&blobs_path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e).context(IoOp::ReadDir, &blobs_path),
+        };
+        let mut removed = 0;
+        for entry in entries {
+            let entry = entry.context(IoOp::ReadDir, &blobs_path)?;
+            let name = entry.file_name();
+            if name.to_str().is_some_and(|name| referenced.contains(name)) {
+                continue;
+            }
+            let blob_path = entry.path();
+            fs::remove_file(&blob_path).context(IoOp::Remove, &blob_path)?;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+/// Returns the hash blobs are named by, the SHA-256 of their contents in lower case hex.
+fn blob_hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .fold(String::with_capacity(64), |mut hash, byte| {
+            // Writing to a string cannot fail.
+            let _ = write!(hash, "{:02x}", byte);
+            hash
+        })
+}
+
+/// Returns the hash of the blob a value refers to, or `None` if it is not a reference.
+fn blob_reference(value: &Value) -> Option<&str> {
+    match value.as_object() {
+        Some(map) if map.len() == 1 => map.get(BLOB_KEY)?.as_str(),
+        _ => None,
+    }
+}
+
+/// Collects the hashes of every blob referred to within `value` into `referenced`.
+fn collect_blob_references(value: &Value, referenced: &mut BTreeSet<String>) {
+    if let Some(hash) = blob_reference(value) {
+        referenced.insert(hash.to_string());
+        return;
+    }
+    match value {
+        Value::Object(map) => map
+            .values()
+            .for_each(|value| collect_blob_references(value, referenced)),
+        Value::Array(array) => array
+            .iter()
+            .for_each(|value| collect_blob_references(value, referenced)),
+        _ => {}
+    }
+}
@@ -0,0 +1,57 @@
+use crate::{Error, Result, Store};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use serde_json::Value;
+
+impl Store {
+    /// Returns the bytes at the given path, stored as a standard base64 string.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("bytes-app").unwrap();
+    /// store.set_bytes("session.token", &[0xde, 0xad, 0xbe, 0xef]).unwrap();
+    /// assert_eq!(store.get("session.token").unwrap().unwrap(), "3q2+7w==");
+    /// assert_eq!(store.get_bytes("session.token").unwrap().unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not a base64 string, with [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_bytes(&self, path: &str) -> Result<Option<Vec<u8>>> {
+        let invalid = |reason: String| Error::InvalidValue {
+            path: path.to_string(),
+            reason,
+        };
+        match self.get(path)? {
+            Some(Value::String(encoded)) => STANDARD
+                .decode(encoded)
+                .map(Some)
+                .map_err(|e| invalid(e.to_string())),
+            Some(_) => Err(invalid("expected a base64 string".to_string())),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets the bytes at the given path as a standard base64 string, for small binary values
+    /// such as keys, tokens or thumbnails. Larger values are better kept as blobs with the
+    /// `blobs` feature.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn set_bytes(&self, path: &str, bytes: &[u8]) -> Result<()> {
+        self.set(path, STANDARD.encode(bytes))
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{error::IoContext, IoOp, Result, Store};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Controls how often the cached contents of the store are checked against the store file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FreshnessPolicy {
+    /// Check before every read.
+    Always,
+    /// Check at most once per interval, serving the cached contents in between.
+    Interval(Duration),
+    /// Never check, so changes by other processes are only seen after a write.
+    Never,
+}
+
+/// The modification time and length of the store file and its journal,
+/// which change whenever another process writes to the store.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Fingerprint {
+    store: (Option<SystemTime>, u64),
+    journal: Option<(Option<SystemTime>, u64)>,
+}
+
+/// The cached contents of the store file.
+pub(crate) struct CachedStore {
+    /// The path to the store file the contents were read from.
+    path: PathBuf,
+    /// The fingerprint of the store file, taken before it was read.
+    fingerprint: Fingerprint,
+    /// When the fingerprint was last checked.
+    checked: Instant,
+    /// The parsed contents of the store file.
+    value: Value,
+}
+
+impl Store {
+    /// Sets whether the parsed contents of the store are kept in memory between reads, and
+    /// how often they are checked against the store file. Caching is disabled by default.
+    ///
+    /// The cache is reloaded when the store file's modification time or length changes,
+    /// and is always checked before a write so other processes' changes are not lost.
+    /// File systems with coarse modification times may miss a change of the same length
+    /// made within the same tick.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{FreshnessPolicy, Store};
+    /// # use std::time::Duration;
+    /// let mut store = Store::new("cache-app").unwrap();
+    /// store.set_cache(Some(FreshnessPolicy::Interval(Duration::from_millis(100))));
+    /// store.set("theme", "dark").unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_cache(&mut self, policy: Option<FreshnessPolicy>) {
+        self.cache_policy = policy;
+        self.invalidate_cache();
+    }
+
+    pub fn get_cache(&self) -> Option<FreshnessPolicy> {
+        self.cache_policy
+    }
+
+    /// Discards the cached contents and re-reads the store file, returning its contents.
+    ///
+    /// Deferred changes are not cached state and are kept, so they are included in the
+    /// returned contents until they are flushed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{FreshnessPolicy, Store};
+    /// let mut store = Store::new("reload-app").unwrap();
+    /// store.set_cache(Some(FreshnessPolicy::Never));
+    /// store.set("theme", "dark").unwrap();
+    /// let contents = store.reload().unwrap();
+    /// assert_eq!(contents["theme"], "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn reload(&self) -> Result<Value> {
+        self.invalidate_cache();
+        self.get_store_as_parsed_json()
+    }
+
+    /// Returns the cached contents of the store if they are fresh according to the
+    /// freshness policy, or `None` if they have to be read from the store file.
+    pub(crate) fn get_cached_value(&self) -> Option<Value> {
+        let policy = self.cache_policy?;
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let store_path = self.get_store_path();
+        let cached = cache.as_mut().filter(|cached| cached.path == store_path)?;
+        let check = match policy {
+            FreshnessPolicy::Always => true,
+            FreshnessPolicy::Interval(interval) => cached.checked.elapsed() >= interval,
+            FreshnessPolicy::Never => false,
+        };
+        if check {
+            if self.fingerprint(&store_path).ok() != Some(cached.fingerprint) {
+                *cache = None;
+                return None;
+            }
+            cached.checked = Instant::now();
+        }
+        Some(cached.value.clone())
+    }
+
+    /// Returns the fingerprint to cache the store file's contents under, taken before the
+    /// store file is read, or `None` if caching is disabled.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file's metadata cannot be read.
+    pub(crate) fn get_cache_fingerprint(&self) -> Result<Option<Fingerprint>> {
+        match self.cache_policy {
+            Some(_) => self.fingerprint(&self.get_store_path()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Caches the contents read from the store file with the given fingerprint.
+    pub(crate) fn cache_value(&self, fingerprint: Fingerprint, value: &Value) {
+        *self.cache.lock().unwrap_or_else(|e| e.into_inner()) = Some(CachedStore {
+            path: self.get_store_path(),
+            fingerprint,
+            checked: Instant::now(),
+            value: value.clone(),
+        });
+    }
+
+    /// Discards the cached contents if the store file changed since they were read,
+    /// regardless of the freshness policy. Called once the store's lock is held, so
+    /// writes start from the current contents.
+    pub(crate) fn revalidate_cache(&self) {
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(cached) = cache.as_mut() {
+            match self.fingerprint(&cached.path) {
+                Ok(fingerprint) if fingerprint == cached.fingerprint => {
+                    cached.checked = Instant::now();
+                }
+                _ => *cache = None,
+            }
+        }
+    }
+
+    /// Discards the cached contents.
+    pub(crate) fn invalidate_cache(&self) {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+
+    /// Returns the fingerprint of the store file at the given path and its journal.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file's metadata cannot be read.
+    fn fingerprint(&self, store_path: &Path) -> Result<Fingerprint> {
+        let stat = |metadata: fs::Metadata| (metadata.modified().ok(), metadata.len());
+        let store = fs::metadata(store_path)
+            .map(stat)
+            .context(IoOp::Metadata, store_path)?;
+        let journal = fs::metadata(store_path.with_extension("journal"))
+            .map(stat)
+            .ok();
+        Ok(Fingerprint { store, journal })
+    }
+}
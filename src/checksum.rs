@@ -0,0 +1,35 @@
+use crate::{Error, Result};
+use std::convert::TryInto;
+
+/// The magic bytes marking the start of the checksum footer.
+const FOOTER_MAGIC: &[u8; 4] = b"BLCK";
+/// The length of the checksum footer, the magic bytes followed by the CRC32.
+const FOOTER_LEN: usize = FOOTER_MAGIC.len() + 4;
+
+/// Appends a footer made of the magic bytes `BLCK` and the little endian
+/// CRC32 of the data.
+pub fn append_checksum(mut data: Vec<u8>) -> Vec<u8> {
+    let checksum = crc32fast::hash(&data);
+    data.extend_from_slice(FOOTER_MAGIC);
+    data.extend_from_slice(&checksum.to_le_bytes());
+    data
+}
+
+/// Verifies and removes the checksum footer of the data.
+///
+/// # Errors
+///
+/// Errors if the footer is missing or the checksum does not match the data.
+pub fn verify_checksum(mut data: Vec<u8>) -> Result<Vec<u8>> {
+    if data.len() < FOOTER_LEN {
+        return Err(Error::ChecksumMismatch);
+    }
+    let (payload, footer) = data.split_at(data.len() - FOOTER_LEN);
+    let (magic, checksum) = footer.split_at(FOOTER_MAGIC.len());
+    let checksum = u32::from_le_bytes(checksum.try_into().map_err(|_| Error::ChecksumMismatch)?);
+    if magic != FOOTER_MAGIC || crc32fast::hash(payload) != checksum {
+        return Err(Error::ChecksumMismatch);
+    }
+    data.truncate(data.len() - FOOTER_LEN);
+    Ok(data)
+}
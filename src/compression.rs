@@ -0,0 +1,76 @@
+use crate::Result;
+use flate2::{
+    read::{DeflateDecoder, GzDecoder},
+    write::{DeflateEncoder, GzEncoder},
+    Compression,
+};
+use std::io::{self, Read, Write};
+
+/// The algorithm used to compress a store.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CompressionAlgorithm {
+    /// Gzip, deflate with a header and a checksum.
+    #[default]
+    Gzip,
+    /// Raw deflate, slightly smaller than gzip but without a checksum.
+    Deflate,
+}
+
+/// A writer compressing the data written to it into a buffer.
+pub enum Compressor {
+    Gzip(GzEncoder<Vec<u8>>),
+    Deflate(DeflateEncoder<Vec<u8>>),
+}
+
+impl Compressor {
+    /// Creates a compressor with the given algorithm and level.
+    /// The level ranges from 0 (no compression) to 9 (best compression).
+    pub fn new(algorithm: CompressionAlgorithm, level: u32) -> Self {
+        let level = Compression::new(level);
+        match algorithm {
+            CompressionAlgorithm::Gzip => Compressor::Gzip(GzEncoder::new(Vec::new(), level)),
+            CompressionAlgorithm::Deflate => {
+                Compressor::Deflate(DeflateEncoder::new(Vec::new(), level))
+            }
+        }
+    }
+
+    /// Finishes compressing and returns the compressed data.
+    pub fn finish(self) -> Result<Vec<u8>> {
+        Ok(match self {
+            Compressor::Gzip(e) => e.finish()?,
+            Compressor::Deflate(e) => e.finish()?,
+        })
+    }
+}
+
+impl Write for Compressor {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Compressor::Gzip(e) => e.write(buf),
+            Compressor::Deflate(e) => e.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Compressor::Gzip(e) => e.flush(),
+            Compressor::Deflate(e) => e.flush(),
+        }
+    }
+}
+
+/// Returns a reader decompressing the data with the given algorithm as it is read.
+pub fn decompressor(data: &[u8], algorithm: CompressionAlgorithm) -> Box<dyn Read + '_> {
+    match algorithm {
+        CompressionAlgorithm::Gzip => Box::new(GzDecoder::new(data)),
+        CompressionAlgorithm::Deflate => Box::new(DeflateDecoder::new(data)),
+    }
+}
+
+/// Decompresses the data with the given algorithm.
+pub fn decompress_data(data: &[u8], algorithm: CompressionAlgorithm) -> Result<Vec<u8>> {
+    let mut decompressed_data = Vec::new();
+    decompressor(data, algorithm).read_to_end(&mut decompressed_data)?;
+    Ok(decompressed_data)
+}
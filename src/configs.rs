@@ -0,0 +1,321 @@
+use crate::{error::IoContext, Error, IoOp, Result, Store};
+use std::{
+    fs::{self, File},
+    io,
+    path::{Path, PathBuf},
+};
+
+/// The extensions of the files and directories kept next to a configuration file, which
+/// are renamed and copied along with it.
+const SIDECAR_EXTENSIONS: &[&str] = &["journal", "shards", "blobs", "generation"];
+
+impl Store {
+    /// Returns the names of all configuration files in the store directory,
+    /// sorted alphabetically.
+    ///
+    /// Profiles of a configuration are not included, see [`list_profiles`](Store::list_profiles).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("list-configs-app").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// store.open_config("work").unwrap();
+    /// store.set("theme", "light").unwrap();
+    /// assert_eq!(store.list_configs().unwrap(), vec!["config", "work"]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be read.
+    pub fn list_configs(&self) -> Result<Vec<String>> {
+        if !self.store_dir_exists() {
+            return Ok(Vec::new());
+        }
+        let suffix = format!(".{}", self.get_file_extension());
+        let mut configs = Vec::new();
+        let store_dir_path = self.get_store_dir_path();
+        for entry in fs::read_dir(&store_dir_path).context(IoOp::ReadDir, &store_dir_path)? {
+            let entry = entry.context(IoOp::ReadDir, &store_dir_path)?;
+            if !entry
+                .file_type()
+                .context(IoOp::Metadata, &entry.path())?
+                .is_file()
+            {
+                continue;
+            }
+            let file_name = entry.file_name();
+            let config = file_name
+                .to_str()
+                .and_then(|file_name| file_name.strip_suffix(&suffix));
+            if let Some(config) = config {
+                if !config.is_empty() && !config.contains('.') {
+                    configs.push(config.to_owned());
+                }
+            }
+        }
+        configs.sort();
+        Ok(configs)
+    }
+
+    /// Renames the configuration file `from` to `to` within the active profile,
+    /// overwriting `to` if it already exists. Renaming a configuration to itself does nothing.
+    ///
+    /// The file is moved as is, so encrypted and compressed stores keep working, along with
+    /// its backups and history snapshots.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("rename-config-app").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// store.rename_config("config", "archived").unwrap();
+    /// assert_eq!(store.list_configs().unwrap(), vec!["archived"]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The `from` configuration does not exist.
+    /// * Deferred changes fail to be written.
+    /// * The file cannot be renamed.
+    pub fn rename_config(&self, from: &str, to: &str) -> Result<()> {
+        self.check_writable()?;
+        self.flush()?;
+        let from_path = self.get_config_path(from);
+        let to_path = self.get_config_path(to);
+        let _locks = self.lock_config_files(&from_path, &to_path)?;
+        if !from_path.exists() {
+            return Err(Error::NotFound);
+        }
+        if from_path == to_path {
+            return Ok(());
+        }
+        fs::rename(&from_path, &to_path).context(IoOp::Rename, &from_path)?;
+        for extension in SIDECAR_EXTENSIONS {
+            let from_sidecar_path = from_path.with_extension(extension);
+            let to_sidecar_path = to_path.with_extension(extension);
+            remove_sidecar(&to_sidecar_path)?;
+            if from_sidecar_path.exists() {
+                fs::rename(&from_sidecar_path, &to_sidecar_path)
+                    .context(IoOp::Rename, &from_sidecar_path)?;
+            }
+        }
+        self.transfer_versions(&from_path, &to_path, false)
+    }
+
+    /// Copies the configuration file `from` to `to` within the active profile,
+    /// overwriting `to` if it already exists. Copying a configuration onto itself does nothing.
+    ///
+    /// The file is copied as is, so encrypted and compressed stores keep working, along with
+    /// its backups and history snapshots.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("duplicate-config-app").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// store.duplicate_config("config", "copy").unwrap();
+    /// store.open_config("copy").unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The `from` configuration does not exist.
+    /// * Deferred changes fail to be written.
+    /// * The file cannot be copied.
+    pub fn duplicate_config(&self, from: &str, to: &str) -> Result<()> {
+        self.check_writable()?;
+        self.flush()?;
+        let from_path = self.get_config_path(from);
+        let to_path = self.get_config_path(to);
+        let _locks = self.lock_config_files(&from_path, &to_path)?;
+        if !from_path.exists() {
+            return Err(Error::NotFound);
+        }
+        if from_path == to_path {
+            return Ok(());
+        }
+        fs::copy(&from_path, &to_path).context(IoOp::Copy, &from_path)?;
+        self.copy_sidecars(&from_path, &to_path)?;
+        self.transfer_versions(&from_path, &to_path, true)
+    }
+
+    /// Takes the locks of the configuration files at `from` and `to`, in the order of their
+    /// paths so two processes moving configurations onto each other cannot deadlock.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store is read-only or a lock cannot be taken.
+    fn lock_config_files(&self, from: &Path, to: &Path) -> Result<Vec<File>> {
+        let mut paths = [from, to];
+        paths.sort();
+        let mut locks = vec![self.lock_config_file(paths[0])?];
+        if paths[0] != paths[1] {
+            locks.push(self.lock_config_file(paths[1])?);
+        }
+        Ok(locks)
+    }
+
+    /// Moves, or copies if `copy` is set, the backups and history snapshots of the
+    /// configuration file at `from` to those of the configuration file at `to`, replacing
+    /// the ones it has.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store or history directory cannot be read, or a backup or snapshot
+    /// cannot be removed, moved or copied.
+    fn transfer_versions(&self, from: &Path, to: &Path, copy: bool) -> Result<()> {
+        let file_name = |path: &Path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default()
+                .to_owned()
+        };
+        let (from_name, to_name) = (file_name(from), file_name(to));
+        transfer_prefixed(
+            &self.get_store_dir_path(),
+            &format!("{}.bak.", from_name),
+            &format!("{}.bak.", to_name),
+            copy,
+        )?;
+        #[cfg(feature = "history")]
+        transfer_prefixed(
+            &self.get_history_dir_path(),
+            &format!("{}.", from_name),
+            &format!("{}.", to_name),
+            copy,
+        )?;
+        Ok(())
+    }
+
+    /// Copies the sidecars of the configuration file at `from` to those of the
+    /// configuration file at `to`, replacing the ones it has.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a sidecar cannot be removed or copied.
+    pub(crate) fn copy_sidecars(&self, from: &Path, to: &Path) -> Result<()> {
+        for extension in SIDECAR_EXTENSIONS {
+            let from_sidecar_path = from.with_extension(extension);
+            let to_sidecar_path = to.with_extension(extension);
+            remove_sidecar(&to_sidecar_path)?;
+            if from_sidecar_path.is_dir() {
+                self.copy_sidecar_dir(&from_sidecar_path, &to_sidecar_path)?;
+            } else if from_sidecar_path.exists() {
+                fs::copy(&from_sidecar_path, &to_sidecar_path)
+                    .context(IoOp::Copy, &from_sidecar_path)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copies the files in the sidecar directory `from` to a new directory `to`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the directory cannot be created or read, or a file cannot be copied.
+    fn copy_sidecar_dir(&self, from: &Path, to: &Path) -> Result<()> {
+        self.dir_builder().create(to).context(IoOp::CreateDir, to)?;
+        for entry in fs::read_dir(from).context(IoOp::ReadDir, from)? {
+            let entry = entry.context(IoOp::ReadDir, from)?;
+            let path = entry.path();
+            fs::copy(&path, to.join(entry.file_name())).context(IoOp::Copy, &path)?;
+        }
+        Ok(())
+    }
+
+    /// Get the path to the configuration file with the given name in the active profile.
+    fn get_config_path(&self, config_name: &str) -> PathBuf {
+        let mut file_name = config_name.to_owned();
+        if let Some(profile) = &self.profile {
+            file_name.push('.');
+            file_name.push_str(profile);
+        }
+        file_name.push('.');
+        file_name.push_str(&self.file_extension);
+        self.get_store_dir_path().join(file_name)
+    }
+
+    /// Switches the store to the configuration file with the given name,
+    /// which is created on the next write if it does not exist.
+    ///
+    /// Deferred changes to the current configuration are flushed first,
+    /// and its undo history is discarded.
+    ///
+    /// # Errors
+    ///
+    /// Errors if deferred changes to the current configuration fail to be written.
+    pub fn open_config(&mut self, name: &str) -> Result<()> {
+        self.flush()?;
+        self.undo_log
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.set_config_name(name);
+        Ok(())
+    }
+}
+
+/// Moves, or copies if `copy` is set, the files in `dir` whose names start with `from` to
+/// files starting with `to` instead, after removing the files already starting with `to`.
+///
+/// # Errors
+///
+/// Errors if the directory cannot be read, or a file cannot be removed, moved or copied.
+fn transfer_prefixed(dir: &Path, from: &str, to: &str, copy: bool) -> Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e).context(IoOp::ReadDir, dir),
+    };
+    let mut names = Vec::new();
+    for entry in entries {
+        let entry = entry.context(IoOp::ReadDir, dir)?;
+        if let Ok(name) = entry.file_name().into_string() {
+            names.push(name);
+        }
+    }
+    for name in names.iter().filter(|name| name.starts_with(to)) {
+        let path = dir.join(name);
+        fs::remove_file(&path).context(IoOp::Remove, &path)?;
+    }
+    for name in &names {
+        if let Some(rest) = name.strip_prefix(from) {
+            let from_path = dir.join(name);
+            let to_path = dir.join(format!("{}{}", to, rest));
+            match copy {
+                true => fs::copy(&from_path, to_path)
+                    .map(|_| ())
+                    .context(IoOp::Copy, &from_path)?,
+                false => fs::rename(&from_path, to_path).context(IoOp::Rename, &from_path)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Removes the sidecar file or directory at `path`, if there is one, so it is not mistaken
+/// for the sidecar of the configuration replacing it.
+///
+/// # Errors
+///
+/// Errors if the sidecar cannot be removed.
+fn remove_sidecar(path: &Path) -> Result<()> {
+    if path.is_dir() {
+        fs::remove_dir_all(path).context(IoOp::Remove, path)?;
+    } else if path.exists() {
+        fs::remove_file(path).context(IoOp::Remove, path)?;
+    }
+    Ok(())
+}
@@ -1,33 +1,381 @@
 use crate::{Error, Result};
-use aes_gcm::aead::{Aead, NewAead};
+use aes_gcm::aead::{Aead, NewAead, Payload};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde_json::Value;
+use sha2::Sha256;
+use std::{convert::TryInto, fmt};
+use zeroize::Zeroizing;
 
-/// Encrypts a message with AES-GCM.
-/// The first 12 bytes of the message are the nonce.
-/// The rest of the message is the plaintext.
-pub fn encrypt_data(data: &str, key: [u8; 32]) -> Result<Vec<u8>> {
-    let key = Key::from_slice(&key);
-    let cipher = Aes256Gcm::new(key);
-    let nonce_array: [u8; 12] = rand::random();
-    let nonce = &Nonce::from(nonce_array);
-    let mut encrypted_data = cipher
-        .encrypt(nonce, data.as_ref())
-        .map_err(|_| Error::Encryption)?;
-    let mut final_vec = nonce_array.to_vec();
+/// The magic bytes at the start of every encrypted store file.
+const MAGIC: &[u8; 4] = b"BLND";
+/// The version of the encrypted file format written by [`encrypt_data`].
+const FORMAT_VERSION: u8 = 1;
+/// The length of the salt used when deriving a key from a password.
+const SALT_LEN: usize = 16;
+/// The length of the key check value, which tells a wrong key apart from damaged data.
+const KEY_CHECK_LEN: usize = 8;
+/// The message authenticated with the key to produce the key check value.
+const KEY_CHECK_MESSAGE: &[u8] = b"bland key check";
+
+/// The header flag set when the data was compressed with gzip before encryption.
+#[cfg(feature = "compression")]
+pub const FLAG_GZIP: u8 = 0b01;
+/// The header flag set when the data was compressed with deflate before encryption.
+#[cfg(feature = "compression")]
+pub const FLAG_DEFLATE: u8 = 0b10;
+
+/// The magic bytes marking the start of the signature footer.
+const SIGNATURE_MAGIC: &[u8; 4] = b"BLSG";
+/// The length of the signature footer, the magic bytes followed by the HMAC-SHA256 tag.
+const SIGNATURE_LEN: usize = SIGNATURE_MAGIC.len() + 32;
+
+/// The largest Argon2id memory size in KiB accepted from a header, 1 GiB.
+const MAX_M_COST: u32 = 1 << 20;
+/// The largest number of Argon2id iterations accepted from a header.
+const MAX_T_COST: u32 = 10;
+/// The largest degree of Argon2id parallelism accepted from a header.
+const MAX_P_COST: u32 = 16;
+
+/// The key derivation function identifier for raw keys.
+const KDF_NONE: u8 = 0;
+/// The key derivation function identifier for Argon2id.
+const KDF_ARGON2ID: u8 = 1;
+
+/// The cipher used to encrypt a store.
+///
+/// The cipher is recorded in the header of the store file, so stores are always
+/// decrypted with the cipher they were written with.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CipherSuite {
+    /// AES-256 in Galois/Counter Mode, fast on targets with AES hardware acceleration.
+    #[default]
+    Aes256Gcm,
+    /// XChaCha20-Poly1305, fast in software on targets without AES hardware acceleration.
+    XChaCha20Poly1305,
+}
+
+impl CipherSuite {
+    /// Returns the identifier of the cipher in the file header.
+    fn id(self) -> u8 {
+        match self {
+            CipherSuite::Aes256Gcm => 0,
+            CipherSuite::XChaCha20Poly1305 => 1,
+        }
+    }
+
+    /// Returns the cipher with the given identifier in the file header.
+    fn from_id(id: u8) -> Result<Self> {
+        match id {
+            0 => Ok(CipherSuite::Aes256Gcm),
+            1 => Ok(CipherSuite::XChaCha20Poly1305),
+            _ => Err(Error::InvalidHeader),
+        }
+    }
+
+    /// Returns the length of the nonce used by the cipher.
+    fn nonce_len(self) -> usize {
+        match self {
+            CipherSuite::Aes256Gcm => 12,
+            CipherSuite::XChaCha20Poly1305 => 24,
+        }
+    }
+}
+
+/// The key material used to encrypt a store, which is wiped from memory when dropped.
+#[derive(Clone)]
+pub enum EncryptionKey {
+    /// A raw 32 byte key which is used as is.
+    Raw(Zeroizing<[u8; 32]>),
+    /// A password which is stretched into a key with Argon2id.
+    Password(Zeroizing<String>),
+}
+
+impl fmt::Debug for EncryptionKey {
+    /// Formats the kind of key without the key material.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EncryptionKey::Raw(_) => f.write_str("Raw(<redacted>)"),
+            EncryptionKey::Password(_) => f.write_str("Password(<redacted>)"),
+        }
+    }
+}
+
+/// The parameters used to derive a key from a password with Argon2id.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct KdfParams {
+    /// The memory size in KiB.
+    pub m_cost: u32,
+    /// The number of iterations.
+    pub t_cost: u32,
+    /// The degree of parallelism.
+    pub p_cost: u32,
+    /// The random salt.
+    pub salt: [u8; SALT_LEN],
+}
+
+impl KdfParams {
+    /// Returns the default Argon2id parameters with a new random salt.
+    pub fn generate() -> Self {
+        Self::with_salt(rand::random())
+    }
+
+    /// Returns the default Argon2id parameters with the given salt.
+    fn with_salt(salt: [u8; SALT_LEN]) -> Self {
+        Self {
+            m_cost: Params::DEFAULT_M_COST,
+            t_cost: Params::DEFAULT_T_COST,
+            p_cost: Params::DEFAULT_P_COST,
+            salt,
+        }
+    }
+
+    /// Derives a 32 byte key from a password with Argon2id.
+    pub fn derive_key(&self, password: &str) -> Result<Zeroizing<[u8; 32]>> {
+        let params = Params::new(self.m_cost, self.t_cost, self.p_cost, Some(32))
+            .map_err(|_| Error::InvalidHeader)?;
+        let mut key = Zeroizing::new([0; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &self.salt, &mut *key)
+            .map_err(|_| Error::Encryption)?;
+        Ok(key)
+    }
+}
+
+/// The information stored in the header of encrypted data.
+pub struct Header {
+    /// The flags recording how the data was processed before encryption.
+    pub flags: u8,
+    /// The cipher the data was encrypted with.
+    pub cipher: CipherSuite,
+    /// The key derivation parameters, or `None` if the data was encrypted with a raw key.
+    pub kdf: Option<KdfParams>,
+    /// The key check value, or `None` for the original format written without a header.
+    key_check: Option<[u8; KEY_CHECK_LEN]>,
+}
+
+/// Reads the header of the encrypted data.
+pub fn read_header(data: &[u8]) -> Result<Header> {
+    Ok(split_header(data)?.0)
+}
+
+/// Returns a boolean indicating whether the data starts with the header of encrypted data.
+pub fn has_header(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Encrypts a message with the given cipher.
+///
+/// The message starts with a header made of
+/// * The magic bytes `BLND`.
+/// * The format version.
+/// * The flags byte, recording whether the data was compressed before encryption.
+/// * The cipher identifier.
+/// * The key derivation function identifier, followed by the little endian Argon2id
+///   memory size, iterations and parallelism and the salt if a password is used.
+/// * The key check value, the truncated HMAC-SHA256 of a fixed message with the key.
+///
+/// The nonce follows the header and the rest of the message is the ciphertext, which
+/// authenticates the header as associated data.
+pub fn encrypt_data(
+    data: &[u8],
+    key: &[u8; 32],
+    cipher: CipherSuite,
+    kdf: Option<&KdfParams>,
+    flags: u8,
+) -> Result<Vec<u8>> {
+    let mut final_vec = MAGIC.to_vec();
+    final_vec.extend_from_slice(&[FORMAT_VERSION, flags, cipher.id()]);
+    match kdf {
+        Some(kdf) => {
+            final_vec.push(KDF_ARGON2ID);
+            final_vec.extend_from_slice(&kdf.m_cost.to_le_bytes());
+            final_vec.extend_from_slice(&kdf.t_cost.to_le_bytes());
+            final_vec.extend_from_slice(&kdf.p_cost.to_le_bytes());
+            final_vec.extend_from_slice(&kdf.salt);
+        }
+        None => final_vec.push(KDF_NONE),
+    }
+    final_vec.extend_from_slice(&key_check_mac(key)?.finalize().into_bytes()[..KEY_CHECK_LEN]);
+    let payload = Payload {
+        msg: data,
+        aad: &final_vec,
+    };
+    let (nonce, mut encrypted_data) = match cipher {
+        CipherSuite::Aes256Gcm => {
+            let nonce: [u8; 12] = rand::random();
+            let encrypted_data = Aes256Gcm::new(&Key::from(*key))
+                .encrypt(&Nonce::from(nonce), payload)
+                .map_err(|_| Error::Encryption)?;
+            (nonce.to_vec(), encrypted_data)
+        }
+        CipherSuite::XChaCha20Poly1305 => {
+            let nonce: [u8; 24] = rand::random();
+            let encrypted_data = XChaCha20Poly1305::new(&chacha20poly1305::Key::from(*key))
+                .encrypt(&XNonce::from(nonce), payload)
+                .map_err(|_| Error::Encryption)?;
+            (nonce.to_vec(), encrypted_data)
+        }
+    };
+    final_vec.extend_from_slice(&nonce);
     final_vec.append(&mut encrypted_data);
     Ok(final_vec)
 }
 
-/// Decrypts a message with AES-GCM.
-/// The first 12 bytes of the message are the nonce.
-/// The rest of the message is the ciphertext.
-pub fn decrypt_data(data: Vec<u8>, key: [u8; 32]) -> Result<String> {
-    let key = Key::from_slice(&key);
-    let cipher = Aes256Gcm::new(key);
-    let (nonce_slice, data_slice) = data.split_at(12);
-    let nonce = Nonce::from_slice(nonce_slice);
-    let encrypted_data = cipher
-        .decrypt(nonce, data_slice.as_ref())
-        .map_err(|_| Error::Decryption)?;
-    String::from_utf8(encrypted_data).map_err(|_| Error::Decryption)
+/// Decrypts a message with the cipher recorded in its header.
+///
+/// Messages without a header are treated as the original format, where the
+/// first 12 bytes of the message are the AES-GCM nonce and the rest is the ciphertext.
+///
+/// # Errors
+///
+/// Errors with [`Error::WrongKey`] if the key does not match the header's key check value,
+/// and with [`Error::Decryption`] if the data cannot be decrypted otherwise.
+pub fn decrypt_data(data: &[u8], key: &[u8; 32]) -> Result<Zeroizing<Vec<u8>>> {
+    let (header, body) = split_header(data)?;
+    if let Some(key_check) = header.key_check {
+        key_check_mac(key)?
+            .verify_truncated_left(&key_check)
+            .map_err(|_| Error::WrongKey)?;
+    }
+    if body.len() < header.cipher.nonce_len() {
+        return Err(Error::Decryption);
+    }
+    let (nonce, encrypted_data) = body.split_at(header.cipher.nonce_len());
+    let payload = Payload {
+        msg: encrypted_data,
+        aad: &data[..data.len() - body.len()],
+    };
+    match header.cipher {
+        CipherSuite::Aes256Gcm => {
+            let nonce: [u8; 12] = nonce.try_into().map_err(|_| Error::Decryption)?;
+            Aes256Gcm::new(&Key::from(*key)).decrypt(&Nonce::from(nonce), payload)
+        }
+        CipherSuite::XChaCha20Poly1305 => {
+            let nonce: [u8; 24] = nonce.try_into().map_err(|_| Error::Decryption)?;
+            XChaCha20Poly1305::new(&chacha20poly1305::Key::from(*key))
+                .decrypt(&XNonce::from(nonce), payload)
+        }
+    }
+    .map(Zeroizing::new)
+    .map_err(|_| Error::Decryption)
+}
+
+/// Splits the encrypted data into its header and the remaining
+/// `nonce || ciphertext` body.
+///
+/// # Errors
+///
+/// Errors if
+/// * The data is plain JSON rather than encrypted data.
+/// * The header is truncated or has an unknown version, cipher or key derivation function.
+/// * The key derivation parameters are above the limits, which would exhaust memory or time
+///   while deriving the key.
+fn split_header(data: &[u8]) -> Result<(Header, &[u8])> {
+    if !data.starts_with(MAGIC) {
+        if serde_json::from_slice::<Value>(data).is_ok() {
+            return Err(Error::NotEncrypted);
+        }
+        let header = Header {
+            flags: 0,
+            cipher: CipherSuite::Aes256Gcm,
+            kdf: None,
+            key_check: None,
+        };
+        return Ok((header, data));
+    }
+    let (flags, cipher, rest) = match &data[MAGIC.len()..] {
+        [FORMAT_VERSION, flags, cipher, rest @ ..] => (*flags, *cipher, rest),
+        _ => return Err(Error::InvalidHeader),
+    };
+    let cipher = CipherSuite::from_id(cipher)?;
+    let (kdf, rest) = match rest {
+        [KDF_NONE, rest @ ..] => (None, rest),
+        [KDF_ARGON2ID, rest @ ..] => {
+            let (m_cost, rest) = split_array(rest)?;
+            let (t_cost, rest) = split_array(rest)?;
+            let (p_cost, rest) = split_array(rest)?;
+            let (salt, rest) = split_array(rest)?;
+            let kdf = KdfParams {
+                m_cost: u32::from_le_bytes(m_cost),
+                t_cost: u32::from_le_bytes(t_cost),
+                p_cost: u32::from_le_bytes(p_cost),
+                salt,
+            };
+            if kdf.m_cost > MAX_M_COST || kdf.t_cost > MAX_T_COST || kdf.p_cost > MAX_P_COST {
+                return Err(Error::InvalidHeader);
+            }
+            (Some(kdf), rest)
+        }
+        _ => return Err(Error::InvalidHeader),
+    };
+    let (key_check, body) = split_array(rest)?;
+    let key_check = Some(key_check);
+    let header = Header {
+        flags,
+        cipher,
+        kdf,
+        key_check,
+    };
+    Ok((header, body))
+}
+
+/// Returns the HMAC-SHA256 of the key check message with the key, which truncated is the
+/// key check value.
+fn key_check_mac(key: &[u8; 32]) -> Result<Hmac<Sha256>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| Error::Encryption)?;
+    mac.update(KEY_CHECK_MESSAGE);
+    Ok(mac)
+}
+
+/// Splits a fixed size array off the front of the data.
+fn split_array<const N: usize>(data: &[u8]) -> Result<([u8; N], &[u8])> {
+    if data.len() < N {
+        return Err(Error::InvalidHeader);
+    }
+    let (array, rest) = data.split_at(N);
+    Ok((array.try_into().map_err(|_| Error::InvalidHeader)?, rest))
+}
+
+/// Appends a footer made of the magic bytes `BLSG` and the HMAC-SHA256 tag of the data.
+pub fn sign_data(mut data: Vec<u8>, key: &[u8]) -> Result<Vec<u8>> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| Error::Encryption)?;
+    mac.update(&data);
+    let tag = mac.finalize().into_bytes();
+    data.extend_from_slice(SIGNATURE_MAGIC);
+    data.extend_from_slice(&tag);
+    Ok(data)
+}
+
+/// Verifies and removes the signature footer of the data.
+///
+/// # Errors
+///
+/// Errors if the footer is missing or the tag does not match the data.
+pub fn verify_signature(mut data: Vec<u8>, key: &[u8]) -> Result<Vec<u8>> {
+    if !has_signature(&data) {
+        return Err(Error::SignatureInvalid);
+    }
+    let (payload, footer) = data.split_at(data.len() - SIGNATURE_LEN);
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).map_err(|_| Error::SignatureInvalid)?;
+    mac.update(payload);
+    mac.verify_slice(&footer[SIGNATURE_MAGIC.len()..])
+        .map_err(|_| Error::SignatureInvalid)?;
+    data.truncate(data.len() - SIGNATURE_LEN);
+    Ok(data)
+}
+
+/// Removes the signature footer of the data, if it has one, without verifying it.
+pub fn strip_signature(mut data: Vec<u8>) -> Vec<u8> {
+    if has_signature(&data) {
+        data.truncate(data.len() - SIGNATURE_LEN);
+    }
+    data
+}
+
+/// Returns a boolean indicating whether the data ends with a signature footer.
+fn has_signature(data: &[u8]) -> bool {
+    data.len() >= SIGNATURE_LEN && data[data.len() - SIGNATURE_LEN..].starts_with(SIGNATURE_MAGIC)
 }
@@ -0,0 +1,82 @@
+use crate::{Error, Result, Store};
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+use serde_json::Value;
+use std::fmt::Display;
+
+impl Store {
+    /// Returns the timestamp at the given path, stored as an RFC 3339 string such as
+    /// `2024-05-01T12:00:00+02:00`, keeping its offset.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// use chrono::{TimeZone, Utc};
+    ///
+    /// let store = Store::new("datetime-app").unwrap();
+    /// let checked = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    /// store.set_datetime("updates.last_checked", checked).unwrap();
+    /// assert_eq!(store.get("updates.last_checked").unwrap().unwrap(), "2024-05-01T12:00:00+00:00");
+    /// assert_eq!(store.get_datetime("updates.last_checked").unwrap().unwrap(), checked);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not an RFC 3339 string, with [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_datetime(&self, path: &str) -> Result<Option<DateTime<FixedOffset>>> {
+        let timestamp = match self.get(path)? {
+            Some(Value::String(timestamp)) => timestamp,
+            Some(_) => return Err(invalid(path, "expected an RFC 3339 string".to_string())),
+            None => return Ok(None),
+        };
+        DateTime::parse_from_rfc3339(&timestamp)
+            .map(Some)
+            .map_err(|e| invalid(path, e.to_string()))
+    }
+
+    /// Sets the timestamp at the given path as an RFC 3339 string, keeping its offset.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn set_datetime<Tz>(&self, path: &str, datetime: DateTime<Tz>) -> Result<()>
+    where
+        Tz: TimeZone,
+        Tz::Offset: Display,
+    {
+        self.set(path, datetime.to_rfc3339())
+    }
+
+    /// Sets the timestamp at the given path to the current time in UTC, such as when
+    /// updates were last checked for.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn touch(&self, path: &str) -> Result<()> {
+        self.set_datetime(path, Utc::now())
+    }
+}
+
+fn invalid(path: &str, reason: String) -> Error {
+    Error::InvalidValue {
+        path: path.to_string(),
+        reason,
+    }
+}
@@ -0,0 +1,111 @@
+use crate::{ChangeSource, Result, Store};
+use serde_json::Value;
+
+impl Store {
+    /// Sets whether mutations are deferred, deferred writes are disabled by default.
+    ///
+    /// While deferred, `set`, `delete` and `undo` only update the document held in memory,
+    /// which is persisted by [`Store::flush`] or when the store is dropped.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("deferred-app").unwrap();
+    /// store.set_deferred(true);
+    /// for i in 0..100 {
+    ///     store.set("counter", i).unwrap();
+    /// }
+    /// assert!(store.is_dirty());
+    /// store.flush().unwrap();
+    /// assert!(!store.is_dirty());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_deferred(&mut self, deferred: bool) {
+        self.deferred = deferred;
+    }
+
+    pub fn get_deferred(&self) -> bool {
+        self.deferred
+    }
+
+    /// Returns whether there are deferred changes that have not been written to the store file.
+    pub fn is_dirty(&self) -> bool {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_some()
+    }
+
+    /// Writes any deferred changes to the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file fails to be written to, the changes are kept in memory.
+    pub fn flush(&self) -> Result<()> {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        let value = match pending {
+            Some(value) => value,
+            None => return Ok(()),
+        };
+        let result = self
+            .lock_store_file()
+            .and_then(|_lock| self.write_value(value.clone()));
+        if result.is_err() {
+            self.defer_value(value);
+        }
+        result
+    }
+
+    /// Writes the given contents of the store, or holds them in memory while writes are deferred.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store would exceed its maximum size or the store file fails to be
+    /// written to.
+    pub(crate) fn write_or_defer(&self, value: Value) -> Result<()> {
+        self.check_max_size(&value)?;
+        self.record_metrics(|metrics| metrics.record_set());
+        match self.deferred {
+            true => {
+                self.publish_changes(&value, ChangeSource::Internal);
+                self.defer_value(value);
+                self.defer_generation();
+                Ok(())
+            }
+            false => self.write_value(value),
+        }
+    }
+
+    /// Holds the given contents of the store in memory until the next flush.
+    pub(crate) fn defer_value(&self, value: Value) {
+        *self.pending.lock().unwrap_or_else(|e| e.into_inner()) = Some(value);
+    }
+
+    /// Returns a copy of the deferred contents of the store, if there are any.
+    pub(crate) fn get_pending_value(&self) -> Option<Value> {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Discards the deferred contents of the store, after the store file was written to.
+    pub(crate) fn clear_pending_value(&self) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+    }
+}
+
+impl Drop for Store {
+    /// Writes any deferred changes, errors are ignored as they cannot be reported.
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
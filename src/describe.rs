@@ -0,0 +1,122 @@
+use crate::Store;
+use std::fmt::{self, Write};
+
+/// Stands in for secrets in the output of [`Store`]'s `Debug` implementation.
+#[cfg(feature = "crypto")]
+const REDACTED: &str = "<redacted>";
+
+impl fmt::Debug for Store {
+    /// Formats the store's paths and settings. Encryption keys, passwords and
+    /// signing keys are redacted, so the store can be logged safely.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut debug = f.debug_struct("Store");
+        debug
+            .field("store_path", &self.get_store_path())
+            .field("kind", &self.kind)
+            .field("profile", &self.profile)
+            .field("read_only", &self.read_only)
+            .field("create_missing", &self.create_missing)
+            .field("permissions", &self.permissions)
+            .field("durability", &self.durability)
+            .field("pretty", &self.pretty)
+            .field("sort_keys", &self.sort_keys)
+            .field("preserve_formatting", &self.preserve_formatting)
+            .field("coerce_types", &self.coerce_types)
+            .field("includes", &self.includes)
+            .field("environment", &self.environment)
+            .field("interpolation", &self.interpolation)
+            .field("checksum", &self.checksum)
+            .field("deferred", &self.deferred)
+            .field("journal", &self.journal)
+            .field("cache", &self.cache_policy)
+            .field("backup", &self.backup)
+            .field("max_size", &self.max_size)
+            .field("undo_depth", &self.undo_depth);
+        #[cfg(feature = "history")]
+        debug.field("history", &self.history);
+        #[cfg(feature = "crypto")]
+        debug
+            .field(
+                "encryption_key",
+                &self.encryption_key.as_ref().map(|_| REDACTED),
+            )
+            .field("cipher_suite", &self.cipher_suite)
+            .field("encrypted_paths", &self.encrypted_paths)
+            .field("signing_key", &self.signing_key.as_ref().map(|_| REDACTED));
+        #[cfg(feature = "electron")]
+        debug.field("electron_compat", &self.get_electron_compat());
+        #[cfg(feature = "mmap")]
+        debug.field("mmap", &self.mmap);
+        #[cfg(feature = "sharding")]
+        debug.field("sharded", &self.sharded);
+        #[cfg(feature = "compression")]
+        debug
+            .field("compressed", &self.compressed)
+            .field("compression_algorithm", &self.compression_algorithm)
+            .field("compression_level", &self.compression_level);
+        debug.finish_non_exhaustive()
+    }
+}
+
+impl Store {
+    /// Returns a report of the store's location and settings for diagnostics, one
+    /// `name: value` pair per line. Secrets are never included, only whether they are set.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("describe-app").unwrap();
+    /// let description = store.describe();
+    /// assert!(description.contains("read only: false"));
+    /// ```
+    pub fn describe(&self) -> String {
+        let mut description = String::new();
+        let mut line = |name: &str, value: &dyn fmt::Display| {
+            // Writing to a string cannot fail.
+            let _ = writeln!(description, "{}: {}", name, value);
+        };
+        line("store path", &self.get_store_path().display());
+        line("exists", &self.get_store_path().exists());
+        line("kind", &format_args!("{:?}", self.kind));
+        line("profile", &self.profile.as_deref().unwrap_or("none"));
+        line("dir env", &self.dir_env.as_deref().unwrap_or("none"));
+        line("read only", &self.read_only);
+        line("create missing", &self.create_missing);
+        line("permissions", &format_args!("{:?}", self.permissions));
+        line("durability", &format_args!("{:?}", self.durability));
+        line("pretty", &format_args!("{:?}", self.pretty));
+        line("checksum", &self.checksum);
+        line("deferred", &self.deferred);
+        line("journal", &self.journal);
+        line("cache", &format_args!("{:?}", self.cache_policy));
+        line("backup", &format_args!("{:?}", self.backup));
+        line("undo depth", &self.undo_depth);
+        #[cfg(feature = "history")]
+        line("history", &self.history);
+        #[cfg(feature = "crypto")]
+        {
+            let encryption = match &self.encryption_key {
+                Some(crate::EncryptionKey::Raw(_)) => "raw key",
+                Some(crate::EncryptionKey::Password(_)) => "password",
+                None => "none",
+            };
+            line("encryption", &encryption);
+            line("cipher suite", &format_args!("{:?}", self.cipher_suite));
+            line("encrypted paths", &self.encrypted_paths.join(", "));
+            line("signed", &self.signing_key.is_some());
+        }
+        #[cfg(feature = "compression")]
+        {
+            line("compressed", &self.compressed);
+            line(
+                "compression",
+                &format_args!(
+                    "{:?} level {}",
+                    self.compression_algorithm, self.compression_level
+                ),
+            );
+        }
+        description
+    }
+}
@@ -0,0 +1,221 @@
+use crate::{
+    error::IoContext,
+    path::{env_name, join_path},
+    Error, IoOp, Result, Store,
+};
+use json_dotpath::DotPaths;
+use serde_json::Value;
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+impl Store {
+    /// Returns every leaf of the store as an environment variable, for handing the
+    /// configuration to a child process with [`Command::envs`](std::process::Command::envs).
+    ///
+    /// A variable's name is the prefix followed by the keys and indices of the value's path
+    /// in upper case, joined by `_`, with any character other than letters and digits
+    /// replaced by `_`. Strings are exported as they are, `null` as an empty string, and any
+    /// other value as JSON.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("export-env-app").unwrap();
+    /// store.set("db.host", "localhost").unwrap();
+    /// store.set("db.port", 5432).unwrap();
+    /// let vars = store.export_env("APP_").unwrap();
+    /// assert_eq!(vars[0], ("APP_DB_HOST".to_string(), "localhost".to_string()));
+    /// assert_eq!(vars[1], ("APP_DB_PORT".to_string(), "5432".to_string()));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn export_env(&self, prefix: &str) -> Result<Vec<(String, String)>> {
+        let mut vars = Vec::new();
+        match self.to_value()? {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    flatten_env(format!("{}{}", prefix, env_name(&key)), value, &mut vars);
+                }
+            }
+            value => flatten_env(prefix.to_string(), value, &mut vars),
+        }
+        Ok(vars)
+    }
+
+    /// Writes the variables returned by [`export_env`](Store::export_env) to a `.env` file
+    /// at the given path, replacing it if it exists. Values are double quoted, with
+    /// backslashes, quotes, dollar signs and newlines escaped.
+    ///
+    /// The file is created with the store's [permissions](Store::set_permissions), as it may
+    /// hold secrets.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The file cannot be written to.
+    pub fn export_dotenv(&self, path: &Path, prefix: &str) -> Result<()> {
+        let mut contents = String::new();
+        for (name, value) in self.export_env(prefix)? {
+            contents.push_str(&format!("{}=\"{}\"\n", name, escape_dotenv(&value)));
+        }
+        let mut file = self
+            .file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+            .context(IoOp::Create, path)?;
+        file.write_all(contents.as_bytes())
+            .context(IoOp::Write, path)
+    }
+
+    /// Reads a `.env` file and sets each of its variables as a string under `target_prefix`
+    /// in a single write, returning the number of variables imported. An empty prefix
+    /// imports the variables at the top level of the store.
+    ///
+    /// Blank lines and lines starting with `#` are skipped, as is an `export ` before a
+    /// variable. Values may be unquoted, with a ` #` starting a comment, single quoted and
+    /// taken literally, or double quoted with `\n`, `\r`, `\t`, `\"`, `\$` and `\\`
+    /// escapes. Variables are not expanded.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("import-dotenv-app").unwrap();
+    /// store.init_store().unwrap();
+    /// let dotenv = store.get_store_dir_path().join(".env");
+    /// std::fs::write(&dotenv, "# Database\nexport DB_HOST=localhost\nDB_NAME='app'\n").unwrap();
+    /// assert_eq!(store.import_dotenv(&dotenv, "env").unwrap(), 2);
+    /// assert_eq!(store.get("env.DB_HOST").unwrap().unwrap(), "localhost");
+    /// assert_eq!(store.get("env.DB_NAME").unwrap().unwrap(), "app");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The file cannot be read or a line cannot be parsed.
+    /// * The store fails to be created or written to.
+    /// * `target_prefix` is not a valid dot path.
+    pub fn import_dotenv(&self, path: &Path, target_prefix: &str) -> Result<usize> {
+        let contents = fs::read_to_string(path).context(IoOp::Read, path)?;
+        let mut vars = Vec::new();
+        for (i, line) in contents.lines().enumerate() {
+            let var = parse_dotenv_line(line).map_err(|reason| Error::Io {
+                path: path.to_path_buf(),
+                op: IoOp::Read,
+                source: io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {}", i + 1, reason),
+                ),
+            })?;
+            vars.extend(var);
+        }
+        let count = vars.len();
+        self.modify(|parsed_json| {
+            for (name, value) in vars {
+                parsed_json.dot_set(&join_path(target_prefix, &name), value)?;
+            }
+            Ok(count)
+        })
+    }
+}
+
+/// Parses a line of a `.env` file into a variable's name and value, `None` for blank
+/// lines and comments.
+fn parse_dotenv_line(line: &str) -> std::result::Result<Option<(String, String)>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (name, value) = line
+        .split_once('=')
+        .ok_or_else(|| "expected NAME=value".to_string())?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("missing variable name".to_string());
+    }
+    let value = value.trim_start();
+    let value = if let Some(quoted) = value.strip_prefix('\'') {
+        let end = quoted
+            .find('\'')
+            .ok_or_else(|| "unterminated single quote".to_string())?;
+        quoted[..end].to_string()
+    } else if let Some(quoted) = value.strip_prefix('"') {
+        unescape_dotenv(quoted)?
+    } else {
+        let end = value.find(" #").unwrap_or(value.len());
+        value[..end].trim_end().to_string()
+    };
+    Ok(Some((name.to_string(), value)))
+}
+
+/// Unescapes a double quoted `.env` value, given from after its opening quote.
+fn unescape_dotenv(quoted: &str) -> std::result::Result<String, String> {
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Ok(value),
+            '\\' => match chars.next() {
+                Some('n') => value.push('\n'),
+                Some('r') => value.push('\r'),
+                Some('t') => value.push('\t'),
+                Some(c) => value.push(c),
+                None => break,
+            },
+            c => value.push(c),
+        }
+    }
+    Err("unterminated double quote".to_string())
+}
+
+/// Collects every leaf below `value` as an environment variable into `vars`.
+fn flatten_env(name: String, value: Value, vars: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten_env(format!("{}_{}", name, env_name(&key)), value, vars);
+            }
+        }
+        Value::Array(array) if !array.is_empty() => {
+            for (i, value) in array.into_iter().enumerate() {
+                flatten_env(format!("{}_{}", name, i), value, vars);
+            }
+        }
+        Value::String(s) => vars.push((name, s)),
+        Value::Null => vars.push((name, String::new())),
+        value => vars.push((name, value.to_string())),
+    }
+}
+
+/// Escapes a value for a double quoted `.env` value.
+fn escape_dotenv(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '$' => escaped.push_str("\\$"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
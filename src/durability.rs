@@ -0,0 +1,94 @@
+use crate::{error::IoContext, IoOp, Result, Store};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// How far a write is flushed to disk before it returns.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Durability {
+    /// The store file is atomically replaced, but may be lost if the system crashes
+    /// before the operating system flushes it.
+    #[default]
+    Fast,
+    /// The new store file is flushed to disk before it replaces the old one.
+    Fsync,
+    /// As [`Durability::Fsync`], and the store directory is flushed after the rename,
+    /// so the replacement itself survives a power failure.
+    FsyncDir,
+}
+
+impl Store {
+    /// Sets how far writes are flushed to disk before they return,
+    /// defaults to [`Durability::Fast`].
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Durability, Store};
+    /// let mut store = Store::new("durability-app").unwrap();
+    /// store.set_durability(Durability::FsyncDir);
+    /// store.set("theme", "dark").unwrap();
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_durability(&mut self, durability: Durability) {
+        self.durability = durability;
+    }
+
+    pub fn get_durability(&self) -> Durability {
+        self.durability
+    }
+
+    /// Flushes a newly written file to disk unless the durability is [`Durability::Fast`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the file cannot be flushed.
+    pub(crate) fn sync_file(&self, file: &File, path: &Path) -> Result<()> {
+        match self.durability {
+            Durability::Fast => Ok(()),
+            Durability::Fsync | Durability::FsyncDir => file.sync_all().context(IoOp::Sync, path),
+        }
+    }
+
+    /// Atomically replaces the file at the given path, other than the store file, with the
+    /// data by writing to a temporary file next to it and renaming it over the file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the temporary file cannot be written or renamed.
+    pub(crate) fn replace_file(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let mut temp_path = OsString::from(path);
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        let mut temp_file = self
+            .file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)
+            .context(IoOp::Create, &temp_path)?;
+        temp_file.write_all(data).context(IoOp::Write, &temp_path)?;
+        self.sync_file(&temp_file, &temp_path)?;
+        fs::rename(&temp_path, path).context(IoOp::Rename, &temp_path)
+    }
+
+    /// Flushes the store directory to disk if the durability is [`Durability::FsyncDir`],
+    /// making renames within it durable. Directories cannot be flushed on Windows,
+    /// where this does nothing.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the directory cannot be opened or flushed.
+    pub(crate) fn sync_store_dir(&self) -> Result<()> {
+        if self.durability != Durability::FsyncDir || cfg!(windows) {
+            return Ok(());
+        }
+        let store_dir_path = self.get_store_dir_path();
+        File::open(&store_dir_path)
+            .and_then(|dir| dir.sync_all())
+            .context(IoOp::Sync, &store_dir_path)
+    }
+}
@@ -0,0 +1,456 @@
+use crate::{lenient, Result, Store};
+use serde_json::{Map, Value};
+
+impl Store {
+    /// Sets whether writes keep the comments and formatting of the store file, disabled by
+    /// default. Only the values which changed are rewritten, so a hand maintained file isn't
+    /// reformatted by a single [`set`](Store::set).
+    ///
+    /// New keys are added after the last key of their object, indented like it, and removed
+    /// keys are cut out along with the rest of their line. Arrays are rewritten whole when
+    /// any of their elements changes, as are objects which lose all their keys and gain
+    /// new ones. Comments can only be read with the `json5` feature.
+    ///
+    /// Stores which are compressed, encrypted, signed, checksummed, sharded or in
+    /// electron-store's format are always rewritten whole, as is a store file which cannot be
+    /// edited safely.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("preserve-formatting-app").unwrap();
+    /// store.set_preserve_formatting(true);
+    /// store.init_store().unwrap();
+    /// let path = store.get_store_path();
+    /// std::fs::write(&path, "{\n    \"theme\": \"dark\",   \"size\": 12\n}\n").unwrap();
+    /// store.set("size", 14).unwrap();
+    /// store.set("font", "mono").unwrap();
+    /// assert_eq!(
+    ///     std::fs::read_to_string(&path).unwrap(),
+    ///     "{\n    \"theme\": \"dark\",   \"size\": 14,\n    \"font\": \"mono\"\n}\n"
+    /// );
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_preserve_formatting(&mut self, preserve_formatting: bool) {
+        self.preserve_formatting = preserve_formatting;
+    }
+
+    pub fn get_preserve_formatting(&self) -> bool {
+        self.preserve_formatting
+    }
+
+    /// Returns the store file with only the values which differ from `value` rewritten, or
+    /// `None` if the store is written whole because formatting is not preserved, the file
+    /// is not plain JSON, or it cannot be edited.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the changed values cannot be serialized.
+    pub(crate) fn edit_store_file(&self, value: &Value) -> Result<Option<Vec<u8>>> {
+        if !self.edits_in_place() {
+            return Ok(None);
+        }
+        let data = match self.read_store_file() {
+            Ok(data) => data,
+            Err(_) => return Ok(None),
+        };
+        let text = match std::str::from_utf8(&data) {
+            Ok(text) => text,
+            Err(_) => return Ok(None),
+        };
+        let root = match Scanner::new(text).parse_document() {
+            Some(root) => root,
+            None => return Ok(None),
+        };
+        let mut edits = Vec::new();
+        self.diff_node(text, &root, value, &mut edits)?;
+        // Later edits are applied first so the positions of earlier ones stay valid, and a
+        // removal is applied before an insertion at its start.
+        edits.sort_by_key(|edit| std::cmp::Reverse((edit.start, edit.end)));
+        let mut edited = text.to_string();
+        for edit in edits {
+            edited.replace_range(edit.start..edit.end, &edit.text);
+        }
+        // Anything the edits got wrong is written whole instead.
+        match lenient::from_slice(edited.as_bytes()) {
+            Ok(parsed) if parsed == *value => Ok(Some(edited.into_bytes())),
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns a boolean indicating whether the store file is plain JSON which can be
+    /// edited in place.
+    fn edits_in_place(&self) -> bool {
+        if !self.preserve_formatting || self.checksum {
+            return false;
+        }
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return false;
+        }
+        #[cfg(feature = "crypto")]
+        if self.get_whole_store_key().is_some() || self.signing_key.is_some() {
+            return false;
+        }
+        #[cfg(feature = "electron")]
+        if self.electron.is_some() {
+            return false;
+        }
+        #[cfg(feature = "sharding")]
+        if self.shards_apply() {
+            return false;
+        }
+        true
+    }
+
+    /// Collects the edits turning the value of `node` into `value`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a changed value cannot be serialized.
+    fn diff_node(
+        &self,
+        text: &str,
+        node: &Node,
+        value: &Value,
+        edits: &mut Vec<Edit>,
+    ) -> Result<()> {
+        let (members, map) = match (&node.kind, value) {
+            (NodeKind::Object(members), Value::Object(map)) => (members, map),
+            _ if node.to_value() == *value => return Ok(()),
+            _ => return self.replace_node(text, node, value, edits),
+        };
+        let kept: Vec<usize> = (0..members.len())
+            .filter(|&i| map.contains_key(&members[i].key))
+            .collect();
+        let last_kept = match kept.last() {
+            Some(&last_kept) => last_kept,
+            None if node.to_value() == *value => return Ok(()),
+            // An emptied object keeps the comments between its braces.
+            None if map.is_empty() => {
+                edits.extend(members.iter().map(|member| remove_member(text, member)));
+                return Ok(());
+            }
+            None => return self.replace_node(text, node, value, edits),
+        };
+        for &i in &kept {
+            self.diff_node(text, &members[i].value, &map[&members[i].key], edits)?;
+        }
+        // Removed members after the last kept one go along with the comma before them.
+        let trailing_removed = last_kept + 1 < members.len();
+        if trailing_removed {
+            edits.push(Edit {
+                start: members[last_kept].value.end,
+                end: members[members.len() - 1].value.end,
+                text: String::new(),
+            });
+        }
+        for i in (0..last_kept).filter(|i| !kept.contains(i)) {
+            edits.push(remove_member(text, &members[i]));
+        }
+        let added: Vec<(&String, &Value)> = map
+            .iter()
+            .filter(|(key, _)| !members.iter().any(|member| &member.key == *key))
+            .collect();
+        if added.is_empty() {
+            return Ok(());
+        }
+        let anchor = &members[last_kept];
+        let multiline = text[node.start..members[0].start].contains('\n');
+        let indent = line_indent(text, anchor.start);
+        let separator = match multiline {
+            true => format!("\n{}", indent),
+            // Members on one line are spaced like the last one.
+            false => {
+                let before = &text[..anchor.start];
+                before[before.trim_end().len()..].to_string()
+            }
+        };
+        let colon = &text[anchor.key_end..anchor.value.start];
+        let mut inserted = String::new();
+        for (key, value) in added {
+            inserted.push(',');
+            inserted.push_str(&separator);
+            inserted.push_str(&serde_json::to_string(key)?);
+            inserted.push_str(colon);
+            inserted.push_str(&self.format_value(value, indent)?);
+        }
+        edits.push(Edit {
+            start: anchor.value.end,
+            end: anchor.value.end,
+            text: inserted,
+        });
+        Ok(())
+    }
+
+    /// Collects the edit replacing the value of `node` with `value`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized.
+    fn replace_node(
+        &self,
+        text: &str,
+        node: &Node,
+        value: &Value,
+        edits: &mut Vec<Edit>,
+    ) -> Result<()> {
+        edits.push(Edit {
+            start: node.start,
+            end: node.end,
+            text: self.format_value(value, line_indent(text, node.start))?,
+        });
+        Ok(())
+    }
+
+    /// Serializes a value the way the store file is written, with every line after the
+    /// first indented by `indent`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized.
+    fn format_value(&self, value: &Value, indent: &str) -> Result<String> {
+        let data = self.serialize_value(value)?;
+        let formatted = String::from_utf8_lossy(&data);
+        Ok(formatted
+            .trim_end_matches('\n')
+            .replace('\n', &format!("\n{}", indent)))
+    }
+}
+
+/// A replacement of the text between two positions of the store file.
+struct Edit {
+    start: usize,
+    end: usize,
+    text: String,
+}
+
+/// A value in the store file along with where it is.
+struct Node {
+    start: usize,
+    end: usize,
+    kind: NodeKind,
+}
+
+enum NodeKind {
+    /// An object, whose members are edited individually.
+    Object(Vec<Member>),
+    /// Any other value, which is replaced whole when it changes.
+    Other(Value),
+}
+
+/// A key and value of an object in the store file.
+struct Member {
+    /// Where the key starts.
+    start: usize,
+    key: String,
+    /// Where the key ends.
+    key_end: usize,
+    value: Node,
+}
+
+impl Node {
+    fn to_value(&self) -> Value {
+        match &self.kind {
+            NodeKind::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|member| (member.key.clone(), member.value.to_value()))
+                    .collect::<Map<_, _>>(),
+            ),
+            NodeKind::Other(value) => value.clone(),
+        }
+    }
+}
+
+/// Returns the edit cutting a member out of the store file, along with the comma after it,
+/// and its line if nothing else is left on it.
+fn remove_member(text: &str, member: &Member) -> Edit {
+    let line_start = text[..member.start].rfind('\n').map_or(0, |i| i + 1);
+    let mut start = member.start;
+    if text[line_start..start].trim().is_empty() {
+        start = line_start;
+    }
+    let mut end = member.value.end;
+    end += text[end..].len() - text[end..].trim_start().len();
+    if text[end..].starts_with(',') {
+        end += 1;
+    }
+    let rest = &text[end..];
+    let line_end = rest.find('\n').map_or(rest.len(), |i| i + 1);
+    let trailing = rest[..line_end].trim();
+    if start == line_start && (trailing.is_empty() || trailing.starts_with("//")) {
+        end += line_end;
+    }
+    Edit {
+        start,
+        end,
+        text: String::new(),
+    }
+}
+
+/// Returns the whitespace the line containing the position starts with.
+fn line_indent(text: &str, position: usize) -> &str {
+    let line_start = text[..position].rfind('\n').map_or(0, |i| i + 1);
+    let line = &text[line_start..position];
+    &line[..line.len() - line.trim_start_matches([' ', '\t']).len()]
+}
+
+/// Scans a store file written as JSON with comments and trailing commas, recording where
+/// each value is.
+struct Scanner<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Scanner { text, pos: 0 }
+    }
+
+    /// Scans the whole file, `None` if it is not a single value.
+    fn parse_document(&mut self) -> Option<Node> {
+        let root = self.parse_value()?;
+        self.skip_trivia()?;
+        (self.pos == self.text.len()).then_some(root)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    /// Skips whitespace and comments, `None` if a comment is not terminated.
+    fn skip_trivia(&mut self) -> Option<()> {
+        loop {
+            let rest = &self.text[self.pos..];
+            let trimmed = rest.trim_start();
+            self.pos += rest.len() - trimmed.len();
+            if trimmed.starts_with("//") {
+                self.pos += trimmed.find('\n').unwrap_or(trimmed.len());
+            } else if let Some(comment) = trimmed.strip_prefix("/*") {
+                self.pos += comment.find("*/")? + 4;
+            } else {
+                return Some(());
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> Option<Node> {
+        self.skip_trivia()?;
+        let start = self.pos;
+        match self.peek()? {
+            b'{' => return self.parse_object(),
+            b'[' => return self.parse_array(),
+            b'"' => self.skip_string()?,
+            _ => {
+                let rest = &self.text[start..];
+                self.pos += rest
+                    .find(|c: char| c.is_whitespace() || ",]}/".contains(c))
+                    .unwrap_or(rest.len());
+            }
+        }
+        let value = serde_json::from_str(&self.text[start..self.pos]).ok()?;
+        Some(Node {
+            start,
+            end: self.pos,
+            kind: NodeKind::Other(value),
+        })
+    }
+
+    fn parse_object(&mut self) -> Option<Node> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut members: Vec<Member> = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek()? == b'}' {
+                break;
+            }
+            let key_start = self.pos;
+            if self.peek()? != b'"' {
+                return None;
+            }
+            self.skip_string()?;
+            let key_end = self.pos;
+            let key: String = serde_json::from_str(&self.text[key_start..key_end]).ok()?;
+            self.skip_trivia()?;
+            if self.peek()? != b':' {
+                return None;
+            }
+            self.pos += 1;
+            let value = self.parse_value()?;
+            // Duplicate keys cannot be edited unambiguously.
+            if members.iter().any(|member| member.key == key) {
+                return None;
+            }
+            members.push(Member {
+                start: key_start,
+                key,
+                key_end,
+                value,
+            });
+            if !self.skip_separator(b'}')? {
+                break;
+            }
+        }
+        self.pos += 1;
+        Some(Node {
+            start,
+            end: self.pos,
+            kind: NodeKind::Object(members),
+        })
+    }
+
+    fn parse_array(&mut self) -> Option<Node> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut values = Vec::new();
+        loop {
+            self.skip_trivia()?;
+            if self.peek()? == b']' {
+                break;
+            }
+            values.push(self.parse_value()?.to_value());
+            if !self.skip_separator(b']')? {
+                break;
+            }
+        }
+        self.pos += 1;
+        Some(Node {
+            start,
+            end: self.pos,
+            kind: NodeKind::Other(Value::Array(values)),
+        })
+    }
+
+    /// Skips the comma after a member or element, returning `false` at the closing bracket
+    /// instead, and `None` if neither follows.
+    fn skip_separator(&mut self, close: u8) -> Option<bool> {
+        self.skip_trivia()?;
+        match self.peek()? {
+            b',' => {
+                self.pos += 1;
+                Some(true)
+            }
+            c if c == close => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Skips a string starting at the current position, `None` if it is not terminated.
+    fn skip_string(&mut self) -> Option<()> {
+        let bytes = self.text.as_bytes();
+        self.pos += 1;
+        loop {
+            match bytes.get(self.pos)? {
+                b'\\' => self.pos += 2,
+                b'"' => {
+                    self.pos += 1;
+                    return Some(());
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{Error, Result, Store};
+use aes::{
+    cipher::{block_padding::Pkcs7, BlockDecryptMut, BlockEncryptMut, KeyIvInit},
+    Aes256,
+};
+use serde::Serialize;
+use serde_json::{ser::PrettyFormatter, Serializer};
+use sha2::Sha512;
+use std::io::Write;
+use zeroize::Zeroizing;
+
+/// The length of the initialization vector at the start of an encrypted store.
+const IV_LEN: usize = 16;
+/// The PBKDF2 iterations deriving the key from the encryption key.
+const PBKDF2_ROUNDS: u32 = 10_000;
+
+/// The settings of a store in electron-store and conf's format.
+#[derive(Clone, Default)]
+pub(crate) struct ElectronCompat {
+    /// conf's `encryptionKey` option.
+    encryption_key: Option<Zeroizing<String>>,
+}
+
+impl Store {
+    /// Creates a new instance of the store used by an Electron app with the given name
+    /// through `electron-store`, `config.json` in the app's `userData` directory,
+    /// in electron-store's format.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::electron_store("My App").unwrap();
+    /// assert_eq!(store.get_store_path(), dirs::config_dir().unwrap().join("My App/config.json"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the config directory cannot be found.
+    pub fn electron_store(app_name: &str) -> Result<Self> {
+        let mut store = Store::new(app_name)?;
+        store.set_project_suffix(None);
+        store.set_electron_compat(true);
+        Ok(store)
+    }
+
+    /// Creates a new instance of the store used by a Node.js app with the given project
+    /// name through `conf`, `config.json` in `<project_name>-nodejs` within the
+    /// platform's preferences directory, in conf's format.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the config directory cannot be found.
+    pub fn conf(project_name: &str) -> Result<Self> {
+        let mut store = Store::new(project_name)?;
+        store.set_project_suffix(Some("nodejs"));
+        store.set_electron_compat(true);
+        #[cfg(target_os = "macos")]
+        store.set_path(dirs::preference_dir().ok_or(Error::ConfigDir)?);
+        // conf keeps the file in a `Config` directory within the project's directory.
+        #[cfg(windows)]
+        {
+            let project_dir = format!("{}-nodejs", project_name);
+            store.set_path(store.get_path().join(project_dir));
+            store.set_project_name("Config");
+            store.set_project_suffix(None);
+        }
+        Ok(store)
+    }
+
+    /// Sets whether the store file is read and written in the format of the
+    /// `electron-store` and `conf` npm packages, so a Rust rewrite of an Electron or
+    /// Node.js app keeps its users' settings. It is disabled by default.
+    ///
+    /// The store file is written as JSON indented with tabs, and encrypted with
+    /// [`set_electron_encryption_key`](Store::set_electron_encryption_key) rather than
+    /// bland's own encryption. Compression, checksums and signatures must be disabled.
+    pub fn set_electron_compat(&mut self, compat: bool) {
+        self.electron = match compat {
+            true => Some(self.electron.take().unwrap_or_default()),
+            false => None,
+        };
+    }
+
+    pub fn get_electron_compat(&self) -> bool {
+        self.electron.is_some()
+    }
+
+    /// Sets the `encryptionKey` the store was created with by `electron-store` or `conf`,
+    /// or `None` for a plain text store. Enables the compatibility mode.
+    ///
+    /// Stores are encrypted with AES-256-CBC, using a key derived from the encryption key
+    /// with PBKDF2-SHA512. Like `conf`, a store which cannot be decrypted is read as plain
+    /// text, and stores encrypted by versions of `conf` before 10 cannot be read.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::electron_store("electron-encrypted-app").unwrap();
+    /// store.set_electron_encryption_key(Some("obfuscation"));
+    /// store.set("theme", "dark").unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "dark");
+    /// assert_eq!(std::fs::read(store.get_store_path()).unwrap()[16], b':');
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_electron_encryption_key(&mut self, key: Option<&str>) {
+        self.electron = Some(ElectronCompat {
+            encryption_key: key.map(|key| Zeroizing::new(key.to_string())),
+        });
+    }
+
+    /// Returns a boolean indicating whether the store is encrypted in electron-store's format.
+    pub(crate) fn has_electron_encryption(&self) -> bool {
+        self.electron
+            .as_ref()
+            .is_some_and(|compat| compat.encryption_key.is_some())
+    }
+}
+
+impl ElectronCompat {
+    /// Serializes the contents of a store to the writer the way electron-store does,
+    /// indented with tabs.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized or written.
+    pub(crate) fn serialize<T: Serialize, W: Write>(&self, value: &T, writer: W) -> Result<()> {
+        let mut serializer =
+            Serializer::with_formatter(writer, PrettyFormatter::with_indent(b"\t"));
+        value.serialize(&mut serializer)?;
+        Ok(())
+    }
+
+    /// Encrypts the data if an encryption key is set, as the initialization vector, a colon
+    /// and the ciphertext.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be encrypted.
+    pub(crate) fn encode(&self, data: Vec<u8>) -> Result<Vec<u8>> {
+        let data = Zeroizing::new(data);
+        let password = match &self.encryption_key {
+            Some(password) => password,
+            None => return Ok(data.to_vec()),
+        };
+        let iv: [u8; IV_LEN] = rand::random();
+        let key = derive_key(password, &iv);
+        let cipher =
+            cbc::Encryptor::<Aes256>::new_from_slices(&*key, &iv).map_err(|_| Error::Encryption)?;
+        let mut encoded = iv.to_vec();
+        encoded.push(b':');
+        encoded.extend(cipher.encrypt_padded_vec_mut::<Pkcs7>(&data));
+        Ok(encoded)
+    }
+
+    /// Decrypts the data if an encryption key is set, returning it unchanged if it cannot
+    /// be decrypted as `conf` does.
+    pub(crate) fn decode(&self, data: Vec<u8>) -> Vec<u8> {
+        let password = match &self.encryption_key {
+            Some(password) => password,
+            None => return data,
+        };
+        if data.len() <= IV_LEN || data[IV_LEN] != b':' {
+            return data;
+        }
+        let iv = &data[..IV_LEN];
+        let key = derive_key(password, iv);
+        let decrypted = cbc::Decryptor::<Aes256>::new_from_slices(&*key, iv)
+            .ok()
+            .and_then(|cipher| {
+                cipher
+                    .decrypt_padded_vec_mut::<Pkcs7>(&data[IV_LEN + 1..])
+                    .ok()
+            });
+        decrypted.unwrap_or(data)
+    }
+}
+
+/// Derives the AES key from conf's encryption key. conf salts it with the initialization
+/// vector decoded as UTF-8, with invalid sequences replaced.
+fn derive_key(password: &str, iv: &[u8]) -> Zeroizing<[u8; 32]> {
+    let salt = String::from_utf8_lossy(iv);
+    let mut key = Zeroizing::new([0; 32]);
+    pbkdf2::pbkdf2_hmac::<Sha512>(
+        password.as_bytes(),
+        salt.as_bytes(),
+        PBKDF2_ROUNDS,
+        &mut *key,
+    );
+    key
+}
@@ -0,0 +1,80 @@
+use crate::{patch::merge_values, Store};
+use serde_json::Value;
+use std::env;
+
+/// The section every environment is overlaid on.
+const DEFAULT_SECTION: &str = "default";
+
+impl Store {
+    /// Sets the active environment. While one is set, the store is read as its `default`
+    /// section overlaid with the section named after the environment, such as `development`
+    /// or `production`, and other top-level keys are hidden.
+    ///
+    /// Writes go to the active environment's section, so the store reads back its own
+    /// writes. A value from the `default` section is overridden for the environment rather
+    /// than changed, and cannot be deleted through it. Without an active environment, a
+    /// section is changed through its top-level key, for example with
+    /// [`scoped`](Store::scoped).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("environment-app").unwrap();
+    /// store.set("default.db.host", "localhost").unwrap();
+    /// store.set("default.db.port", 5432).unwrap();
+    /// store.scoped("production").set("db.host", "db.example.com").unwrap();
+    ///
+    /// store.set_environment(Some("production"));
+    /// assert_eq!(store.get("db.host").unwrap().unwrap(), "db.example.com");
+    /// assert_eq!(store.get("db.port").unwrap().unwrap(), 5432);
+    /// store.set("db.port", 6543).unwrap();
+    /// assert_eq!(store.get("db.port").unwrap().unwrap(), 6543);
+    /// store.set_environment(Some("development"));
+    /// assert_eq!(store.get("db.host").unwrap().unwrap(), "localhost");
+    /// assert_eq!(store.get("db.port").unwrap().unwrap(), 5432);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_environment(&mut self, environment: Option<&str>) {
+        self.environment = environment.map(str::to_string);
+    }
+
+    pub fn get_environment(&self) -> Option<&str> {
+        self.environment.as_deref()
+    }
+
+    /// Sets the active environment from the environment variable `key`, such as `APP_ENV`.
+    /// The environment is left unchanged if the variable is unset or empty.
+    ///
+    /// Returns a boolean indicating whether the environment was set.
+    pub fn set_environment_from_env(&mut self, key: &str) -> bool {
+        match env::var(key) {
+            Ok(environment) if !environment.is_empty() => {
+                self.set_environment(Some(&environment));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the `default` section of the store's contents overlaid with the active
+    /// environment's section, or the contents unchanged if no environment is active.
+    pub(crate) fn resolve_environment(&self, value: Value) -> Value {
+        let environment = match &self.environment {
+            Some(environment) => environment,
+            None => return value,
+        };
+        let mut sections = match value {
+            Value::Object(map) => map,
+            _ => return Value::Object(Default::default()),
+        };
+        let mut resolved = match sections.remove(DEFAULT_SECTION) {
+            Some(default) => default,
+            None => Value::Object(Default::default()),
+        };
+        if let Some(section) = sections.remove(environment) {
+            merge_values(&mut resolved, section);
+        }
+        resolved
+    }
+}
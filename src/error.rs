@@ -2,46 +2,361 @@
 use aes_gcm::Error as EncryptionError;
 use json_dotpath::Error as JsonDotPathError;
 use serde_json::Error as SerdeJsonError;
+#[cfg(feature = "jsonpath")]
+use serde_json_path::ParseError as JsonPathError;
 use std::string::FromUtf8Error;
-use std::{error, fmt, io};
+use std::{
+    error, fmt, io,
+    path::{Path, PathBuf},
+};
+
+/// The file system operation that failed with an [`Error::Io`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoOp {
+    /// Reading a file.
+    Read,
+    /// Writing a file.
+    Write,
+    /// Creating a file.
+    Create,
+    /// Creating a directory.
+    CreateDir,
+    /// Renaming a file, the path is the file being renamed.
+    Rename,
+    /// Copying a file, the path is the file being copied.
+    Copy,
+    /// Removing a file or directory.
+    Remove,
+    /// Listing a directory.
+    ReadDir,
+    /// Reading a file's metadata.
+    Metadata,
+    /// Locking the lock file.
+    Lock,
+    /// Flushing a file or directory to disk.
+    Sync,
+    /// An operation that does not involve a file, such as compression.
+    Other,
+}
+
+impl fmt::Display for IoOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            IoOp::Read => "read",
+            IoOp::Write => "write",
+            IoOp::Create => "create",
+            IoOp::CreateDir => "create directory",
+            IoOp::Rename => "rename",
+            IoOp::Copy => "copy",
+            IoOp::Remove => "remove",
+            IoOp::ReadDir => "read directory",
+            IoOp::Metadata => "read metadata of",
+            IoOp::Lock => "lock",
+            IoOp::Sync => "sync",
+            IoOp::Other => "access",
+        })
+    }
+}
 
 /// The `Error` type is an enum for all errors that can be thrown by this library.
 #[derive(Debug)]
 pub enum Error {
     /// `Io` errors are errors that occur when reading from or writing to a file.
-    Io(io::Error),
+    /// The path is empty for errors that do not involve a file.
+    Io {
+        path: PathBuf,
+        op: IoOp,
+        source: io::Error,
+    },
     /// `NotFound` errors are errors that occur when a requested path is not found.
     NotFound,
     /// `DotPath` errors are errors that occur when using the `JsonDotPath` library.
     DotPath(JsonDotPathError),
     /// `SerdeJson` errors are errors that occur when using the `SerdeJson` library.
     Serde(SerdeJsonError),
+    /// `Corrupt` errors are errors that occur when the store file cannot be parsed,
+    /// for example after a truncated write. `config` is the file name of the configuration.
+    Corrupt {
+        config: String,
+        source: SerdeJsonError,
+    },
+    /// `FormatMismatch` errors are errors that occur when the store file is in a format the
+    /// store is not configured to read, such as an encrypted store opened without a key.
+    /// `config` is the file name of the configuration and `format` describes the file.
+    FormatMismatch {
+        config: String,
+        format: &'static str,
+    },
+    /// `ChecksumMismatch` errors are errors that occur when the store file's checksum
+    /// is missing or does not match its contents.
+    ChecksumMismatch,
     /// `ConfigDir` errors are errors that occur when locating the config directory.
     ConfigDir,
+    /// `InvalidPointer` errors are errors that occur when a JSON Pointer is malformed
+    /// or refers to a location that does not exist.
+    InvalidPointer(String),
+    /// `TestFailed` errors are errors that occur when a JSON Patch `test` operation
+    /// does not match the value at its path.
+    TestFailed(String),
+    /// `NotAnInteger` errors are errors that occur when incrementing a value that is not
+    /// an integer, or when the result would overflow.
+    NotAnInteger(String),
+    /// `ReadOnly` errors are errors that occur when writing to a store in read-only mode.
+    ReadOnly,
+    /// `Conflict` errors are errors that occur when a conditional write finds that the store
+    /// was changed since the expected generation.
+    Conflict,
+    /// `QuotaExceeded` errors are errors that occur when a write would make the value at
+    /// `path` larger than its quota, or the whole store larger than its maximum size if
+    /// `path` is empty. Sizes are in bytes of serialized JSON.
+    QuotaExceeded {
+        path: String,
+        size: usize,
+        limit: usize,
+    },
+    /// `InvalidValue` errors are errors that occur when a write is rejected by the schema
+    /// of the value at `path`, or when a directive in the store such as `$include` is invalid.
+    InvalidValue {
+        path: String,
+        reason: String,
+    },
+    /// `JsonPath` errors are errors that occur when parsing a JSONPath expression.
+    #[cfg(feature = "jsonpath")]
+    JsonPath(JsonPathError),
     #[cfg(feature = "crypto")]
     InvalidKeyLength,
     #[cfg(feature = "crypto")]
     Encryption,
     #[cfg(feature = "crypto")]
     Decryption,
+    /// `WrongKey` errors are errors that occur when decrypting a store with a key
+    /// or password other than the one it was encrypted with.
+    #[cfg(feature = "crypto")]
+    WrongKey,
+    /// `InvalidHeader` errors are errors that occur when an encrypted store's header is
+    /// truncated or uses an unknown format version, cipher or key derivation function.
+    #[cfg(feature = "crypto")]
+    InvalidHeader,
+    /// `NotEncrypted` errors are errors that occur when reading a plain text store
+    /// while an encryption key is set.
+    #[cfg(feature = "crypto")]
+    NotEncrypted,
+    /// `SignatureInvalid` errors are errors that occur when a signed store's signature
+    /// is missing or does not match its contents.
+    #[cfg(feature = "crypto")]
+    SignatureInvalid,
     FromUTF8Error(FromUtf8Error),
 }
 
+/// The category of an [`Error`], available regardless of the enabled features.
+///
+/// New kinds may be added, so matches should include a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The store, or the requested value within it, does not exist.
+    NotFound,
+    /// The file system refused access to the store.
+    PermissionDenied,
+    /// Any other I/O error.
+    Io,
+    /// The store file cannot be parsed or fails its checksum.
+    Corrupt,
+    /// The store file is in a format the store is not configured to read.
+    FormatMismatch,
+    /// A value cannot be serialized or deserialized.
+    Serialization,
+    /// A dot path, JSON Pointer or JSONPath expression is invalid.
+    InvalidPath,
+    /// A JSON Patch `test` operation failed.
+    TestFailed,
+    /// A value is not an integer or an increment would overflow.
+    NotAnInteger,
+    /// The config directory cannot be found.
+    ConfigDir,
+    /// The store is read-only.
+    ReadOnly,
+    /// The store was changed by another writer.
+    Conflict,
+    /// A write would exceed a size quota.
+    QuotaExceeded,
+    /// A write was rejected by a schema, or a directive in the store is invalid.
+    InvalidValue,
+    /// The encryption key is invalid.
+    InvalidKey,
+    /// The store cannot be encrypted.
+    Encryption,
+    /// The store cannot be decrypted, because it is not encrypted, tampered with or damaged.
+    Decryption,
+    /// The store is encrypted with a different key or password.
+    WrongKey,
+}
+
+impl Error {
+    /// Returns the category of the error.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{ErrorKind, Store};
+    /// let store = Store::new("error-kind-app").unwrap();
+    /// let error = store.delete("a").unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::NotFound);
+    /// assert!(error.is_not_found());
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            Error::Io { source, .. } => match source.kind() {
+                io::ErrorKind::NotFound => ErrorKind::NotFound,
+                io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                _ => ErrorKind::Io,
+            },
+            Error::NotFound => ErrorKind::NotFound,
+            Error::DotPath(_) | Error::InvalidPointer(_) => ErrorKind::InvalidPath,
+            #[cfg(feature = "jsonpath")]
+            Error::JsonPath(_) => ErrorKind::InvalidPath,
+            Error::Serde(_) => ErrorKind::Serialization,
+            Error::Corrupt { .. } | Error::ChecksumMismatch | Error::FromUTF8Error(_) => {
+                ErrorKind::Corrupt
+            }
+            Error::FormatMismatch { .. } => ErrorKind::FormatMismatch,
+            Error::ConfigDir => ErrorKind::ConfigDir,
+            Error::TestFailed(_) => ErrorKind::TestFailed,
+            Error::NotAnInteger(_) => ErrorKind::NotAnInteger,
+            Error::ReadOnly => ErrorKind::ReadOnly,
+            Error::Conflict => ErrorKind::Conflict,
+            Error::QuotaExceeded { .. } => ErrorKind::QuotaExceeded,
+            Error::InvalidValue { .. } => ErrorKind::InvalidValue,
+            #[cfg(feature = "crypto")]
+            Error::InvalidKeyLength => ErrorKind::InvalidKey,
+            #[cfg(feature = "crypto")]
+            Error::Encryption => ErrorKind::Encryption,
+            #[cfg(feature = "crypto")]
+            Error::Decryption
+            | Error::InvalidHeader
+            | Error::NotEncrypted
+            | Error::SignatureInvalid => ErrorKind::Decryption,
+            #[cfg(feature = "crypto")]
+            Error::WrongKey => ErrorKind::WrongKey,
+        }
+    }
+
+    /// Returns a boolean indicating whether the store, its directory or a requested value
+    /// does not exist.
+    pub fn is_not_found(&self) -> bool {
+        self.kind() == ErrorKind::NotFound
+    }
+
+    /// Returns a boolean indicating whether the file system refused access to the store.
+    pub fn is_permission_denied(&self) -> bool {
+        self.kind() == ErrorKind::PermissionDenied
+    }
+
+    /// Returns a boolean indicating whether the store file is corrupt.
+    pub fn is_corrupt(&self) -> bool {
+        self.kind() == ErrorKind::Corrupt
+    }
+
+    /// Returns a boolean indicating whether the store cannot be decrypted,
+    /// including because the key is wrong.
+    pub fn is_decryption_failure(&self) -> bool {
+        matches!(self.kind(), ErrorKind::Decryption | ErrorKind::WrongKey)
+    }
+
+    /// Returns a boolean indicating whether the store is encrypted with a different key
+    /// or password, so the user can be asked for it again.
+    pub fn is_wrong_key(&self) -> bool {
+        self.kind() == ErrorKind::WrongKey
+    }
+
+    /// Returns a boolean indicating whether the store is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.kind() == ErrorKind::ReadOnly
+    }
+
+    /// Returns a boolean indicating whether the store was changed by another writer,
+    /// so the change should be retried on the current contents.
+    pub fn is_conflict(&self) -> bool {
+        self.kind() == ErrorKind::Conflict
+    }
+
+    /// Returns a boolean indicating whether a write would exceed a size quota.
+    pub fn is_quota_exceeded(&self) -> bool {
+        self.kind() == ErrorKind::QuotaExceeded
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             // TODO: check this
-            Error::Io(ref err) => err.fmt(f),
+            Error::Io {
+                ref path,
+                op,
+                ref source,
+            } => match path.as_os_str().is_empty() {
+                true => source.fmt(f),
+                false => write!(f, "Failed to {} {}: {}", op, path.display(), source),
+            },
             Error::NotFound => write!(f, "Store not found"),
             Error::DotPath(ref err) => err.fmt(f),
             Error::Serde(ref err) => err.fmt(f),
+            Error::Corrupt {
+                ref config,
+                ref source,
+            } => write!(f, "Store {} is corrupt: {}", config, source),
+            Error::FormatMismatch { ref config, format } => write!(
+                f,
+                "Store {} is {}, which the store is not configured to read",
+                config, format
+            ),
+            Error::ChecksumMismatch => write!(f, "Store checksum mismatch"),
             Error::ConfigDir => write!(f, "Config directory not found"),
+            Error::InvalidPointer(ref pointer) => write!(f, "Invalid JSON pointer: {}", pointer),
+            Error::TestFailed(ref pointer) => write!(f, "Patch test failed at {}", pointer),
+            Error::NotAnInteger(ref path) => {
+                write!(f, "Value at {} is not an integer or would overflow", path)
+            }
+            Error::ReadOnly => write!(f, "Store is read-only"),
+            Error::Conflict => write!(f, "Store was changed by another writer"),
+            Error::QuotaExceeded {
+                ref path,
+                size,
+                limit,
+            } if path.is_empty() => write!(
+                f,
+                "Store would be {} bytes, exceeding its maximum size of {} bytes",
+                size, limit
+            ),
+            Error::QuotaExceeded {
+                ref path,
+                size,
+                limit,
+            } => write!(
+                f,
+                "Value at {} would be {} bytes, exceeding its quota of {} bytes",
+                path, size, limit
+            ),
+            Error::InvalidValue {
+                ref path,
+                ref reason,
+            } => write!(f, "Invalid value at {}: {}", path, reason),
+            #[cfg(feature = "jsonpath")]
+            Error::JsonPath(ref err) => err.fmt(f),
             #[cfg(feature = "crypto")]
             Error::Encryption => write!(f, "Encryption error"),
             #[cfg(feature = "crypto")]
             Error::InvalidKeyLength => write!(f, "Invalid encryption key length"),
             #[cfg(feature = "crypto")]
             Error::Decryption => write!(f, "Decryption error"),
+            #[cfg(feature = "crypto")]
+            Error::WrongKey => write!(f, "Wrong encryption key or password"),
+            #[cfg(feature = "crypto")]
+            Error::InvalidHeader => write!(f, "Invalid encrypted store header"),
+            #[cfg(feature = "crypto")]
+            Error::NotEncrypted => write!(f, "Store is not encrypted"),
+            #[cfg(feature = "crypto")]
+            Error::SignatureInvalid => write!(f, "Invalid store signature"),
             Error::FromUTF8Error(ref err) => err.fmt(f),
         }
     }
@@ -50,26 +365,65 @@ impl fmt::Display for Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            Error::Io(ref err) => Some(err),
+            Error::Io { ref source, .. } => Some(source),
             Error::NotFound => None,
             Error::DotPath(ref err) => Some(err),
             Error::Serde(ref err) => Some(err),
+            Error::Corrupt { ref source, .. } => Some(source),
+            Error::FormatMismatch { .. } => None,
+            Error::ChecksumMismatch => None,
             Error::ConfigDir => None,
+            Error::InvalidPointer(_) => None,
+            Error::TestFailed(_) => None,
+            Error::NotAnInteger(_) => None,
+            Error::ReadOnly => None,
+            Error::Conflict => None,
+            Error::QuotaExceeded { .. } => None,
+            Error::InvalidValue { .. } => None,
+            #[cfg(feature = "jsonpath")]
+            Error::JsonPath(ref err) => Some(err),
             #[cfg(feature = "crypto")]
             Error::Encryption => None,
             #[cfg(feature = "crypto")]
             Error::InvalidKeyLength => None,
             #[cfg(feature = "crypto")]
             Error::Decryption => None,
+            #[cfg(feature = "crypto")]
+            Error::WrongKey => None,
+            #[cfg(feature = "crypto")]
+            Error::InvalidHeader => None,
+            #[cfg(feature = "crypto")]
+            Error::NotEncrypted => None,
+            #[cfg(feature = "crypto")]
+            Error::SignatureInvalid => None,
             Error::FromUTF8Error(ref err) => Some(err),
         }
     }
 }
 
-/// A function to convert io::Error to Error.
+/// A function to convert io::Error to Error, for errors that do not involve a file.
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Error {
-        Error::Io(e)
+        Error::Io {
+            path: PathBuf::new(),
+            op: IoOp::Other,
+            source: e,
+        }
+    }
+}
+
+/// Attaches the failed operation and the path it was performed on to I/O errors.
+pub(crate) trait IoContext<T> {
+    fn context(self, op: IoOp, path: &Path) -> Result<T, Error>;
+}
+
+impl<T> IoContext<T> for io::Result<T> {
+    fn context(self, op: IoOp, path: &Path) -> Result<T, Error> {
+        self.map_err(|source| Error::Io {
+            path: path.to_path_buf(),
+            op,
+            source,
+        })
     }
 }
 
@@ -87,6 +441,14 @@ impl From<JsonDotPathError> for Error {
     }
 }
 
+/// A function to convert serde_json_path::ParseError to Error.
+#[cfg(feature = "jsonpath")]
+impl From<JsonPathError> for Error {
+    fn from(e: JsonPathError) -> Error {
+        Error::JsonPath(e)
+    }
+}
+
 /// A function to convert aes_gcm::Error to Error.
 #[cfg(feature = "crypto")]
 impl From<EncryptionError> for Error {
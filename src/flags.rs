@@ -0,0 +1,318 @@
+use crate::{
+    path::{env_name, join_path},
+    Error, Result, Store,
+};
+use serde_json::Value;
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    env, fs,
+    hash::BuildHasher,
+    process,
+    sync::OnceLock,
+    time::SystemTime,
+};
+
+/// The key under which flags are kept in the store.
+const FLAGS_ROOT: &str = "flags";
+
+/// The file in the store directory holding the machine id generated for machines without
+/// one.
+const MACHINE_ID_FILE: &str = "machine-id";
+
+/// The files holding the machine id on Unix systems, in order of preference.
+#[cfg(all(unix, not(target_os = "macos")))]
+const MACHINE_ID_PATHS: [&str; 2] = ["/etc/machine-id", "/var/lib/dbus/machine-id"];
+
+/// The state of a feature flag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Flag {
+    /// Enabled everywhere.
+    On,
+    /// Disabled everywhere.
+    Off,
+    /// Enabled on the given percentage of machines, from 0 to 100.
+    Rollout(u8),
+}
+
+impl From<bool> for Flag {
+    fn from(enabled: bool) -> Self {
+        match enabled {
+            true => Flag::On,
+            false => Flag::Off,
+        }
+    }
+}
+
+impl Flag {
+    /// Parses a flag from its representation in the store, `true`, `false` or a percentage.
+    fn from_value(value: &Value) -> Option<Flag> {
+        match value {
+            Value::Bool(enabled) => Some(Flag::from(*enabled)),
+            Value::Number(percent) => percent
+                .as_u64()
+                .filter(|percent| *percent <= 100)
+                .map(|percent| Flag::Rollout(percent as u8)),
+            _ => None,
+        }
+    }
+
+    /// Parses a flag from an environment variable, `1`, `true`, `on`, `0`, `false`, `off`
+    /// or a percentage followed by `%`.
+    fn from_env(value: &str) -> Option<Flag> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "on" => Some(Flag::On),
+            "0" | "false" | "off" => Some(Flag::Off),
+            value => value
+                .strip_suffix('%')?
+                .parse::<u8>()
+                .ok()
+                .filter(|percent| *percent <= 100)
+                .map(Flag::Rollout),
+        }
+    }
+
+    fn to_value(self) -> Value {
+        match self {
+            Flag::On => Value::Bool(true),
+            Flag::Off => Value::Bool(false),
+            Flag::Rollout(percent) => Value::from(percent),
+        }
+    }
+}
+
+/// Feature flags kept in the store under `flags`.
+///
+/// A flag is looked up in the environment variable named by the env prefix followed by the
+/// flag's name in upper case, then in the store, then in the defaults. Flags in the store
+/// are `true`, `false` or the percentage of machines to roll out to. Environment variables
+/// are `1`, `true`, `on`, `0`, `false`, `off` or a percentage like `25%`.
+///
+/// Rollouts pick machines by hashing the flag's name with the machine id, so a machine
+/// keeps its decision across restarts and is included in every larger rollout.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{Flag, Flags, Store};
+/// let store = Store::new("flags-app").unwrap();
+/// let mut flags = Flags::new(&store);
+/// flags.set_default("new_ui", false);
+/// assert!(!flags.is_enabled("new_ui"));
+///
+/// flags.set("new_ui", true).unwrap();
+/// assert!(flags.is_enabled("new_ui"));
+///
+/// flags.set_machine_id(Some("machine"));
+/// flags.set("sync", Flag::Rollout(100)).unwrap();
+/// assert!(flags.is_enabled("sync"));
+///
+/// std::env::set_var("FLAGS_APP_FLAG_NEW_UI", "off");
+/// assert!(!flags.is_enabled("new_ui"));
+/// # std::env::remove_var("FLAGS_APP_FLAG_NEW_UI");
+/// # store.delete_store_dir().unwrap();
+/// ```
+pub struct Flags<'s> {
+    store: &'s Store,
+    /// The flags used when neither the environment nor the store sets them.
+    defaults: HashMap<String, Flag>,
+    /// The prefix of the environment variables overriding flags.
+    env_prefix: String,
+    /// The id of the machine rollouts are decided for, `None` if it is unknown, resolved on
+    /// first use.
+    machine_id: OnceLock<Option<String>>,
+}
+
+impl<'s> Flags<'s> {
+    /// Creates the flags of the given store, with environment variables prefixed with the
+    /// project's name in upper case followed by `_FLAG_`.
+    ///
+    /// The machine id is the operating system's, `/etc/machine-id` on Linux,
+    /// `IOPlatformUUID` on macOS and `MachineGuid` on Windows. Elsewhere a random id is
+    /// generated and kept in the `machine-id` file in the store directory, unless the
+    /// store is read-only. The id is only looked up once a rollout is decided.
+    pub fn new(store: &'s Store) -> Self {
+        Flags {
+            store,
+            defaults: HashMap::new(),
+            env_prefix: format!("{}_FLAG_", env_name(store.get_project_name())),
+            machine_id: OnceLock::new(),
+        }
+    }
+
+    /// Sets the state of a flag which is set by neither the environment nor the store.
+    pub fn set_default<F>(&mut self, name: &str, flag: F)
+    where
+        F: Into<Flag>,
+    {
+        self.defaults.insert(name.to_string(), flag.into());
+    }
+
+    pub fn set_env_prefix(&mut self, prefix: &str) {
+        self.env_prefix = prefix.to_string();
+    }
+
+    pub fn get_env_prefix(&self) -> &str {
+        &self.env_prefix
+    }
+
+    /// Sets the id of the machine rollouts are decided for. Rollouts are disabled on a
+    /// machine without an id, except those to 100%.
+    pub fn set_machine_id(&mut self, machine_id: Option<&str>) {
+        self.machine_id = OnceLock::from(machine_id.map(str::to_string));
+    }
+
+    pub fn get_machine_id(&self) -> Option<&str> {
+        self.machine_id
+            .get_or_init(|| system_machine_id().or_else(|| stored_machine_id(self.store)))
+            .as_deref()
+    }
+
+    /// Returns the state of the flag with the given name, `None` if it is not set anywhere.
+    ///
+    /// An invalid environment variable or value in the store is ignored, as is a store
+    /// that cannot be read.
+    pub fn get(&self, name: &str) -> Option<Flag> {
+        let from_env = env::var(format!("{}{}", self.env_prefix, env_name(name)))
+            .ok()
+            .and_then(|value| Flag::from_env(&value));
+        let from_store = || {
+            self.store
+                .get(&join_path(FLAGS_ROOT, name))
+                .ok()
+                .flatten()
+                .and_then(|value| Flag::from_value(&value))
+        };
+        from_env
+            .or_else(from_store)
+            .or_else(|| self.defaults.get(name).copied())
+    }
+
+    /// Returns a boolean indicating whether the flag with the given name is enabled on this
+    /// machine. Flags which are not set anywhere are disabled.
+    pub fn is_enabled(&self, name: &str) -> bool {
+        match self.get(name) {
+            Some(Flag::On) => true,
+            Some(Flag::Off) | None => false,
+            Some(Flag::Rollout(percent)) => {
+                percent >= 100
+                    || self.get_machine_id().is_some_and(|machine_id| {
+                        rollout_bucket(name, machine_id) < u32::from(percent)
+                    })
+            }
+        }
+    }
+
+    /// Sets the state of a flag in the store.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store fails to be created or written to.
+    pub fn set<F>(&self, name: &str, flag: F) -> Result<()>
+    where
+        F: Into<Flag>,
+    {
+        self.store
+            .set(&join_path(FLAGS_ROOT, name), flag.into().to_value())
+    }
+
+    /// Removes a flag from the store, so it falls back to its default.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read or written to.
+    pub fn remove(&self, name: &str) -> Result<()> {
+        match self.store.delete(&join_path(FLAGS_ROOT, name)) {
+            Ok(_) | Err(Error::NotFound) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Returns the bucket from 0 to 99 the machine falls into for the flag's rollout.
+fn rollout_bucket(name: &str, machine_id: &str) -> u32 {
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(name.as_bytes());
+    hasher.update(b":");
+    hasher.update(machine_id.as_bytes());
+    hasher.finalize() % 100
+}
+
+/// Returns the operating system's id of the machine, if it has one.
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_machine_id() -> Option<String> {
+    MACHINE_ID_PATHS.iter().find_map(|path| {
+        fs::read_to_string(path)
+            .ok()
+            .map(|id| id.trim().to_string())
+            .filter(|id| !id.is_empty())
+    })
+}
+
+/// Returns the hardware UUID of the machine reported by `ioreg`.
+#[cfg(target_os = "macos")]
+fn system_machine_id() -> Option<String> {
+    let output = process::Command::new("ioreg")
+        .args(["-rd1", "-c", "IOPlatformExpertDevice"])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("\"IOPlatformUUID\""))
+        .and_then(|line| line.split('"').nth(3))
+        .map(str::to_string)
+        .filter(|id| !id.is_empty())
+}
+
+/// Returns the `MachineGuid` Windows keeps in the registry.
+#[cfg(windows)]
+fn system_machine_id() -> Option<String> {
+    let output = process::Command::new("reg")
+        .args([
+            "query",
+            r"HKLM\SOFTWARE\Microsoft\Cryptography",
+            "/v",
+            "MachineGuid",
+        ])
+        .output()
+        .ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find(|line| line.contains("MachineGuid"))
+        .and_then(|line| line.split_whitespace().last())
+        .map(str::to_string)
+        .filter(|id| !id.is_empty())
+}
+
+/// Returns `None`, as there is no known machine id on other systems.
+#[cfg(not(any(unix, windows)))]
+fn system_machine_id() -> Option<String> {
+    None
+}
+
+/// Returns the machine id kept in the store directory, generating it if there is none.
+/// Returns `None` if it cannot be read or written.
+fn stored_machine_id(store: &Store) -> Option<String> {
+    let path = store.get_store_dir_path().join(MACHINE_ID_FILE);
+    if let Ok(id) = fs::read_to_string(&path) {
+        return Some(id.trim().to_string()).filter(|id| !id.is_empty());
+    }
+    if store.check_writable().is_err() {
+        return None;
+    }
+    let id = random_machine_id();
+    store.make_store_path().ok()?;
+    store.replace_file(&path, id.as_bytes()).ok()?;
+    Some(id)
+}
+
+/// Returns a random 128-bit id in hex, which needs no source of randomness beyond the
+/// standard library's randomly seeded hasher.
+fn random_machine_id() -> String {
+    let state = RandomState::new();
+    let seed = (SystemTime::now(), process::id());
+    format!(
+        "{:016x}{:016x}",
+        state.hash_one((seed, 0)),
+        state.hash_one((seed, 1))
+    )
+}
@@ -0,0 +1,130 @@
+use crate::{Result, Store};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+use serde_json::{ser::PrettyFormatter, Value};
+use std::io::Write;
+
+/// How a pretty printed store file is laid out, see [`Store::set_pretty_style`].
+///
+/// The default matches [`Store::set_pretty`], two spaces and no newline at the end of the file.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PrettyStyle {
+    /// What each level of nesting is indented with.
+    pub indent: Indent,
+    /// Whether the file ends with a newline, as most editors save files.
+    pub trailing_newline: bool,
+}
+
+/// What each level of nesting of a pretty printed store file is indented with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Indent {
+    /// The given number of spaces.
+    Spaces(usize),
+    /// A tab.
+    Tabs,
+}
+
+impl Default for Indent {
+    fn default() -> Self {
+        Indent::Spaces(2)
+    }
+}
+
+impl Store {
+    /// Sets whether the keys of every object are sorted alphabetically when the store file is
+    /// written, disabled by default. The file then only depends on the store's contents, not
+    /// on the order values were set in, so it can be compared and versioned reliably.
+    ///
+    /// Keys are always sorted unless the `preserve_order` feature is enabled, which keeps
+    /// them in the order they were set for reads while this writes them sorted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("sort-keys-app").unwrap();
+    /// store.set_sort_keys(true);
+    /// store.set("b", 1).unwrap();
+    /// store.set("a.d", 2).unwrap();
+    /// store.set("a.c", 3).unwrap();
+    /// let contents = std::fs::read_to_string(store.get_store_path()).unwrap();
+    /// assert_eq!(contents, r#"{"a":{"c":3,"d":2},"b":1}"#);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_sort_keys(&mut self, sort_keys: bool) {
+        self.sort_keys = sort_keys;
+    }
+
+    pub fn get_sort_keys(&self) -> bool {
+        self.sort_keys
+    }
+
+    /// Enables pretty printing with the given indentation and trailing newline, so store
+    /// files match a project's formatting conventions and hand edits don't cause churn.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Indent, PrettyStyle, Store};
+    /// let mut store = Store::new("pretty-style-app").unwrap();
+    /// store.set_pretty_style(PrettyStyle {
+    ///     indent: Indent::Spaces(4),
+    ///     trailing_newline: true,
+    /// });
+    /// store.set("theme", "dark").unwrap();
+    /// let contents = std::fs::read_to_string(store.get_store_path()).unwrap();
+    /// assert_eq!(contents, "{\n    \"theme\": \"dark\"\n}\n");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_pretty_style(&mut self, style: PrettyStyle) {
+        self.pretty = Some(style);
+    }
+
+    /// Returns the layout of the store file, `None` if it is not pretty printed.
+    pub fn get_pretty_style(&self) -> Option<PrettyStyle> {
+        self.pretty
+    }
+}
+
+impl PrettyStyle {
+    /// Serializes a value laid out in this style into the writer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized or written.
+    pub(crate) fn serialize<T: Serialize, W: Write>(&self, value: &T, mut writer: W) -> Result<()> {
+        let indent = match self.indent {
+            Indent::Spaces(width) => vec![b' '; width],
+            Indent::Tabs => vec![b'\t'],
+        };
+        let formatter = PrettyFormatter::with_indent(&indent);
+        value.serialize(&mut serde_json::Serializer::with_formatter(
+            &mut writer,
+            formatter,
+        ))?;
+        if self.trailing_newline {
+            writer.write_all(b"\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Serializes a value with the keys of every object sorted, without copying it.
+pub(crate) struct SortedKeys<'a>(pub(crate) &'a Value);
+
+impl Serialize for SortedKeys<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        match self.0 {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_unstable_by_key(|(key, _)| *key);
+                let mut object = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    object.serialize_entry(key, &SortedKeys(value))?;
+                }
+                object.end()
+            }
+            Value::Array(array) => serializer.collect_seq(array.iter().map(SortedKeys)),
+            value => value.serialize(serializer),
+        }
+    }
+}
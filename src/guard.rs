@@ -0,0 +1,139 @@
+use crate::{patch, path::take_path, Error, Result, Store};
+use json_dotpath::DotPaths;
+use serde::Serialize;
+use serde_json::Value;
+
+/// A scoped edit session returned by [`Store::edit`].
+///
+/// Changes are made to a copy of the store held in memory and are persisted in a single write
+/// when the guard is dropped or committed, unless the session is cancelled. They are applied
+/// to the contents of the store at that time, so changes made to the store since the session
+/// started are kept.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::Store;
+/// let store = Store::new("edit-app").unwrap();
+/// {
+///     let mut edit = store.edit().unwrap();
+///     edit.set("window.width", 800).unwrap();
+///     edit.set("window.height", 600).unwrap();
+/// }
+/// assert_eq!(store.get("window.height").unwrap().unwrap(), 600);
+///
+/// let mut edit = store.edit().unwrap();
+/// edit.delete("window").unwrap();
+/// edit.cancel();
+/// assert!(store.get("window").unwrap().is_some());
+/// # store.delete_store_dir().unwrap();
+/// ```
+pub struct StoreGuard<'s> {
+    store: &'s Store,
+    /// The contents of the store when the session started.
+    previous: Value,
+    /// The edited contents of the store.
+    value: Value,
+    /// Whether the guard has already committed or cancelled the session.
+    finished: bool,
+}
+
+impl<'s> StoreGuard<'s> {
+    /// Returns the value at the given path, including uncommitted changes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `path` is not a valid dot path or attempts to access an index out of bounds.
+    pub fn get(&self, path: &str) -> Result<Option<Value>> {
+        DotPaths::dot_get::<Value>(&self.value, path).map_err(Error::from)
+    }
+
+    /// Sets the given data using a dot path, see [`Store::set`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be serialized or `path` is not a valid dot path.
+    pub fn set<T>(&mut self, path: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        DotPaths::dot_set(&mut self.value, path, json_data).map_err(Error::from)
+    }
+
+    /// Deletes the given path, returning the deleted value, see [`Store::delete`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if `path` is not a valid dot path.
+    pub fn delete(&mut self, path: &str) -> Result<Option<Value>> {
+        take_path(&mut self.value, path)
+    }
+
+    /// Persists the changes now, reporting any error instead of ignoring it as dropping does.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created, read or written to.
+    /// * A change cannot be applied because the store was changed since the session started,
+    ///   for example a path it changes was deleted.
+    pub fn commit(mut self) -> Result<()> {
+        self.persist()
+    }
+
+    /// Discards the changes made in this session.
+    pub fn cancel(mut self) {
+        self.finished = true;
+    }
+
+    fn persist(&mut self) -> Result<()> {
+        if self.finished {
+            return Ok(());
+        }
+        self.finished = true;
+        if self.value == self.previous {
+            return Ok(());
+        }
+        // Only the session's changes are applied to the contents of the store as they are
+        // now, so writes made since the session started are kept.
+        let mut ops = Vec::new();
+        patch::diff("", &self.previous, &self.value, &mut ops);
+        self.store.modify(|parsed_json| {
+            for op in &ops {
+                patch::apply_op(parsed_json, op)?;
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<'s> Drop for StoreGuard<'s> {
+    fn drop(&mut self) {
+        let _ = self.persist();
+    }
+}
+
+impl Store {
+    /// Starts a scoped edit session, see [`StoreGuard`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist and may not be created, see
+    ///   [`set_create_missing`](Store::set_create_missing).
+    /// * The store exists but cannot be read or deserialized.
+    pub fn edit(&self) -> Result<StoreGuard<'_>> {
+        self.check_create_missing()?;
+        let value = match self.store_exists() || self.is_dirty() {
+            true => self.get_store_as_parsed_json()?,
+            false => Value::Object(Default::default()),
+        };
+        Ok(StoreGuard {
+            store: self,
+            previous: value.clone(),
+            value,
+            finished: false,
+        })
+    }
+}
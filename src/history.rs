@@ -0,0 +1,216 @@
+use crate::{error::IoContext, observer::diff_values, Error, IoOp, Result, Store};
+use serde_json::Value;
+use std::{
+    fs,
+    io::Write,
+    path::PathBuf,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// A snapshot of the store file, taken after a write.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The identifier of the snapshot, the time it was taken in nanoseconds since the Unix epoch.
+    pub id: u128,
+    /// The path to the snapshot file.
+    pub path: PathBuf,
+}
+
+impl Snapshot {
+    /// Returns the time the snapshot was taken.
+    pub fn created(&self) -> SystemTime {
+        let secs = (self.id / 1_000_000_000) as u64;
+        let nanos = (self.id % 1_000_000_000) as u32;
+        UNIX_EPOCH + Duration::new(secs, nanos)
+    }
+}
+
+/// A difference between two versions of the store.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    /// The dot path of the changed value, with dots in keys escaped.
+    pub path: String,
+    /// The value in the older version, `None` if it was added.
+    pub old: Option<Value>,
+    /// The value in the newer version, `None` if it was deleted.
+    pub new: Option<Value>,
+}
+
+impl Store {
+    /// Sets how many snapshots of the store file are kept in the history directory, one
+    /// taken after every write, `0` disables history. The oldest snapshots are removed once
+    /// there are more.
+    pub fn set_history(&mut self, keep: usize) {
+        self.history = keep;
+    }
+
+    pub fn get_history(&self) -> usize {
+        self.history
+    }
+
+    /// Get the path to the directory where snapshots are kept.
+    pub fn get_history_dir_path(&self) -> PathBuf {
+        let mut history_path = self.get_store_dir_path();
+        history_path.push("history");
+        history_path
+    }
+
+    /// Returns the snapshots of the store file, oldest first.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the history directory cannot be read.
+    pub fn snapshots(&self) -> Result<Vec<Snapshot>> {
+        let history_path = self.get_history_dir_path();
+        if !history_path.exists() {
+            return Ok(Vec::new());
+        }
+        let prefix = self.get_snapshot_prefix();
+        let mut snapshots = Vec::new();
+        for entry in fs::read_dir(&history_path).context(IoOp::ReadDir, &history_path)? {
+            let entry = entry.context(IoOp::ReadDir, &history_path)?;
+            let id = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_prefix(&prefix))
+                .and_then(|id| id.parse().ok());
+            if let Some(id) = id {
+                snapshots.push(Snapshot {
+                    id,
+                    path: entry.path(),
+                });
+            }
+        }
+        snapshots.sort_by_key(|snapshot| snapshot.id);
+        Ok(snapshots)
+    }
+
+    /// Returns the changes between the snapshot with the given id and the current store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("diff-snapshot-app").unwrap();
+    /// store.set_history(10);
+    /// store.set("theme", "light").unwrap();
+    /// let snapshot = store.snapshots().unwrap().pop().unwrap();
+    /// store.set("theme", "dark").unwrap();
+    ///
+    /// let changes = store.diff_snapshot(snapshot.id).unwrap();
+    /// assert_eq!(changes[0].path, "theme");
+    /// assert_eq!(changes[0].old.as_ref().unwrap(), "light");
+    /// assert_eq!(changes[0].new.as_ref().unwrap(), "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The snapshot or the store does not exist.
+    /// * The snapshot or the store cannot be read or deserialized.
+    pub fn diff_snapshot(&self, id: u128) -> Result<Vec<Change>> {
+        let snapshot_path = self.get_snapshot_path(id)?;
+        let snapshot_data = fs::read(&snapshot_path).context(IoOp::Read, &snapshot_path)?;
+        let old = self.parse_store_data(snapshot_data)?;
+        let new = self.get_store_as_parsed_json()?;
+        let mut changes = Vec::new();
+        diff_values(
+            String::new(),
+            Some(&old),
+            Some(&new),
+            &mut |path, old, new| {
+                changes.push(Change {
+                    path,
+                    old: old.cloned(),
+                    new: new.cloned(),
+                })
+            },
+        );
+        Ok(changes)
+    }
+
+    /// Reverts the store to the snapshot with the given id. Changes journaled against the
+    /// replaced store file are discarded along with it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The snapshot does not exist.
+    /// * The snapshot cannot be read.
+    /// * The store file fails to be written to.
+    pub fn revert_to(&self, id: u128) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let snapshot_path = self.get_snapshot_path(id)?;
+        let snapshot_data = fs::read(&snapshot_path).context(IoOp::Read, &snapshot_path)?;
+        self.write_file(&snapshot_data)
+    }
+
+    /// Keeps a copy of the data just written to the store file in the history directory,
+    /// removing the oldest snapshots beyond the number kept.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the history directory cannot be created, the snapshot cannot be written or
+    /// an old snapshot cannot be removed.
+    pub(crate) fn snapshot_store(&self, data: &[u8]) -> Result<()> {
+        if self.history == 0 {
+            return Ok(());
+        }
+        let history_path = self.get_history_dir_path();
+        self.dir_builder()
+            .recursive(true)
+            .create(&history_path)
+            .context(IoOp::CreateDir, &history_path)?;
+        let mut id = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_nanos())
+            .unwrap_or_default();
+        let prefix = self.get_snapshot_prefix();
+        let mut snapshot_path = history_path.join(format!("{}{}", prefix, id));
+        // Writes within the clock's resolution get the next free id.
+        while snapshot_path.exists() {
+            id += 1;
+            snapshot_path = history_path.join(format!("{}{}", prefix, id));
+        }
+        self.file_options()
+            .write(true)
+            .create_new(true)
+            .open(&snapshot_path)
+            .context(IoOp::Create, &snapshot_path)?
+            .write_all(data)
+            .context(IoOp::Write, &snapshot_path)?;
+        let snapshots = self.snapshots()?;
+        let stale = snapshots.len().saturating_sub(self.history);
+        for snapshot in &snapshots[..stale] {
+            fs::remove_file(&snapshot.path).context(IoOp::Remove, &snapshot.path)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the path to the snapshot with the given id.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the snapshot does not exist.
+    fn get_snapshot_path(&self, id: u128) -> Result<PathBuf> {
+        let snapshot_path =
+            self.get_history_dir_path()
+                .join(format!("{}{}", self.get_snapshot_prefix(), id));
+        match snapshot_path.exists() {
+            true => Ok(snapshot_path),
+            false => Err(Error::NotFound),
+        }
+    }
+
+    /// Returns the prefix of snapshot file names, so each configuration file has its own history.
+    fn get_snapshot_prefix(&self) -> String {
+        let store_path = self.get_store_path();
+        let file_name = store_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        format!("{}.", file_name)
+    }
+}
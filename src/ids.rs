@@ -0,0 +1,90 @@
+use crate::{Error, Result, Store};
+use json_dotpath::DotPaths;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// The key the installation's id is kept under.
+const INSTALL_ID_KEY: &str = "install_id";
+
+impl Store {
+    /// Returns the UUID at the given path, stored as a hyphenated string.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not a UUID string, with [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_uuid(&self, path: &str) -> Result<Option<Uuid>> {
+        self.get(path)?
+            .map(|value| parse_uuid(path, &value))
+            .transpose()
+    }
+
+    /// Sets the UUID at the given path as a hyphenated string.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn set_uuid(&self, path: &str, uuid: Uuid) -> Result<()> {
+        self.set(path, uuid.hyphenated().to_string())
+    }
+
+    /// Returns the id of this installation, a random UUID kept under `install_id` which is
+    /// generated and written on the first call, for anonymous telemetry or crash reports.
+    /// Concurrent first calls all return the same id.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("install-id-app").unwrap();
+    /// let id = store.install_id().unwrap();
+    /// assert_eq!(store.install_id().unwrap(), id);
+    /// assert_eq!(store.get_uuid("install_id").unwrap(), Some(id));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created or written to.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value under `install_id` is not a UUID string, with [`Error::InvalidValue`].
+    pub fn install_id(&self) -> Result<Uuid> {
+        if let Some(id) = self.get_uuid(INSTALL_ID_KEY).or_else(|e| match e {
+            Error::NotFound => Ok(None),
+            e => Err(e),
+        })? {
+            return Ok(id);
+        }
+        self.modify(|parsed_json| {
+            if let Some(value) = parsed_json.dot_get::<Value>(INSTALL_ID_KEY)? {
+                return parse_uuid(INSTALL_ID_KEY, &value);
+            }
+            let id = Uuid::new_v4();
+            parsed_json.dot_set(INSTALL_ID_KEY, id.hyphenated().to_string())?;
+            Ok(id)
+        })
+    }
+}
+
+/// Parses a UUID stored as a string.
+fn parse_uuid(path: &str, value: &Value) -> Result<Uuid> {
+    let invalid = |reason: String| Error::InvalidValue {
+        path: path.to_string(),
+        reason,
+    };
+    match value {
+        Value::String(uuid) => Uuid::parse_str(uuid).map_err(|e| invalid(e.to_string())),
+        _ => Err(invalid("expected a UUID string".to_string())),
+    }
+}
@@ -0,0 +1,177 @@
+use crate::{
+    error::IoContext,
+    lenient,
+    patch::{apply_changes, merge_values},
+    Error, IoOp, Result, Store,
+};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// The key of the directive listing the files to include.
+const INCLUDE_KEY: &str = "$include";
+
+impl Store {
+    /// Sets whether the files listed in a top-level `"$include"` directive are merged into
+    /// the store when it is read. Includes are disabled by default.
+    ///
+    /// The directive is a path or an array of paths to JSON files, relative to the file
+    /// containing it. Included files may include other files themselves. Objects are merged
+    /// recursively, with later files overriding earlier ones and the including file
+    /// overriding them all, so a shared base config can be overridden per machine.
+    ///
+    /// Writes change the store file's own contents, so the included files are never
+    /// modified. Writes which read the current value, such as [`increment`](Store::increment)
+    /// or [`compare_and_swap`](Store::compare_and_swap), see the included values, and only
+    /// the values they change are copied into the store file. A value which comes from an
+    /// included file cannot be deleted, only overridden.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let mut store = Store::new("include-app").unwrap();
+    /// store.set_includes(true);
+    /// store.init_store().unwrap();
+    /// let base = store.get_store_dir_path().join("base.json");
+    /// std::fs::write(&base, r#"{ "theme": "light", "font": { "size": 12 } }"#).unwrap();
+    ///
+    /// store.set("$include", ["base.json"]).unwrap();
+    /// store.set("font.family", "mono").unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "light");
+    /// assert_eq!(store.get("font").unwrap().unwrap(), json!({ "size": 12, "family": "mono" }));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_includes(&mut self, includes: bool) {
+        self.includes = includes;
+    }
+
+    pub fn get_includes(&self) -> bool {
+        self.includes
+    }
+
+    /// Returns the parsed JSON of the store file with its includes merged in, if includes
+    /// are enabled, and the active environment's section resolved.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file or an included file cannot be read or deserialized, or
+    /// the files include each other.
+    pub(crate) fn get_resolved_json(&self) -> Result<Value> {
+        let value = self.merge_includes(self.get_store_as_parsed_json()?)?;
+        Ok(self.resolve_environment(value))
+    }
+
+    /// Runs `f` on the contents of the store as they are read, with its includes merged in
+    /// and the active environment's section resolved, and applies the changes it makes to
+    /// `value`, the contents of the store file. With an environment active the changes are
+    /// applied to its section.
+    ///
+    /// # Errors
+    ///
+    /// Errors if an included file cannot be read or deserialized, the files include each
+    /// other, or `f` errors.
+    pub(crate) fn modify_resolved<R, F>(&self, value: &mut Value, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Value) -> Result<R>,
+    {
+        let resolved = self.resolve_environment(self.merge_includes(value.clone())?);
+        let mut modified = resolved.clone();
+        let result = f(&mut modified)?;
+        match self.get_environment() {
+            Some(environment) => {
+                if !value.is_object() {
+                    *value = Value::Object(Default::default());
+                }
+                if let Value::Object(sections) = value {
+                    let section = sections
+                        .entry(environment)
+                        .or_insert_with(|| Value::Object(Default::default()));
+                    apply_changes(section, &resolved, &modified);
+                }
+            }
+            None => apply_changes(value, &resolved, &modified),
+        }
+        Ok(result)
+    }
+
+    /// Returns the contents of the store file with its includes merged in, or unchanged if
+    /// includes are disabled.
+    ///
+    /// # Errors
+    ///
+    /// Errors if an included file cannot be read or deserialized, or the files include
+    /// each other.
+    pub(crate) fn merge_includes(&self, value: Value) -> Result<Value> {
+        if !self.includes {
+            return Ok(value);
+        }
+        let store_path = self.get_store_path();
+        let store_path = fs::canonicalize(&store_path).context(IoOp::Read, &store_path)?;
+        resolve_includes(value, &store_path, &mut Vec::new())
+    }
+}
+
+/// Merges the files included by `value`, which was read from `path`, into it.
+/// `chain` holds the files including it, to detect cycles.
+///
+/// # Errors
+///
+/// Errors if an included file cannot be read or deserialized, or the files include
+/// each other.
+fn resolve_includes(mut value: Value, path: &Path, chain: &mut Vec<PathBuf>) -> Result<Value> {
+    let includes = match value
+        .as_object_mut()
+        .and_then(|map| map.remove(INCLUDE_KEY))
+    {
+        Some(Value::String(include)) => vec![include],
+        Some(Value::Array(includes)) => includes
+            .into_iter()
+            .map(|include| match include {
+                Value::String(include) => Ok(include),
+                _ => Err(invalid_include("paths must be strings".to_string())),
+            })
+            .collect::<Result<_>>()?,
+        Some(_) => {
+            return Err(invalid_include(
+                "must be a path or an array of paths".to_string(),
+            ))
+        }
+        None => return Ok(value),
+    };
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    chain.push(path.to_path_buf());
+    let mut resolved = Value::Object(Default::default());
+    for include in includes {
+        let include_path = dir.join(include);
+        let include_path = fs::canonicalize(&include_path).context(IoOp::Read, &include_path)?;
+        if chain.contains(&include_path) {
+            return Err(invalid_include(format!(
+                "{} includes itself",
+                include_path.display()
+            )));
+        }
+        let data = fs::read(&include_path).context(IoOp::Read, &include_path)?;
+        let included = lenient::from_slice(&data).map_err(|source| Error::Corrupt {
+            config: include_path.display().to_string(),
+            source,
+        })?;
+        merge_values(
+            &mut resolved,
+            resolve_includes(included, &include_path, chain)?,
+        );
+    }
+    chain.pop();
+    merge_values(&mut resolved, value);
+    Ok(resolved)
+}
+
+fn invalid_include(reason: String) -> Error {
+    Error::InvalidValue {
+        path: INCLUDE_KEY.to_string(),
+        reason,
+    }
+}
@@ -0,0 +1,172 @@
+use crate::{path::join_path, Error, Result, Store};
+use json_dotpath::DotPaths;
+use serde_json::Value;
+use std::env;
+
+/// The prefix of variables referring to another value in the store.
+const CONFIG_PREFIX: &str = "config:";
+
+/// Whether and how variables in string values are expanded when the store is read.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Interpolation {
+    /// String values are returned as they are stored.
+    #[default]
+    Off,
+    /// Variables are expanded, undefined variables expand to an empty string.
+    Lenient,
+    /// Variables are expanded, undefined variables are an error.
+    Strict,
+}
+
+impl Store {
+    /// Sets whether variables in string values are expanded when the store is read,
+    /// defaults to [`Interpolation::Off`].
+    ///
+    /// `${NAME}` expands to the environment variable `NAME`, and `${config:dot.path}` to the
+    /// value at the dot path in the store, itself expanded. Values other than strings are
+    /// inserted as JSON. `$$` is a literal `$`, and a `$` followed by anything else is kept.
+    ///
+    /// Variables are expanded in the values returned by [`get`](Store::get),
+    /// [`get_all`](Store::get_all), [`iter`](Store::iter) and [`to_value`](Store::to_value).
+    /// Writes store the values as they are given.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{ErrorKind, Interpolation, Store};
+    /// let mut store = Store::new("interpolation-app").unwrap();
+    /// store.set_interpolation(Interpolation::Strict);
+    /// std::env::set_var("INTERPOLATION_APP_HOME", "/home/me");
+    /// store.set("paths.root", "${INTERPOLATION_APP_HOME}/app").unwrap();
+    /// store.set("paths.cache", "${config:paths.root}/cache").unwrap();
+    /// store.set("price", "$$5").unwrap();
+    /// assert_eq!(store.get("paths.cache").unwrap().unwrap(), "/home/me/app/cache");
+    /// assert_eq!(store.get("price").unwrap().unwrap(), "$5");
+    ///
+    /// store.set("missing", "${config:nothing}").unwrap();
+    /// let error = store.get("missing").unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::InvalidValue);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_interpolation(&mut self, interpolation: Interpolation) {
+        self.interpolation = interpolation;
+    }
+
+    pub fn get_interpolation(&self) -> Interpolation {
+        self.interpolation
+    }
+
+    /// Expands the variables in every string of `value`, read from the given path of the
+    /// store's contents `root`, unless interpolation is off.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a variable is malformed, refers to itself, or is undefined in strict mode.
+    pub(crate) fn interpolate(&self, root: &Value, path: &str, value: Value) -> Result<Value> {
+        if self.interpolation == Interpolation::Off {
+            return Ok(value);
+        }
+        let mut interpolator = Interpolator {
+            root,
+            strict: self.interpolation == Interpolation::Strict,
+            expanding: Vec::new(),
+        };
+        interpolator.expand_value(path, &value)
+    }
+}
+
+/// Expands variables against the contents of a store.
+struct Interpolator<'a> {
+    /// The contents of the store `${config:...}` variables refer to.
+    root: &'a Value,
+    /// Whether undefined variables are an error.
+    strict: bool,
+    /// The dot paths of the values being expanded, to detect cycles.
+    expanding: Vec<String>,
+}
+
+impl<'a> Interpolator<'a> {
+    fn expand_value(&mut self, path: &str, value: &Value) -> Result<Value> {
+        Ok(match value {
+            Value::String(s) => Value::String(self.expand_str(path, s)?),
+            Value::Array(array) => Value::Array(
+                array
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| self.expand_value(&join_path(path, &i.to_string()), value))
+                    .collect::<Result<_>>()?,
+            ),
+            Value::Object(map) => Value::Object(
+                map.iter()
+                    .map(|(key, value)| {
+                        Ok((
+                            key.clone(),
+                            self.expand_value(&join_path(path, key), value)?,
+                        ))
+                    })
+                    .collect::<Result<_>>()?,
+            ),
+            value => value.clone(),
+        })
+    }
+
+    /// Expands the variables in the string at the given path.
+    fn expand_str(&mut self, path: &str, s: &str) -> Result<String> {
+        let mut expanded = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find('$') {
+            expanded.push_str(&rest[..start]);
+            rest = &rest[start + 1..];
+            if let Some(after) = rest.strip_prefix('$') {
+                expanded.push('$');
+                rest = after;
+            } else if let Some(after) = rest.strip_prefix('{') {
+                let end = after
+                    .find('}')
+                    .ok_or_else(|| invalid(path, "unterminated variable".to_string()))?;
+                expanded.push_str(&self.expand_variable(path, &after[..end])?);
+                rest = &after[end + 1..];
+            } else {
+                expanded.push('$');
+            }
+        }
+        expanded.push_str(rest);
+        Ok(expanded)
+    }
+
+    /// Returns the value of the variable with the given name, used in the string at `path`.
+    fn expand_variable(&mut self, path: &str, name: &str) -> Result<String> {
+        let value = match name.strip_prefix(CONFIG_PREFIX) {
+            Some(config_path) => match self.root.dot_get::<Value>(config_path)? {
+                Some(Value::String(s)) => {
+                    if self
+                        .expanding
+                        .iter()
+                        .any(|expanding| expanding == config_path)
+                    {
+                        return Err(invalid(path, format!("${{{}}} refers to itself", name)));
+                    }
+                    self.expanding.push(config_path.to_string());
+                    let expanded = self.expand_str(config_path, &s);
+                    self.expanding.pop();
+                    Some(expanded?)
+                }
+                Some(value) => Some(value.to_string()),
+                None => None,
+            },
+            None => env::var(name).ok(),
+        };
+        match value {
+            Some(value) => Ok(value),
+            None if self.strict => Err(invalid(path, format!("${{{}}} is undefined", name))),
+            None => Ok(String::new()),
+        }
+    }
+}
+
+fn invalid(path: &str, reason: String) -> Error {
+    Error::InvalidValue {
+        path: path.to_string(),
+        reason,
+    }
+}
@@ -0,0 +1,306 @@
+use crate::{error::IoContext, patch, ChangeSource, IoOp, PatchOp, Result, Store};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// The first line of a journal, identifying the store file its entries apply to.
+#[derive(Serialize, Deserialize)]
+struct JournalHeader {
+    /// The CRC32 of the store file the journal was started on.
+    base: u32,
+}
+
+/// The CRC32 of a store file, kept with the file's modification time and length so the
+/// file is only read again once it changed.
+pub(crate) struct JournalBase {
+    /// The path to the store file.
+    path: PathBuf,
+    /// The modification time and length of the store file.
+    stat: (SystemTime, u64),
+    /// The CRC32 of the store file.
+    crc: u32,
+}
+
+impl Store {
+    /// Sets whether mutations are appended to a journal next to the store file instead of
+    /// rewriting it, journaling is disabled by default.
+    ///
+    /// Each mutation is appended as a line of JSON Patch operations to
+    /// [`get_journal_path`](Store::get_journal_path), so writes cost the size of the change
+    /// rather than the size of the store. The journal is compacted into the store file once
+    /// it grows larger than the store file, or by [`compact`](Store::compact).
+    ///
+    /// The journal holds changes in plain text, so encrypted, signed and compressed stores
+    /// are always rewritten in full. Deferred writes are not journaled either, nor are
+    /// stores which keep backups or history, as those are taken of whole store files.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("journal-app").unwrap();
+    /// store.set_journal(true);
+    /// store.set("theme", "dark").unwrap();
+    /// for i in 0..10 {
+    ///     store.set("counter", i).unwrap();
+    /// }
+    /// assert_eq!(store.get("counter").unwrap().unwrap(), 9);
+    /// store.compact().unwrap();
+    /// assert!(!store.get_journal_path().exists());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_journal(&mut self, journal: bool) {
+        self.journal = journal;
+    }
+
+    pub fn get_journal(&self) -> bool {
+        self.journal
+    }
+
+    /// Get the path to the journal of the store file, the store file's path with the
+    /// `journal` extension.
+    pub fn get_journal_path(&self) -> PathBuf {
+        self.get_store_path().with_extension("journal")
+    }
+
+    /// Writes the store file with the journal's changes applied and removes the journal.
+    /// Does nothing if there is no journal.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store cannot be read or written to.
+    pub fn compact(&self) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        if !self.get_journal_path().exists() {
+            return Ok(());
+        }
+        self.write_value(self.get_store_as_parsed_json()?)
+    }
+
+    /// Appends the change from `previous` to `value` to the journal when journaling applies,
+    /// compacting the journal once it is larger than the store file. Otherwise the store is
+    /// written or deferred as usual.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store would exceed its maximum size or the journal or the store file
+    /// cannot be written to.
+    pub(crate) fn write_journaled(&self, previous: &Value, value: Value) -> Result<()> {
+        if !self.journal_applies() || !self.store_exists() {
+            return self.write_or_defer(value);
+        }
+        self.check_max_size(&value)?;
+        self.record_metrics(|metrics| metrics.record_set());
+        let mut ops = Vec::new();
+        patch::diff("", previous, &value, &mut ops);
+        let journal_len = self.append_journal(&ops)?;
+        self.publish_changes(&value, ChangeSource::Internal);
+        let store_path = self.get_store_path();
+        let store_len = fs::metadata(&store_path)
+            .context(IoOp::Metadata, &store_path)?
+            .len();
+        if journal_len > store_len {
+            self.write_value(value)?;
+        }
+        Ok(())
+    }
+
+    /// Reads the raw contents of the journal, or `None` if there is no journal.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the journal exists but cannot be read.
+    pub(crate) fn read_journal_file(&self) -> Result<Option<Vec<u8>>> {
+        let journal_path = self.get_journal_path();
+        let journal = match fs::read(&journal_path) {
+            Ok(journal) => journal,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e).context(IoOp::Read, &journal_path),
+        };
+        self.record_metrics(|metrics| metrics.record_bytes_read(journal.len()));
+        Ok(Some(journal))
+    }
+
+    /// Decodes and parses the raw contents of a store file and applies the operations
+    /// recorded in its journal.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be decoded or deserialized or an operation fails.
+    pub(crate) fn parse_journaled(
+        &self,
+        store_data: Vec<u8>,
+        journal: Option<&[u8]>,
+    ) -> Result<Value> {
+        let ops = journal
+            .map(|journal| parse_journal(journal, &store_data))
+            .unwrap_or_default();
+        let mut value = self.parse_store_data(store_data)?;
+        for op in &ops {
+            patch::apply_op(&mut value, op)?;
+        }
+        Ok(value)
+    }
+
+    /// Removes the journal after the store file was rewritten.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the journal exists but cannot be removed.
+    pub(crate) fn remove_journal(&self) -> Result<()> {
+        let journal_path = self.get_journal_path();
+        match fs::remove_file(&journal_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).context(IoOp::Remove, &journal_path)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns a boolean indicating whether mutations are journaled, which needs the
+    /// store file to be plain JSON.
+    fn journal_applies(&self) -> bool {
+        #[cfg(feature = "crypto")]
+        if self.encryption_key.is_some() || self.signing_key.is_some() {
+            return false;
+        }
+        #[cfg(feature = "electron")]
+        if self.has_electron_encryption() {
+            return false;
+        }
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return false;
+        }
+        #[cfg(feature = "sharding")]
+        if self.shards_apply() {
+            return false;
+        }
+        #[cfg(feature = "history")]
+        if self.history != 0 {
+            return false;
+        }
+        self.journal && !self.deferred && self.backup.keep == 0
+    }
+
+    /// Appends a line with the operations to the journal, starting a new journal on the
+    /// current store file if there is none or it is stale. Returns the journal's length.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be read or the journal cannot be written to.
+    fn append_journal(&self, ops: &[PatchOp]) -> Result<u64> {
+        let journal_path = self.get_journal_path();
+        let base = self.get_journal_base(&self.get_store_path())?;
+        let mut journal = self
+            .file_options()
+            .read(true)
+            .append(true)
+            .create(true)
+            .open(&journal_path)
+            .context(IoOp::Create, &journal_path)?;
+        let mut entry = Vec::new();
+        if journal_is_current(&mut journal, base).context(IoOp::Read, &journal_path)? {
+            // A partially written last line is cut off, as entries after it would be ignored.
+            truncate_partial_line(&mut journal).context(IoOp::Write, &journal_path)?;
+        } else {
+            journal.set_len(0).context(IoOp::Write, &journal_path)?;
+            serde_json::to_writer(&mut entry, &JournalHeader { base })?;
+            entry.push(b'\n');
+        }
+        serde_json::to_writer(&mut entry, ops)?;
+        entry.push(b'\n');
+        journal
+            .write_all(&entry)
+            .context(IoOp::Write, &journal_path)?;
+        self.sync_file(&journal, &journal_path)?;
+        self.advance_generation()?;
+        self.invalidate_cache();
+        self.record_metrics(|metrics| metrics.record_bytes_written(entry.len()));
+        Ok(journal
+            .metadata()
+            .context(IoOp::Metadata, &journal_path)?
+            .len())
+    }
+
+    /// Returns the CRC32 of the store file, which is only read if it changed since the
+    /// last time.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be read.
+    fn get_journal_base(&self, store_path: &Path) -> Result<u32> {
+        let metadata = fs::metadata(store_path).context(IoOp::Metadata, store_path)?;
+        let stat = metadata
+            .modified()
+            .ok()
+            .map(|modified| (modified, metadata.len()));
+        let mut journal_base = self.journal_base.lock().unwrap_or_else(|e| e.into_inner());
+        match journal_base.as_ref() {
+            Some(base) if base.path == store_path && Some(base.stat) == stat => return Ok(base.crc),
+            _ => {}
+        }
+        let crc = crc32fast::hash(&fs::read(store_path).context(IoOp::Read, store_path)?);
+        *journal_base = stat.map(|stat| JournalBase {
+            path: store_path.to_path_buf(),
+            stat,
+            crc,
+        });
+        Ok(crc)
+    }
+}
+
+/// Returns the operations recorded in the journal of the store file with the given contents.
+///
+/// A journal started on other contents was left behind by an interrupted compaction,
+/// its changes are already in the store file and it is ignored. Entries are read up to
+/// the first line which cannot be parsed, such as a last line which was only partially
+/// written, so no state is built which never existed.
+fn parse_journal(journal: &[u8], store_data: &[u8]) -> Vec<PatchOp> {
+    let mut lines = journal.split(|byte| *byte == b'\n');
+    let header = lines
+        .next()
+        .and_then(|line| serde_json::from_slice::<JournalHeader>(line).ok());
+    match header {
+        Some(header) if header.base == crc32fast::hash(store_data) => lines
+            .map_while(|line| serde_json::from_slice::<Vec<PatchOp>>(line).ok())
+            .flatten()
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Returns a boolean indicating whether the journal's header names the store file with the
+/// given CRC32, rather than the journal being empty or left behind by an earlier store file.
+fn journal_is_current(journal: &mut File, base: u32) -> io::Result<bool> {
+    journal.seek(SeekFrom::Start(0))?;
+    let mut header = String::new();
+    BufReader::new(&mut *journal).read_line(&mut header)?;
+    Ok(serde_json::from_str::<JournalHeader>(header.trim_end())
+        .is_ok_and(|header| header.base == base))
+}
+
+/// Cuts off the journal after its last newline, removing a partially written last line.
+fn truncate_partial_line(journal: &mut File) -> io::Result<()> {
+    journal.seek(SeekFrom::End(-1))?;
+    let mut last = [0; 1];
+    journal.read_exact(&mut last)?;
+    if last[0] == b'\n' {
+        return Ok(());
+    }
+    let mut contents = Vec::new();
+    journal.seek(SeekFrom::Start(0))?;
+    journal.read_to_end(&mut contents)?;
+    let complete = contents
+        .iter()
+        .rposition(|byte| *byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    journal.set_len(complete as u64)
+}
@@ -0,0 +1,21 @@
+use serde_json::Value;
+
+/// Deserializes a store file or included file. With the `json5` feature, files which are not
+/// valid JSON are read as JSON5, so hand edited files may have comments, trailing commas,
+/// unquoted keys and single quoted strings.
+///
+/// # Errors
+///
+/// Errors if the data is neither valid JSON nor, with the `json5` feature, valid JSON5.
+pub(crate) fn from_slice(data: &[u8]) -> serde_json::Result<Value> {
+    let error = match serde_json::from_slice(data) {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+    #[cfg(feature = "json5")]
+    if error.is_syntax() {
+        let data = std::str::from_utf8(data).map_err(serde::de::Error::custom)?;
+        return json5::from_str(data).map_err(serde::de::Error::custom);
+    }
+    Err(error)
+}
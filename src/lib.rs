@@ -1,49 +1,327 @@
+mod array;
+mod backup;
+#[cfg(feature = "blobs")]
+mod blob;
+#[cfg(feature = "base64")]
+mod bytes;
+pub use backup::{Backup, BackupPolicy, Recovery};
+mod cache;
+pub use cache::FreshnessPolicy;
+mod checksum;
+#[cfg(feature = "compression")]
+mod compression;
+mod configs;
+#[cfg(feature = "chrono")]
+mod datetime;
+mod deferred;
+mod describe;
+mod dotenv;
+mod durability;
+mod edit;
+#[cfg(feature = "electron")]
+mod electron;
+mod environment;
+#[cfg(feature = "compression")]
+pub use compression::CompressionAlgorithm;
+pub use durability::Durability;
 #[cfg(feature = "crypto")]
 mod crypto;
+#[cfg(feature = "crypto")]
+pub use crypto::{CipherSuite, EncryptionKey};
+#[cfg(feature = "crypto")]
+pub use zeroize::Zeroizing;
 /// A simple to use config storage library for Rust.
 mod error;
-pub use error::Error;
-#[cfg(feature = "compression")]
-use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+mod flags;
+mod format;
+pub use flags::{Flag, Flags};
+pub use format::{Indent, PrettyStyle};
+mod guard;
+#[cfg(feature = "history")]
+mod history;
+#[cfg(feature = "uuid")]
+mod ids;
+mod include;
+mod interpolate;
+pub use interpolate::Interpolation;
+mod journal;
+mod lenient;
+mod location;
+pub use location::{DirProvider, StoreKind, SystemDirs};
+mod lock;
+mod macros;
+mod manager;
+mod metadata;
+pub use metadata::{StoreMetadata, StoreStats};
+mod metrics;
+#[cfg(feature = "mmap")]
+mod mmap;
+mod namespace;
+pub use manager::StoreManager;
+pub use metrics::Metrics;
+pub use namespace::{Namespace, NamespaceOptions, NamespaceSchema};
+mod observer;
+pub use observer::{ChangeEvent, ChangeSource};
+mod partial;
+mod patch;
+mod path;
+mod permissions;
+pub use path::{escape_key, DotPath};
+pub use permissions::Permissions;
+mod pointer;
+#[cfg(feature = "jsonpath")]
+mod query;
+mod quota;
+mod scoped;
+pub use scoped::ScopedStore;
+#[cfg(feature = "sharding")]
+mod shard;
+mod shared;
+#[cfg(all(feature = "signals", unix))]
+mod signals;
+pub mod testing;
+mod trace;
+mod typed;
+mod undo;
+#[cfg(feature = "crypto")]
+use base64::Engine;
+use error::IoContext;
+pub use error::{Error, ErrorKind, IoOp};
+pub use guard::StoreGuard;
+#[cfg(feature = "history")]
+pub use history::{Change, Snapshot};
 use json_dotpath::DotPaths;
-use serde::Serialize;
+pub use patch::PatchOp;
+use serde::{de::DeserializeOwned, Serialize};
 use serde_json::{self, Value};
-#[cfg(feature = "compression")]
-use std::io::{Read, Write};
+pub use shared::SharedStore;
+use trace::{trace_done, trace_info, trace_span};
 
 use std::{
+    borrow::Cow,
+    collections::{BTreeMap, VecDeque},
+    env,
     fs::{self, File},
+    io::Write,
     path::{Path, PathBuf},
     result,
+    sync::{mpsc::Sender, Arc, Mutex},
 };
 
+/// Overwrites the contents of a file with zeros and flushes them to disk.
+#[cfg(feature = "crypto")]
+fn overwrite_with_zeros(mut file: &File) -> std::io::Result<()> {
+    let len = file.metadata()?.len();
+    let zeros = [0; 4096];
+    let mut written = 0;
+    while written < len {
+        let chunk = (len - written).min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..chunk])?;
+        written += chunk as u64;
+    }
+    file.sync_all()
+}
+
+/// Overwrites a file with zeros and removes it.
+///
+/// # Errors
+///
+/// Errors if the file cannot be overwritten or removed.
+#[cfg(feature = "crypto")]
+fn wipe_file(path: &Path) -> Result<()> {
+    let file = fs::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .context(IoOp::Write, path)?;
+    overwrite_with_zeros(&file).context(IoOp::Write, path)?;
+    fs::remove_file(path).context(IoOp::Remove, path)
+}
+
+/// The contents of a store file once decrypted, read into memory or mapped.
+pub(crate) enum Decoded<'a> {
+    Plain(Cow<'a, [u8]>),
+    /// Decrypted data, which is wiped from memory when dropped.
+    #[cfg(feature = "crypto")]
+    Decrypted(Zeroizing<Vec<u8>>),
+    /// Data to be decompressed with the given algorithm.
+    #[cfg(feature = "compression")]
+    Compressed(Cow<'a, [u8]>, compression::CompressionAlgorithm),
+}
+
+impl<'a> Decoded<'a> {
+    /// Returns the decoded data, decompressing it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be decompressed.
+    fn into_bytes(self) -> Result<DecodedBytes<'a>> {
+        match self {
+            Decoded::Plain(data) => Ok(DecodedBytes::Plain(data)),
+            #[cfg(feature = "crypto")]
+            Decoded::Decrypted(data) => Ok(DecodedBytes::Decrypted(data)),
+            #[cfg(feature = "compression")]
+            Decoded::Compressed(data, algorithm) => compression::decompress_data(&data, algorithm)
+                .map(|data| DecodedBytes::Plain(Cow::Owned(data))),
+        }
+    }
+}
+
+/// The decompressed contents of a store file, which are wiped from memory when dropped
+/// if they were decrypted.
+pub(crate) enum DecodedBytes<'a> {
+    Plain(Cow<'a, [u8]>),
+    #[cfg(feature = "crypto")]
+    Decrypted(Zeroizing<Vec<u8>>),
+}
+
+impl std::ops::Deref for DecodedBytes<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            DecodedBytes::Plain(data) => data,
+            #[cfg(feature = "crypto")]
+            DecodedBytes::Decrypted(data) => data,
+        }
+    }
+}
+
+/// Maps an error reading a store file which does not exist to [`Error::NotFound`].
+fn missing_store(e: Error) -> Error {
+    match e {
+        Error::Io { source, .. } if source.kind() == std::io::ErrorKind::NotFound => {
+            Error::NotFound
+        }
+        e => e,
+    }
+}
+
 /// Convenience type for resulting from a `Result<T>` using [`Result`].
 ///
 /// [`Result`]: `https://doc.rust-lang.org/std/result/enum.Result.html`
 pub type Result<T> = result::Result<T, Error>;
 
 /// Represents a store of configuration data in a JSON format.
-pub struct Store<'a> {
+pub struct Store {
     /// The base directory for the store.
     path: PathBuf,
+    /// Resolves the base directory of each kind of store.
+    dir_provider: Arc<dyn DirProvider>,
+    /// The kind of data the store holds.
+    kind: StoreKind,
+    /// Whether writes are refused with [`Error::ReadOnly`].
+    read_only: bool,
+    /// Whether mutations create a missing store.
+    create_missing: bool,
+    /// The permissions of created directories and files.
+    permissions: Permissions,
+    /// How far writes are flushed to disk.
+    durability: Durability,
+    /// Receives counters of the store's activity.
+    metrics: Option<Arc<dyn Metrics>>,
+    /// Faults injected into reads and writes of the store file.
+    #[cfg(feature = "test-util")]
+    faults: Option<testing::FaultInjector>,
+    /// The environment variable overriding the store directory.
+    dir_env: Option<String>,
     /// The project's name
-    project_name: &'a str,
+    project_name: String,
     /// The configuration name
-    config_name: &'a str,
+    config_name: String,
     /// The file extension for configuration files.
-    file_extension: &'a str,
+    file_extension: String,
     /// The project name's suffix
-    project_suffix: Option<&'a str>,
-    /// Whether the configuration files should be human readable or not.
-    pretty: bool,
+    project_suffix: Option<String>,
+    /// The active profile, stored as `<config_name>.<profile>.<file_extension>`.
+    profile: Option<String>,
+    /// How the configuration files are laid out to be human readable, `None` for compact.
+    pretty: Option<PrettyStyle>,
+    /// Whether the keys of objects are sorted when the store file is written.
+    sort_keys: bool,
+    /// Whether writes only rewrite the changed values of the store file.
+    preserve_formatting: bool,
+    /// Whether the typed accessors convert values stored as other types.
+    coerce_types: bool,
+    /// The contents of a new or cleared store.
+    defaults: Option<Value>,
+    /// Whether files listed in an `$include` directive are merged into the store on reads.
+    includes: bool,
+    /// The section overlaid on the `default` section on reads, `None` to read the whole store.
+    environment: Option<String>,
+    /// Whether variables in string values are expanded on reads.
+    interpolation: Interpolation,
+    /// How many previous versions of the store file are kept.
+    backup: BackupPolicy,
+    /// Whether a CRC32 checksum footer is written and verified.
+    checksum: bool,
+    /// Whether mutations are held in memory until flushed.
+    deferred: bool,
+    /// Whether mutations are appended to a journal instead of rewriting the store file.
+    journal: bool,
+    /// The CRC32 of the store file the journal was last appended to.
+    journal_base: Mutex<Option<journal::JournalBase>>,
+    /// How often cached contents are checked against the store file, `None` if the
+    /// contents are not cached.
+    cache_policy: Option<FreshnessPolicy>,
+    /// The cached contents of the store file.
+    cache: Mutex<Option<cache::CachedStore>>,
+    /// The deferred contents of the store, `Some` while there are unwritten changes.
+    pending: Mutex<Option<Value>>,
+    /// The number of deferred writes, which count towards the generation.
+    pending_generations: Mutex<u64>,
+    /// How many mutations can be undone.
+    undo_depth: usize,
+    /// The contents of the store before each of the most recent mutations, newest last.
+    undo_log: Mutex<VecDeque<Value>>,
+    /// The callbacks registered with `on_change`, along with the path prefix they observe.
+    observers: Vec<(String, observer::ChangeCallback)>,
+    /// The namespaces registered with `register_namespace`, by name.
+    namespaces: BTreeMap<String, NamespaceOptions>,
+    /// The largest size in bytes the serialized store may grow to.
+    max_size: Option<usize>,
+    /// The senders of the receivers returned by `subscribe`.
+    subscribers: Mutex<Vec<Sender<ChangeEvent>>>,
+    /// The contents of the store the subscribers were last sent changes up to.
+    last_seen: Mutex<Option<Value>>,
+    /// The number of snapshots of the store file kept, one taken after every write.
+    #[cfg(feature = "history")]
+    history: usize,
     /// An optional encrpytion key for the store.
     #[cfg(feature = "crypto")]
-    encryption_key: Option<[u8; 32]>,
+    encryption_key: Option<crypto::EncryptionKey>,
+    /// The parameters and key last derived from the password, so the key derivation
+    /// only runs when the parameters change.
+    #[cfg(feature = "crypto")]
+    derived_key: Mutex<Option<(crypto::KdfParams, Zeroizing<[u8; 32]>)>>,
+    /// The electron-store compatibility settings, `None` for bland's own format.
+    #[cfg(feature = "electron")]
+    electron: Option<electron::ElectronCompat>,
+    /// Whether the store file is read through a memory map.
+    #[cfg(feature = "mmap")]
+    mmap: bool,
+    /// Whether top-level sections are written to their own files.
+    #[cfg(feature = "sharding")]
+    sharded: bool,
+    /// The cipher used when writing encrypted stores.
+    #[cfg(feature = "crypto")]
+    cipher_suite: crypto::CipherSuite,
+    /// The paths whose values are encrypted individually, leaving the rest of the store in plain text.
+    #[cfg(feature = "crypto")]
+    encrypted_paths: Vec<String>,
+    /// An optional key the store file is signed with using HMAC-SHA256.
+    #[cfg(feature = "crypto")]
+    signing_key: Option<Vec<u8>>,
     #[cfg(feature = "compression")]
     compressed: bool,
+    /// The algorithm used to compress the store.
+    #[cfg(feature = "compression")]
+    compression_algorithm: compression::CompressionAlgorithm,
+    /// The compression level, from 0 (no compression) to 9 (best compression).
+    #[cfg(feature = "compression")]
+    compression_level: u32,
 }
 
-impl<'a> Store<'a> {
+impl Store {
     /// Creates a new instance of the store requiring the project's name.
     /// This name will be used as the folder name to store the configuration data.
     /// The default store location is the application configuration directory.
@@ -56,29 +334,210 @@ impl<'a> Store<'a> {
     /// # use bland::Store;
     /// let store = Store::new("my-app").unwrap();
     /// ```
-    pub fn new(project_name: &'static str) -> Result<Self> {
-        match dirs::config_dir() {
-            Some(base_dirs) => {
-                let root_path = base_dirs;
-                Ok(Self {
-                    path: root_path,
-                    project_name,
-                    config_name: "config",
-                    file_extension: "json",
-                    project_suffix: Some("rs"),
-                    pretty: false,
-                    #[cfg(feature = "crypto")]
-                    encryption_key: None,
-                    #[cfg(feature = "compression")]
-                    compressed: false,
-                })
-            }
+    pub fn new(project_name: &str) -> Result<Self> {
+        Store::with_dir_provider(project_name, SystemDirs)
+    }
+
+    /// Creates a new instance of the store whose location is resolved by the given
+    /// [`DirProvider`] instead of the platform's directories.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{DirProvider, Store};
+    /// # use std::path::PathBuf;
+    /// struct Sandbox;
+    ///
+    /// impl DirProvider for Sandbox {
+    ///     fn config_dir(&self) -> Option<PathBuf> {
+    ///         Some(PathBuf::from("./sandbox"))
+    ///     }
+    /// }
+    ///
+    /// let store = Store::with_dir_provider("my-app", Sandbox).unwrap();
+    /// assert_eq!(store.get_store_dir_path(), PathBuf::from("./sandbox/my-app-rs"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the provider cannot find a config directory.
+    pub fn with_dir_provider<P>(project_name: &str, provider: P) -> Result<Self>
+    where
+        P: DirProvider,
+    {
+        match provider.config_dir() {
+            Some(root_path) => Ok(Self {
+                path: root_path,
+                dir_provider: Arc::new(provider),
+                kind: StoreKind::Config,
+                read_only: false,
+                create_missing: true,
+                permissions: Permissions::Private,
+                durability: Durability::Fast,
+                metrics: None,
+                #[cfg(feature = "test-util")]
+                faults: None,
+                dir_env: None,
+                project_name: project_name.to_owned(),
+                config_name: "config".to_owned(),
+                file_extension: "json".to_owned(),
+                project_suffix: Some("rs".to_owned()),
+                profile: None,
+                pretty: None,
+                sort_keys: false,
+                preserve_formatting: false,
+                coerce_types: false,
+                defaults: None,
+                includes: false,
+                environment: None,
+                interpolation: Interpolation::Off,
+                backup: BackupPolicy::default(),
+                checksum: false,
+                deferred: false,
+                journal: false,
+                journal_base: Mutex::new(None),
+                cache_policy: None,
+                cache: Mutex::new(None),
+                pending: Mutex::new(None),
+                pending_generations: Mutex::new(0),
+                undo_depth: 0,
+                undo_log: Mutex::new(VecDeque::new()),
+                observers: Vec::new(),
+                namespaces: BTreeMap::new(),
+                max_size: None,
+                subscribers: Mutex::new(Vec::new()),
+                last_seen: Mutex::new(None),
+                #[cfg(feature = "history")]
+                history: 0,
+                #[cfg(feature = "crypto")]
+                encryption_key: None,
+                #[cfg(feature = "crypto")]
+                derived_key: Mutex::new(None),
+                #[cfg(feature = "crypto")]
+                cipher_suite: crypto::CipherSuite::default(),
+                #[cfg(feature = "electron")]
+                electron: None,
+                #[cfg(feature = "mmap")]
+                mmap: false,
+                #[cfg(feature = "sharding")]
+                sharded: false,
+                #[cfg(feature = "crypto")]
+                encrypted_paths: Vec::new(),
+                #[cfg(feature = "crypto")]
+                signing_key: None,
+                #[cfg(feature = "compression")]
+                compressed: false,
+                #[cfg(feature = "compression")]
+                compression_algorithm: compression::CompressionAlgorithm::default(),
+                #[cfg(feature = "compression")]
+                compression_level: 6,
+            }),
             None => Err(Error::ConfigDir),
         }
     }
 
+    /// Returns a store with the same location and settings, without the callbacks,
+    /// namespaces and subscribers registered on this store or its cached contents.
+    pub(crate) fn clone_settings(&self) -> Store {
+        Store {
+            path: self.path.clone(),
+            dir_provider: Arc::clone(&self.dir_provider),
+            kind: self.kind,
+            read_only: self.read_only,
+            create_missing: self.create_missing,
+            permissions: self.permissions,
+            durability: self.durability,
+            metrics: self.metrics.clone(),
+            #[cfg(feature = "test-util")]
+            faults: self.faults.clone(),
+            dir_env: self.dir_env.clone(),
+            project_name: self.project_name.clone(),
+            config_name: self.config_name.clone(),
+            file_extension: self.file_extension.clone(),
+            project_suffix: self.project_suffix.clone(),
+            profile: self.profile.clone(),
+            pretty: self.pretty,
+            sort_keys: self.sort_keys,
+            preserve_formatting: self.preserve_formatting,
+            coerce_types: self.coerce_types,
+            defaults: self.defaults.clone(),
+            includes: self.includes,
+            environment: self.environment.clone(),
+            interpolation: self.interpolation,
+            backup: self.backup,
+            checksum: self.checksum,
+            deferred: self.deferred,
+            journal: self.journal,
+            journal_base: Mutex::new(None),
+            cache_policy: self.cache_policy,
+            cache: Mutex::new(None),
+            pending: Mutex::new(None),
+            pending_generations: Mutex::new(0),
+            undo_depth: self.undo_depth,
+            undo_log: Mutex::new(VecDeque::new()),
+            observers: Vec::new(),
+            namespaces: BTreeMap::new(),
+            max_size: self.max_size,
+            subscribers: Mutex::new(Vec::new()),
+            last_seen: Mutex::new(None),
+            #[cfg(feature = "history")]
+            history: self.history,
+            #[cfg(feature = "crypto")]
+            encryption_key: self.encryption_key.clone(),
+            #[cfg(feature = "crypto")]
+            derived_key: Mutex::new(None),
+            #[cfg(feature = "crypto")]
+            cipher_suite: self.cipher_suite,
+            #[cfg(feature = "electron")]
+            electron: self.electron.clone(),
+            #[cfg(feature = "mmap")]
+            mmap: self.mmap,
+            #[cfg(feature = "sharding")]
+            sharded: self.sharded,
+            #[cfg(feature = "crypto")]
+            encrypted_paths: self.encrypted_paths.clone(),
+            #[cfg(feature = "crypto")]
+            signing_key: self.signing_key.clone(),
+            #[cfg(feature = "compression")]
+            compressed: self.compressed,
+            #[cfg(feature = "compression")]
+            compression_algorithm: self.compression_algorithm,
+            #[cfg(feature = "compression")]
+            compression_level: self.compression_level,
+        }
+    }
+
+    /// Creates a new instance of the store named after a Cargo package, usually through the
+    /// [`store!`] macro which passes the calling crate's `CARGO_PKG_NAME` at compile time.
+    ///
+    /// With a major version, the configuration file is named `config-v<major>` so
+    /// incompatible versions of an application keep their settings apart.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::from_cargo_env("my-app", Some("2")).unwrap();
+    /// assert!(store.get_store_path().ends_with("my-app-rs/config-v2.json"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the config directory cannot be found.
+    pub fn from_cargo_env(pkg_name: &str, major_version: Option<&str>) -> Result<Self> {
+        let mut store = Store::new(pkg_name)?;
+        if let Some(major_version) = major_version {
+            store.set_config_name(&format!("config-v{}", major_version));
+        }
+        Ok(store)
+    }
+
     /// Returns the value at the given path from the store.
     ///
+    /// Unless the store is cached or read with includes, environments, interpolation or
+    /// encrypted paths, only the objects leading to the value and the value itself are
+    /// deserialized, so reading one setting from a large store stays cheap.
+    ///
     /// # Example
     ///
     /// ```rust
@@ -86,7 +545,7 @@ impl<'a> Store<'a> {
     /// let store = Store::new("get-app").unwrap();
     /// store.set("a.b", 42).unwrap();
     /// assert_eq!(store.get("a.b").unwrap().unwrap(), 42);
-    /// # store.delete_store().unwrap();
+    /// # store.delete_store_dir().unwrap();
     /// ```
     ///
     /// # Errors
@@ -98,11 +557,227 @@ impl<'a> Store<'a> {
     /// * `path` is not a valid dot path.
     /// * `path` attempts to access an index out of bounds.
     pub fn get(&self, path: &str) -> Result<Option<Value>> {
-        if !self.store_exists() {
-            return Err(Error::NotFound);
+        if self.reads_partially() {
+            return self.get_partial(path);
         }
-        let parsed_json = self.get_store_as_parsed_json()?;
-        DotPaths::dot_get::<Value>(&parsed_json, path).map_err(Error::from)
+        let parsed_json = self.get_resolved_json()?;
+        DotPaths::dot_get::<Value>(&parsed_json, path)?
+            .map(|value| self.interpolate(&parsed_json, path, value))
+            .transpose()
+    }
+
+    /// Returns the value at the given path deserialized as `T`, like [`get`](Store::get).
+    ///
+    /// Numbers are kept as they are written in the store file with the
+    /// `arbitrary_precision` feature, so integers beyond `u64`, such as `u128` ids, and
+    /// decimals with more digits than `f64` holds round-trip losslessly.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("get-as-app").unwrap();
+    /// store.set("window.size", [800, 600]).unwrap();
+    /// let size: Option<(u32, u32)> = store.get_as("window.size").unwrap();
+    /// assert_eq!(size, Some((800, 600)));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value cannot be deserialized as `T`.
+    /// * `path` is not a valid dot path.
+    pub fn get_as<T: DeserializeOwned>(&self, path: &str) -> Result<Option<T>> {
+        Ok(self.get(path)?.map(serde_json::from_value).transpose()?)
+    }
+
+    /// Returns every value matching a dot path in which `*` segments match any key of an
+    /// object or index of an array, along with its concrete dot path. Use `\*` for a key
+    /// that is a literal `*`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("get-all-app").unwrap();
+    /// store.set("accounts.home.token", "abc").unwrap();
+    /// store.set("accounts.work.token", "def").unwrap();
+    /// let tokens = store.get_all("accounts.*.token").unwrap();
+    /// assert_eq!(tokens[0], ("accounts.home.token".to_string(), "abc".into()));
+    /// assert_eq!(tokens[1], ("accounts.work.token".to_string(), "def".into()));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn get_all(&self, pattern: &str) -> Result<Vec<(String, Value)>> {
+        let parsed_json = self.get_resolved_json()?;
+        let mut matches = Vec::new();
+        path::select(
+            String::new(),
+            &parsed_json,
+            &path::split_pattern(pattern),
+            &mut matches,
+        );
+        matches
+            .into_iter()
+            .map(|(path, value)| Ok((path.clone(), self.interpolate(&parsed_json, &path, value)?)))
+            .collect()
+    }
+
+    /// Returns the keys of the object at the given path, or the indices of the array at it,
+    /// `None` if there is no value at the path. An empty path lists the top level of the store.
+    ///
+    /// Keys are sorted alphabetically, or listed in the order they were first set with the
+    /// `preserve_order` feature.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("keys-app").unwrap();
+    /// store.set("accounts.home.email", "me@home.com").unwrap();
+    /// store.set("accounts.work.email", "me@work.com").unwrap();
+    /// store.set("recent", ["a", "b"]).unwrap();
+    /// assert_eq!(store.keys("accounts").unwrap().unwrap(), ["home", "work"]);
+    /// assert_eq!(store.keys("recent").unwrap().unwrap(), ["0", "1"]);
+    /// assert!(store.keys("missing").unwrap().is_none());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn keys(&self, path: &str) -> Result<Option<Vec<String>>> {
+        let parsed_json = self.get_resolved_json()?;
+        let value = match path.is_empty() {
+            true => Some(parsed_json),
+            false => DotPaths::dot_get::<Value>(&parsed_json, path)?,
+        };
+        Ok(value.map(|value| match value {
+            Value::Object(map) => map.into_iter().map(|(key, _)| key).collect(),
+            Value::Array(array) => (0..array.len()).map(|i| i.to_string()).collect(),
+            _ => Vec::new(),
+        }))
+    }
+
+    /// Returns an iterator over every leaf of the store as a dot path and value pair,
+    /// with dots in keys escaped. Empty objects and arrays are leaves themselves.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("iter-app").unwrap();
+    /// store.set("a.b", 42).unwrap();
+    /// store.set("c", [true]).unwrap();
+    /// let leaves: Vec<_> = store.iter().unwrap().collect();
+    /// assert_eq!(leaves, [("a.b".to_string(), 42.into()), ("c.0".to_string(), true.into())]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn iter(&self) -> Result<impl Iterator<Item = (String, Value)>> {
+        let mut leaves = Vec::new();
+        let parsed_json = self.get_resolved_json()?;
+        match self.interpolate(&parsed_json, "", parsed_json.clone())? {
+            Value::Object(map) => {
+                for (key, value) in map {
+                    path::flatten(path::join_path("", &key), value, &mut leaves);
+                }
+            }
+            value => path::flatten(String::new(), value, &mut leaves),
+        }
+        Ok(leaves.into_iter())
+    }
+
+    /// Returns the number of top-level keys in the store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("len-app").unwrap();
+    /// store.init_store().unwrap();
+    /// assert!(store.is_empty().unwrap());
+    /// store.set("window.width", 800).unwrap();
+    /// store.set("window.height", 600).unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// assert_eq!(store.len().unwrap(), 2);
+    /// assert_eq!(store.deep_len().unwrap(), 3);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn len(&self) -> Result<usize> {
+        Ok(match self.get_resolved_json()? {
+            Value::Object(map) => map.len(),
+            Value::Array(array) => array.len(),
+            _ => 0,
+        })
+    }
+
+    /// Returns whether the store has no top-level keys.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::len`] errors.
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Returns the number of leaves in the store, the values [`Store::iter`] yields.
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::iter`] errors.
+    pub fn deep_len(&self) -> Result<usize> {
+        self.iter().map(Iterator::count)
+    }
+
+    /// Returns the whole store as a JSON value.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("to-value-app").unwrap();
+    /// store.set("a.b", 42).unwrap();
+    /// assert_eq!(store.to_value().unwrap(), serde_json::json!({ "a": { "b": 42 } }));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn to_value(&self) -> Result<Value> {
+        let parsed_json = self.get_resolved_json()?;
+        self.interpolate(&parsed_json, "", parsed_json.clone())
     }
 
     /// Sets the given data using a [json dotpath](https://crates.io/crates/json_dotpath).
@@ -116,7 +791,7 @@ impl<'a> Store<'a> {
     /// let store = Store::new("set-app").unwrap();
     /// store.set("a.b", 42).unwrap();
     /// assert_eq!(store.get("a.b").unwrap().unwrap(), 42);
-    /// # store.delete_store().unwrap();
+    /// # store.delete_store_dir().unwrap();
     /// ```
     /// The data will be stored in the following format:
     /// ```json
@@ -139,7 +814,7 @@ impl<'a> Store<'a> {
     ///         assert_eq!(e.to_string(), "Unexpected value reached while traversing path");
     ///    },
     /// };
-    /// # store.delete_store().unwrap();
+    /// # store.delete_store_dir().unwrap();
     /// ```
     ///
     /// # Errors
@@ -148,6 +823,8 @@ impl<'a> Store<'a> {
     /// * The store fails to be created.
     /// * It fails to read the store file.
     /// * The store cannot be deserialized.
+    /// * The store would exceed its [maximum size](Store::set_max_size), with
+    ///   [`Error::QuotaExceeded`].
     /// * The store file fails to be written to.
     /// * `path` is not a valid dot path.
     pub fn set<T>(&self, path: &str, data: T) -> Result<()>
@@ -155,434 +832,4852 @@ impl<'a> Store<'a> {
         T: Serialize,
     {
         let json_data = serde_json::to_value(&data)?;
-        if !self.store_exists() {
-            self.init_store()?;
-        }
-        let mut parsed_json = self.get_store_as_parsed_json()?;
-        DotPaths::dot_set(&mut parsed_json, path, json_data)?;
-        self.write_value(parsed_json)
+        self.modify(|parsed_json| {
+            DotPaths::dot_set(parsed_json, path, json_data).map_err(Error::from)
+        })
     }
 
-    /// Deletes the given path from the store.
+    /// Sets the value at the given path only if there is no value at it yet,
+    /// returning whether it was written.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
     ///
     /// # Example
     ///
     /// ```rust
     /// # use bland::Store;
-    /// let store = Store::new("delete-app").unwrap();
-    /// store.set("a.b", 42).unwrap();
-    /// assert_eq!(store.get("a.b").unwrap().unwrap(), 42);
-    /// store.delete("a.b").unwrap();
-    /// assert!(store.get("a.b").unwrap().is_none());
-    /// # store.delete_store().unwrap();
+    /// let store = Store::new("set-if-absent-app").unwrap();
+    /// assert!(store.set_if_absent("theme", "light").unwrap());
+    /// assert!(!store.set_if_absent("theme", "dark").unwrap());
+    /// assert!(store.replace_if_present("theme", "dark").unwrap());
+    /// assert!(!store.replace_if_present("font", "mono").unwrap());
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "dark");
+    /// assert!(store.get("font").unwrap().is_none());
+    /// # store.delete_store_dir().unwrap();
     /// ```
     ///
     /// # Errors
     ///
     /// Errors if
-    /// * The store does not exist.
+    /// * The store fails to be created.
     /// * It fails to read the store file.
     /// * The store cannot be deserialized.
     /// * The store file fails to be written to.
     /// * `path` is not a valid dot path.
-    pub fn delete(&self, path: &str) -> Result<Option<Value>> {
-        if !self.store_exists() {
-            return Err(Error::NotFound);
-        }
-
-        let mut parsed_json = self.get_store_as_parsed_json()?;
-        let value = DotPaths::dot_take::<Value>(&mut parsed_json, path)?;
-        self.write_value(parsed_json)?;
-        Ok(value)
-    }
-
-    /// Get the path to the directory where the configuration data is stored.
-    pub fn get_store_dir_path(&self) -> PathBuf {
-        let mut project_name = self.project_name.to_owned();
-        if let Some(suffix) = self.project_suffix {
-            project_name.push('-');
-            project_name.push_str(suffix);
-        }
-        let mut store_path = self.path.clone();
-        store_path.push(project_name);
-        store_path
-    }
-
-    /// Get the path to the configuration file.
-    pub fn get_store_path(&self) -> PathBuf {
-        let mut store_dir_path = self.get_store_dir_path();
-        let mut file_name = PathBuf::new();
-        file_name.push(self.config_name);
-        file_name.set_extension(self.file_extension);
-        store_dir_path.push(file_name);
-        store_dir_path
+    pub fn set_if_absent<T>(&self, path: &str, data: T) -> Result<bool>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        self.modify(|parsed_json| {
+            if DotPaths::dot_get::<Value>(parsed_json, path)?.is_some() {
+                return Ok(false);
+            }
+            DotPaths::dot_set(parsed_json, path, json_data)?;
+            Ok(true)
+        })
     }
 
-    /// Makes the store directory if it does not exist.
+    /// Sets the value at the given path only if there already is a value at it,
+    /// returning whether it was written.
     ///
     /// # Errors
     ///
-    /// Errors if the store directory cannot be created.
-    fn make_store_path(&self) -> Result<()> {
-        fs::create_dir(self.get_store_dir_path()).map_err(Error::from)
+    /// Errors if
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn replace_if_present<T>(&self, path: &str, data: T) -> Result<bool>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        if !self.store_exists() {
+            return Ok(false);
+        }
+        self.modify(|parsed_json| {
+            if DotPaths::dot_get::<Value>(parsed_json, path)?.is_none() {
+                return Ok(false);
+            }
+            DotPaths::dot_set(parsed_json, path, json_data)?;
+            Ok(true)
+        })
     }
 
-    /// Initializes the store file.
-
-    /// *NOTE* This will initilize the store as either encrypted or
-    /// plain text depending on if the encryption key is set.
+    /// Replaces the value at the given path with `new` only if it currently equals
+    /// `expected`, in a single read-modify-write under the store's lock file.
+    /// Returns whether it was replaced.
     ///
-    /// # Errors
+    /// Values are compared as JSON, descending into objects and arrays. A missing value
+    /// never equals `expected`, use [`set_if_absent`](Store::set_if_absent) for that.
     ///
-    /// * The store file directory be created.
-    /// * The store file cannot be created.
-    /// * The store file cannot be initialized.
-    /// * The store file cannot be wrote to.
-    pub fn init_store(&self) -> Result<()> {
-        if !self.store_dir_exists() {
-            if let Err(e) = self.make_store_path() {
-                return Err(e);
-            };
-        }
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("compare-and-swap-app").unwrap();
+    /// store.set("leader", "instance-1").unwrap();
+    /// assert!(store.compare_and_swap("leader", "instance-1", "instance-2").unwrap());
+    /// assert!(!store.compare_and_swap("leader", "instance-1", "instance-3").unwrap());
+    /// assert_eq!(store.get("leader").unwrap().unwrap(), "instance-2");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn compare_and_swap<E, T>(&self, path: &str, expected: E, new: T) -> Result<bool>
+    where
+        E: Serialize,
+        T: Serialize,
+    {
+        let expected = serde_json::to_value(&expected)?;
+        let json_data = serde_json::to_value(&new)?;
         if !self.store_exists() {
-            File::create(self.get_store_path())?;
+            return Ok(false);
         }
-        self.write_store("{}".to_string())
+        self.modify(|parsed_json| {
+            if DotPaths::dot_get::<Value>(parsed_json, path)?.as_ref() != Some(&expected) {
+                return Ok(false);
+            }
+            DotPaths::dot_set(parsed_json, path, json_data)?;
+            Ok(true)
+        })
     }
 
-    /// Returns a boolean indicating whether the store directory exists.
-    pub fn store_dir_exists(&self) -> bool {
-        Path::new(&self.get_store_dir_path()).exists()
+    /// Adds `delta` to the integer at the given path in a single read-modify-write under the
+    /// store's lock file, returning the new value. A missing value counts as `0`.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("increment-app").unwrap();
+    /// assert_eq!(store.increment("stats.launch_count", 1).unwrap(), 1);
+    /// assert_eq!(store.increment("stats.launch_count", 1).unwrap(), 2);
+    /// assert_eq!(store.decrement("stats.launch_count", 5).unwrap(), -3);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    /// * The value at `path` is not an integer or the result would overflow.
+    pub fn increment(&self, path: &str, delta: i64) -> Result<i64> {
+        self.modify(|parsed_json| {
+            let current = match DotPaths::dot_get::<Value>(parsed_json, path)? {
+                Some(value) => value.as_i64(),
+                None => Some(0),
+            };
+            let value = current
+                .and_then(|current| current.checked_add(delta))
+                .ok_or_else(|| Error::NotAnInteger(path.to_owned()))?;
+            DotPaths::dot_set(parsed_json, path, value)?;
+            Ok(value)
+        })
     }
 
-    /// Returns a boolean indicating whether the store file exists.
-    pub fn store_exists(&self) -> bool {
-        Path::new(&self.get_store_path()).exists()
+    /// Subtracts `delta` from the integer at the given path, see [`Store::increment`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::increment`] errors.
+    pub fn decrement(&self, path: &str, delta: i64) -> Result<i64> {
+        let delta = delta
+            .checked_neg()
+            .ok_or_else(|| Error::NotAnInteger(path.to_owned()))?;
+        self.increment(path, delta)
     }
 
-    /// Deletes the store file and directory.
+    /// Returns the value at the given path, or stores and returns the value computed by
+    /// `default` if there is none, in a single read-modify-write under the store's lock file.
+    ///
+    /// `default` must not call back into the store, as the lock file is held while it runs.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("get-or-insert-with-app").unwrap();
+    /// let first = store.get_or_insert_with("first_run", || "2021-06-01").unwrap();
+    /// let second = store.get_or_insert_with("first_run", || "2021-06-02").unwrap();
+    /// assert_eq!(first, "2021-06-01");
+    /// assert_eq!(second, "2021-06-01");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
     ///
     /// # Errors
     ///
-    /// Errors if the store file cannot be deleted.
-    pub fn delete_store(&self) -> Result<()> {
-        fs::remove_dir_all(self.get_store_dir_path()).map_err(Error::from)
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    /// * The default cannot be serialized.
+    pub fn get_or_insert_with<T, F>(&self, path: &str, default: F) -> Result<Value>
+    where
+        T: Serialize,
+        F: FnOnce() -> T,
+    {
+        self.modify(|parsed_json| {
+            if let Some(value) = DotPaths::dot_get::<Value>(parsed_json, path)? {
+                return Ok(value);
+            }
+            let value = serde_json::to_value(default())?;
+            DotPaths::dot_set(parsed_json, path, value.clone())?;
+            Ok(value)
+        })
     }
 
-    /// Writes the store file.
+    /// Hands the current value at the given path to `f` and stores whatever it returns,
+    /// deleting the path if it returns `None`, in a single read-modify-write under the store's
+    /// lock file.
+    ///
+    /// `f` must not call back into the store, as the lock file is held while it runs.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let store = Store::new("update-app").unwrap();
+    /// store.set("recent", json!(["a", "b", "c"])).unwrap();
+    /// store
+    ///     .update("recent", |recent| {
+    ///         let mut recent = recent?;
+    ///         recent.as_array_mut()?.truncate(2);
+    ///         Some(recent)
+    ///     })
+    ///     .unwrap();
+    /// assert_eq!(store.get("recent").unwrap().unwrap(), json!(["a", "b"]));
+    ///
+    /// store.update("recent", |_| None).unwrap();
+    /// assert!(store.get("recent").unwrap().is_none());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
     ///
     /// # Errors
     ///
-    /// Errors if the store file cannot be written to.
-    fn write_value(&self, value: Value) -> Result<()> {
-        // self.write_store(serde_json::to_string_pretty(&data)?)
-        match self.pretty {
-            true => self.write_store(serde_json::to_string_pretty(&value)?),
-            false => self.write_store(value.to_string()),
-        }
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn update<F>(&self, path: &str, f: F) -> Result<()>
+    where
+        F: FnOnce(Option<Value>) -> Option<Value>,
+    {
+        self.modify(|parsed_json| {
+            let current = DotPaths::dot_get::<Value>(parsed_json, path)?;
+            let existed = current.is_some();
+            match f(current) {
+                Some(value) => DotPaths::dot_set(parsed_json, path, value)?,
+                None if existed => {
+                    path::take_path(parsed_json, path)?;
+                }
+                None => {}
+            }
+            Ok(())
+        })
     }
 
-    fn write_store(&self, data: String) -> Result<()> {
-        #[cfg(feature = "crypto")]
-        if let Some(key) = self.encryption_key {
-            let encrypted_data = crypto::encrypt_data(&data, key)?;
-            return fs::write(self.get_store_path(), encrypted_data).map_err(Error::from);
+    /// Deep-merges `patch` into the value at the given path following JSON Merge Patch
+    /// ([RFC 7386](https://www.rfc-editor.org/rfc/rfc7386)), leaving sibling keys untouched.
+    ///
+    /// `null` members of the patch delete the corresponding keys, and a `null` patch
+    /// deletes the path. An empty path merges into the top level of the store, which only
+    /// takes an object patch.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let store = Store::new("merge-app").unwrap();
+    /// store.set("window", json!({ "width": 800, "height": 600, "maximized": false })).unwrap();
+    /// store.merge("window", json!({ "width": 1024, "maximized": null })).unwrap();
+    /// assert_eq!(store.get("window").unwrap().unwrap(), json!({ "width": 1024, "height": 600 }));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    /// * `path` is empty and `patch` is not an object, with [`Error::InvalidValue`].
+    pub fn merge(&self, path: &str, patch: Value) -> Result<()> {
+        if path.is_empty() && !patch.is_object() {
+            return Err(Error::InvalidValue {
+                path: path.to_string(),
+                reason: "the top level of the store can only be merged with an object".to_string(),
+            });
         }
+        self.modify(|parsed_json| {
+            if path.is_empty() {
+                patch::merge_patch(parsed_json, patch);
+                return Ok(());
+            }
+            let mut target = DotPaths::dot_get::<Value>(parsed_json, path)?.unwrap_or(Value::Null);
+            patch::merge_patch(&mut target, patch);
+            match target {
+                Value::Null => {
+                    path::take_path(parsed_json, path)?;
+                }
+                target => DotPaths::dot_set(parsed_json, path, target)?,
+            }
+            Ok(())
+        })
+    }
 
-        #[cfg(feature = "compression")]
-        if self.get_compressed() {
-            let mut e = GzEncoder::new(Vec::new(), Compression::default());
-            e.write_all(data.as_bytes())?;
-            // returns io error so can be unwrapped
-            let compressed_data = e.finish()?;
-            return fs::write(self.get_store_path(), compressed_data).map_err(Error::from);
-        }
+    /// Returns the value at the given [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901),
+    /// an alternative to dot paths for keys containing dots.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("get-ptr-app").unwrap();
+    /// store.set_ptr("/hosts/example.com/port", 8080).unwrap();
+    /// assert_eq!(store.get_ptr("/hosts/example.com/port").unwrap().unwrap(), 8080);
+    /// assert_eq!(store.delete_ptr("/hosts/example.com").unwrap().unwrap()["port"], 8080);
+    /// assert!(store.get_ptr("/hosts/example.com").unwrap().is_none());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `pointer` is not a valid JSON Pointer.
+    pub fn get_ptr(&self, pointer: &str) -> Result<Option<Value>> {
+        let parsed_json = self.get_resolved_json()?;
+        Ok(pointer::get(&parsed_json, pointer)?.cloned())
+    }
 
-        fs::write(self.get_store_path(), data).map_err(Error::from)
+    /// Sets the value at the given JSON Pointer, creating missing parent objects.
+    /// Array elements are replaced, with `-` appending to the array.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `pointer` is not a valid JSON Pointer, traverses a value that is neither an object
+    ///   nor an array, or is out of bounds.
+    pub fn set_ptr<T>(&self, pointer: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        self.modify(|parsed_json| pointer::set(parsed_json, pointer, json_data))
     }
 
-    /// Returns the parsed JSON of the store file.
+    /// Deletes the value at the given JSON Pointer, returning it.
     ///
     /// # Errors
     ///
-    /// * Errors if the store file does not exist.
-    /// * Errors if the store file cannot be read.
-    /// * Errors if the store file cannot be deserialized.
-    fn get_store_as_parsed_json(&self) -> Result<Value> {
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `pointer` is not a valid JSON Pointer or refers to the whole store.
+    pub fn delete_ptr(&self, pointer: &str) -> Result<Option<Value>> {
         if !self.store_exists() {
             return Err(Error::NotFound);
         }
-        let store_data = fs::read(self.get_store_path())?;
+        self.modify(|parsed_json| match pointer::get(parsed_json, pointer)? {
+            Some(_) => pointer::remove(parsed_json, pointer).map(Some),
+            None => Ok(None),
+        })
+    }
 
-        #[cfg(feature = "crypto")]
-        if let Some(key) = self.encryption_key {
-            let data = crypto::decrypt_data(store_data, key)?;
-            return Store::parse_json(data);
+    /// Applies a JSON Patch ([RFC 6902](https://www.rfc-editor.org/rfc/rfc6902)) to the store.
+    ///
+    /// The patch is applied atomically, if any operation fails the store is left unchanged.
+    ///
+    /// **NOTE:** This will create the store directory and file if it doesn't exist.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{PatchOp, Store};
+    /// # use serde_json::json;
+    /// let store = Store::new("apply-patch-app").unwrap();
+    /// store.set("recent", ["a.txt"]).unwrap();
+    /// let patch: Vec<PatchOp> = serde_json::from_value(json!([
+    ///     { "op": "add", "path": "/recent/0", "value": "b.txt" },
+    ///     { "op": "replace", "path": "/theme", "value": "dark" },
+    /// ]))
+    /// .unwrap();
+    /// assert!(store.apply_patch(&patch).is_err());
+    /// assert_eq!(store.get("recent").unwrap().unwrap(), json!(["a.txt"]));
+    ///
+    /// store.apply_patch(&patch[..1]).unwrap();
+    /// assert_eq!(store.get("recent").unwrap().unwrap(), json!(["b.txt", "a.txt"]));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * A path is not a valid JSON Pointer or does not exist.
+    /// * A `test` operation fails.
+    pub fn apply_patch(&self, ops: &[PatchOp]) -> Result<()> {
+        self.modify(|parsed_json| {
+            for op in ops {
+                patch::apply_op(parsed_json, op)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Returns the JSON Patch operations that turn the store into `other`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{PatchOp, Store};
+    /// # use serde_json::json;
+    /// let store = Store::new("diff-app").unwrap();
+    /// store.set("theme", "light").unwrap();
+    /// let ops = store.diff(&json!({ "theme": "dark" })).unwrap();
+    /// assert_eq!(ops, [PatchOp::Replace { path: "/theme".into(), value: json!("dark") }]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn diff(&self, other: &Value) -> Result<Vec<PatchOp>> {
+        let parsed_json = self.get_store_as_parsed_json()?;
+        let mut ops = Vec::new();
+        patch::diff("", &parsed_json, other, &mut ops);
+        Ok(ops)
+    }
+
+    /// Deletes the given path from the store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("delete-app").unwrap();
+    /// store.set("a.b", 42).unwrap();
+    /// assert_eq!(store.get("a.b").unwrap().unwrap(), 42);
+    /// store.delete("a.b").unwrap();
+    /// assert!(store.get("a.b").unwrap().is_none());
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `path` is not a valid dot path.
+    pub fn delete(&self, path: &str) -> Result<Option<Value>> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
         }
 
-        #[cfg(feature = "compression")]
-        if self.get_compressed() {
-            let mut gz = GzDecoder::new(&store_data[..]);
-            let mut s = String::new();
-            gz.read_to_string(&mut s)?;
-            return Self::parse_json(s);
+        self.modify(|parsed_json| path::take_path(parsed_json, path))
+    }
+
+    /// Moves the value at one dot path to another in a single write, creating the target's
+    /// parents if needed. Returns `false` if there is no value at `from`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("rename-path-app").unwrap();
+    /// store.set("settings.theme", "dark").unwrap();
+    /// assert!(store.rename_path("settings.theme", "appearance.theme").unwrap());
+    /// assert!(store.get("settings.theme").unwrap().is_none());
+    /// assert_eq!(store.get("appearance.theme").unwrap().unwrap(), "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `from` or `to` is not a valid dot path.
+    pub fn rename_path(&self, from: &str, to: &str) -> Result<bool> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
         }
+        self.modify(|parsed_json| match path::take_path(parsed_json, from)? {
+            Some(value) => {
+                DotPaths::dot_set(parsed_json, to, value)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        })
+    }
 
-        let data = String::from_utf8(store_data)?;
-        Store::parse_json(data)
+    /// Deep-clones the value at one dot path to another in a single read and write,
+    /// creating the target's parents if needed. Returns `false` if there is no value at `from`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("copy-path-app").unwrap();
+    /// store.set("profiles.default", serde_json::json!({ "theme": "dark" })).unwrap();
+    /// assert!(store.copy_path("profiles.default", "profiles.work").unwrap());
+    /// store.set("profiles.work.theme", "light").unwrap();
+    /// assert_eq!(store.get("profiles.default.theme").unwrap().unwrap(), "dark");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    /// * `from` or `to` is not a valid dot path.
+    pub fn copy_path(&self, from: &str, to: &str) -> Result<bool> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        self.modify(
+            |parsed_json| match DotPaths::dot_get::<Value>(parsed_json, from)? {
+                Some(value) => {
+                    DotPaths::dot_set(parsed_json, to, value)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            },
+        )
+    }
+
+    /// Get the path to the directory where the configuration data is stored.
+    ///
+    /// See [`set_dir_env`](Store::set_dir_env) for overriding it from the environment.
+    pub fn get_store_dir_path(&self) -> PathBuf {
+        if let Some(dir) = self.get_dir_override() {
+            return dir;
+        }
+        let mut project_name = self.project_name.to_owned();
+        if let Some(suffix) = &self.project_suffix {
+            project_name.push('-');
+            project_name.push_str(suffix);
+        }
+        let mut store_path = self.path.clone();
+        store_path.push(project_name);
+        store_path
+    }
+
+    /// Get the path to the configuration file.
+    ///
+    /// If a profile is active, the file name is `<config_name>.<profile>.<file_extension>`.
+    pub fn get_store_path(&self) -> PathBuf {
+        self.get_profile_store_path(self.profile.as_deref())
+    }
+
+    /// Get the path to the configuration file of the given profile.
+    fn get_profile_store_path(&self, profile: Option<&str>) -> PathBuf {
+        let mut store_dir_path = self.get_store_dir_path();
+        let mut file_name = self.config_name.to_owned();
+        if let Some(profile) = profile {
+            file_name.push('.');
+            file_name.push_str(profile);
+        }
+        file_name.push('.');
+        file_name.push_str(&self.file_extension);
+        store_dir_path.push(file_name);
+        store_dir_path
+    }
+
+    /// Returns the names of all profiles that have a configuration file in the
+    /// store directory, sorted alphabetically.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("list-profiles-app").unwrap();
+    /// store.set_profile("dev");
+    /// store.set("debug", true).unwrap();
+    /// store.set_profile("prod");
+    /// store.set("debug", false).unwrap();
+    /// assert_eq!(store.list_profiles().unwrap(), vec!["dev", "prod"]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be read.
+    pub fn list_profiles(&self) -> Result<Vec<String>> {
+        if !self.store_dir_exists() {
+            return Ok(Vec::new());
+        }
+        let prefix = format!("{}.", self.config_name);
+        let suffix = format!(".{}", self.file_extension);
+        let mut profiles = Vec::new();
+        let store_dir_path = self.get_store_dir_path();
+        for entry in fs::read_dir(&store_dir_path).context(IoOp::ReadDir, &store_dir_path)? {
+            let file_name = entry.context(IoOp::ReadDir, &store_dir_path)?.file_name();
+            let file_name = match file_name.to_str() {
+                Some(file_name) => file_name,
+                None => continue,
+            };
+            if let Some(profile) = file_name
+                .strip_prefix(&prefix)
+                .and_then(|rest| rest.strip_suffix(&suffix))
+            {
+                if !profile.is_empty() {
+                    profiles.push(profile.to_owned());
+                }
+            }
+        }
+        profiles.sort();
+        Ok(profiles)
+    }
+
+    /// Copies the configuration file of the profile `from` to the profile `to`,
+    /// overwriting `to` if it already exists.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The `from` profile does not exist.
+    /// * The file cannot be copied.
+    pub fn copy_profile(&self, from: &str, to: &str) -> Result<()> {
+        self.check_writable()?;
+        let from_path = self.get_profile_store_path(Some(from));
+        if !from_path.exists() {
+            return Err(Error::NotFound);
+        }
+        fs::copy(&from_path, self.get_profile_store_path(Some(to)))
+            .context(IoOp::Copy, &from_path)?;
+        Ok(())
+    }
+
+    /// Makes the store directory if it does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be created.
+    fn make_store_path(&self) -> Result<()> {
+        let store_dir_path = self.get_store_dir_path();
+        self.dir_builder()
+            .recursive(true)
+            .create(&store_dir_path)
+            .context(IoOp::CreateDir, &store_dir_path)
+    }
+
+    /// Initializes the store file with the defaults, or `{}` if none are set,
+    /// unless it already exists. Use [`Store::clear`] to reset an existing store.
+    ///
+    /// *NOTE* This will initilize the store as either encrypted or
+    /// plain text depending on if the encryption key is set.
+    ///
+    /// # Errors
+    ///
+    /// * The store file directory be created.
+    /// * The store file cannot be created.
+    /// * The store file cannot be initialized.
+    /// * The store file cannot be wrote to.
+    pub fn init_store(&self) -> Result<()> {
+        self.check_writable()?;
+        if !self.store_dir_exists() {
+            self.make_store_path()?;
+        }
+        if self.store_exists() {
+            return Ok(());
+        }
+        let store_path = self.get_store_path();
+        self.file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&store_path)
+            .context(IoOp::Create, &store_path)?;
+        self.write_value(self.get_initial_value())
+    }
+
+    /// Resets the store to the defaults, or `{}` if none are set, keeping the store file.
+    ///
+    /// A store that cannot be read, for example because it is corrupt, is reset as well.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let mut store = Store::new("clear-app").unwrap();
+    /// store.set_defaults(json!({ "theme": "light" }));
+    /// store.set("theme", "dark").unwrap();
+    /// store.set("font", "mono").unwrap();
+    /// store.clear().unwrap();
+    /// assert_eq!(store.to_value().unwrap(), json!({ "theme": "light" }));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store fails to be created or written to.
+    pub fn clear(&self) -> Result<()> {
+        self.check_create_missing()?;
+        let lock = self.lock_store_file()?;
+        if !self.store_exists() {
+            return self.init_store();
+        }
+        let initial_value = self.get_initial_value();
+        match self.get_store_as_parsed_json() {
+            Ok(previous) if previous == initial_value => Ok(()),
+            Ok(previous) => self.write_mutation(previous, initial_value, lock),
+            Err(_) => self.write_or_defer(initial_value),
+        }
+    }
+
+    /// Returns the contents of a new or cleared store.
+    fn get_initial_value(&self) -> Value {
+        self.defaults
+            .clone()
+            .unwrap_or_else(|| Value::Object(Default::default()))
+    }
+
+    /// Returns a boolean indicating whether the store directory exists.
+    pub fn store_dir_exists(&self) -> bool {
+        Path::new(&self.get_store_dir_path()).exists()
+    }
+
+    /// Returns a boolean indicating whether the store file exists.
+    pub fn store_exists(&self) -> bool {
+        Path::new(&self.get_store_path()).exists()
+    }
+
+    /// Deletes the store directory, including every other configuration file,
+    /// backup and any other data kept there.
+    ///
+    /// See [`delete_config`](Store::delete_config) for deleting only the current configuration.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be deleted, or was set by the
+    /// [`dir_env`](Store::set_dir_env) variable.
+    pub fn delete_store_dir(&self) -> Result<()> {
+        self.check_writable()?;
+        self.check_dir_deletable()?;
+        self.clear_pending_value();
+        let store_dir_path = self.get_store_dir_path();
+        fs::remove_dir_all(&store_dir_path).context(IoOp::Remove, &store_dir_path)
+    }
+
+    /// Deletes the store file and directory.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be deleted.
+    #[deprecated(
+        note = "removes every configuration in the store directory, use `delete_config` or `delete_store_dir`"
+    )]
+    pub fn delete_store(&self) -> Result<()> {
+        self.delete_store_dir()
+    }
+
+    /// Deletes only the current configuration file, leaving sibling configurations,
+    /// profiles and backups in the store directory untouched.
+    ///
+    /// The lock file is kept, so another process waiting for the lock keeps locking the
+    /// same file as the next process to open the configuration. The generation file is
+    /// kept too, so a recreated configuration doesn't reuse an earlier generation.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("delete-config-app").unwrap();
+    /// store.set("a", 1).unwrap();
+    /// store.open_config("work").unwrap();
+    /// store.set("a", 2).unwrap();
+    /// store.delete_config().unwrap();
+    /// assert_eq!(store.list_configs().unwrap(), vec!["config"]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store file does not exist.
+    /// * The store file cannot be deleted.
+    pub fn delete_config(&self) -> Result<()> {
+        self.clear_pending_value();
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        let lock = self.lock_store_file()?;
+        let store_path = self.get_store_path();
+        fs::remove_file(&store_path).context(IoOp::Remove, &store_path)?;
+        self.remove_journal()?;
+        #[cfg(feature = "sharding")]
+        self.remove_shards()?;
+        self.invalidate_cache();
+        drop(lock);
+        Ok(())
+    }
+
+    /// Writes the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be written to.
+    fn write_value(&self, value: Value) -> Result<()> {
+        let published = self.has_subscribers().then(|| value.clone());
+        #[cfg(feature = "crypto")]
+        let value = self.encrypt_fields(value)?;
+        #[cfg(feature = "sharding")]
+        let mut current_shards = self.remove_stale_shards()?;
+        #[cfg(feature = "sharding")]
+        let value = match self.shards_apply() {
+            true => self.write_shards(value, &mut current_shards)?,
+            false => value,
+        };
+        self.write_store(&value)?;
+        if let Some(value) = published {
+            self.publish_changes(&value, ChangeSource::Internal);
+        }
+        Ok(())
+    }
+
+    /// Serializes the contents of the store for the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized.
+    fn serialize_value(&self, value: &Value) -> Result<Vec<u8>> {
+        let mut data = Vec::new();
+        self.serialize_to(value, &mut data)?;
+        Ok(data)
+    }
+
+    /// Serializes the contents of the store for the store file into the writer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized or written.
+    fn serialize_to<W: Write>(&self, value: &Value, writer: W) -> Result<()> {
+        match self.sort_keys {
+            true => self.serialize_with(&format::SortedKeys(value), writer),
+            false => self.serialize_with(value, writer),
+        }
+    }
+
+    /// Serializes a value in the format of the store file into the writer.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be serialized or written.
+    fn serialize_with<T: Serialize, W: Write>(&self, value: &T, writer: W) -> Result<()> {
+        #[cfg(feature = "electron")]
+        if let Some(compat) = &self.electron {
+            return compat.serialize(value, writer);
+        }
+        match &self.pretty {
+            Some(style) => style.serialize(value, writer),
+            None => Ok(serde_json::to_writer(writer, value)?),
+        }
+    }
+
+    /// Applies `f` to the contents of the store in a single read-modify-write under the
+    /// store's lock file, creating the store if it doesn't exist. Nothing is written if
+    /// `f` errors or leaves the contents unchanged.
+    ///
+    /// With includes enabled or an environment active, `f` is applied to the contents as
+    /// they are read and only its changes are written to the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be created, read or written to, or if `f` errors.
+    pub(crate) fn modify<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Value) -> Result<R>,
+    {
+        self.check_create_missing()?;
+        let lock = self.lock_store_file()?;
+        if !self.store_exists() {
+            self.init_store()?;
+        }
+        let mut parsed_json = self.get_store_as_parsed_json()?;
+        let previous = parsed_json.clone();
+        let result = match self.includes || self.environment.is_some() {
+            true => self.modify_resolved(&mut parsed_json, f)?,
+            false => f(&mut parsed_json)?,
+        };
+        if parsed_json != previous {
+            self.write_mutation(previous, parsed_json, lock)?;
+        }
+        Ok(result)
+    }
+
+    /// Writes the mutated contents of the store and releases the store's lock file,
+    /// then notifies the observers of the change and records the previous contents for undo.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be written to.
+    pub(crate) fn write_mutation(&self, previous: Value, value: Value, lock: File) -> Result<()> {
+        let observed = match self.observers.is_empty() {
+            true => None,
+            false => Some(value.clone()),
+        };
+        self.write_journaled(&previous, value)?;
+        // Observers may mutate the store themselves.
+        drop(lock);
+        if let Some(value) = observed {
+            self.notify_observers(&previous, &value);
+        }
+        self.record_undo(previous);
+        Ok(())
+    }
+
+    fn write_store(&self, value: &Value) -> Result<()> {
+        if let Some(data) = self.edit_store_file(value)? {
+            return self.write_file(&data);
+        }
+        self.write_file(&self.encode_file(value)?)
+    }
+
+    /// Encodes the contents of the store and appends its signature and checksum, as
+    /// written to the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized, compressed, encrypted or signed.
+    fn encode_file(&self, value: &Value) -> Result<Vec<u8>> {
+        let encoded_data = self.encode(value)?;
+        #[cfg(feature = "crypto")]
+        let encoded_data = match &self.signing_key {
+            Some(key) => crypto::sign_data(encoded_data, key)?,
+            None => encoded_data,
+        };
+        Ok(match self.checksum {
+            true => checksum::append_checksum(encoded_data),
+            false => encoded_data,
+        })
+    }
+
+    /// Serializes the contents of the store, then compresses and encrypts them, depending
+    /// on the store's settings. Compressed stores are serialized straight into the
+    /// compressor, so the uncompressed contents are never held in memory as a whole.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized, compressed or encrypted.
+    fn encode(&self, value: &Value) -> Result<Vec<u8>> {
+        #[cfg(feature = "electron")]
+        if let Some(compat) = &self.electron {
+            return compat.encode(self.serialize_value(value)?);
+        }
+
+        #[cfg(feature = "compression")]
+        let data = match self.compressed {
+            true => {
+                let mut compressor = compression::Compressor::new(
+                    self.compression_algorithm,
+                    self.compression_level,
+                );
+                let mut writer = std::io::BufWriter::new(&mut compressor);
+                self.serialize_to(value, &mut writer)?;
+                // The buffer is written out without flushing the compressor, which would end
+                // the output with a needless sync-flush block.
+                writer
+                    .into_inner()
+                    .map_err(|e| e.into_error())
+                    .context(IoOp::Write, &self.get_store_path())?;
+                compressor.finish()?
+            }
+            false => self.serialize_value(value)?,
+        };
+        #[cfg(not(feature = "compression"))]
+        let data = self.serialize_value(value)?;
+
+        #[cfg(feature = "crypto")]
+        if let Some(key) = self.get_whole_store_key() {
+            return self.encrypt(key, &Zeroizing::new(data), self.get_header_flags());
+        }
+
+        Ok(data)
+    }
+
+    /// Decrypts the data, and decompresses encrypted data according to its header rather
+    /// than the store's settings. Data which is only compressed is left to be decompressed
+    /// as it is parsed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be decrypted or decompressed.
+    fn decode(&self, data: Vec<u8>) -> Result<Decoded<'static>> {
+        #[cfg(feature = "electron")]
+        if let Some(compat) = &self.electron {
+            return Ok(Decoded::Plain(Cow::Owned(compat.decode(data))));
+        }
+
+        #[cfg(feature = "crypto")]
+        if let Some(key) = self.get_whole_store_key() {
+            let flags = crypto::read_header(&data)?.flags;
+            let data = self.decrypt(key, &data)?;
+            let data = match flags {
+                0 => Ok(data),
+                #[cfg(feature = "compression")]
+                crypto::FLAG_GZIP => {
+                    compression::decompress_data(&data, compression::CompressionAlgorithm::Gzip)
+                        .map(Zeroizing::new)
+                }
+                #[cfg(feature = "compression")]
+                crypto::FLAG_DEFLATE => {
+                    compression::decompress_data(&data, compression::CompressionAlgorithm::Deflate)
+                        .map(Zeroizing::new)
+                }
+                _ => Err(Error::InvalidHeader),
+            };
+            return data.map(Decoded::Decrypted);
+        }
+
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return Ok(Decoded::Compressed(
+                Cow::Owned(data),
+                self.compression_algorithm,
+            ));
+        }
+
+        Ok(Decoded::Plain(Cow::Owned(data)))
+    }
+
+    /// Atomically replaces the store file with the given data by writing to a
+    /// temporary file next to it and renaming it over the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the temporary file cannot be written or renamed.
+    fn write_file(&self, data: &[u8]) -> Result<()> {
+        self.check_writable()?;
+        trace_span!(timer, "bland.write", path = %self.get_store_path().display());
+        #[cfg(feature = "test-util")]
+        if let Some(faults) = &self.faults {
+            faults.check_write(&self.get_store_path())?;
+        }
+        let store_path = self.get_store_path();
+        let mut temp_path = store_path.clone().into_os_string();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+        let mut temp_file = self
+            .file_options()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&temp_path)
+            .context(IoOp::Create, &temp_path)?;
+        temp_file.write_all(data).context(IoOp::Write, &temp_path)?;
+        self.sync_file(&temp_file, &temp_path)?;
+        drop(temp_file);
+        self.backup_store()?;
+        fs::rename(&temp_path, store_path).context(IoOp::Rename, &temp_path)?;
+        self.sync_store_dir()?;
+        self.remove_journal()?;
+        self.advance_generation()?;
+        self.invalidate_cache();
+        self.clear_pending_value();
+        #[cfg(feature = "history")]
+        self.snapshot_store(data)?;
+        self.record_metrics(|metrics| metrics.record_bytes_written(data.len()));
+        trace_done!(timer, bytes = data.len(), "wrote store file");
+        Ok(())
+    }
+
+    /// Returns the parsed JSON of the store file.
+    ///
+    /// # Errors
+    ///
+    /// * Errors if the store file does not exist.
+    /// * Errors if the store file cannot be read.
+    /// * Errors if the store file cannot be deserialized.
+    pub(crate) fn get_store_as_parsed_json(&self) -> Result<Value> {
+        self.record_metrics(|metrics| metrics.record_get());
+        if let Some(value) = self.get_pending_value() {
+            self.record_metrics(|metrics| metrics.record_cache_hit());
+            return Ok(value);
+        }
+        if let Some(value) = self.get_cached_value() {
+            self.record_metrics(|metrics| metrics.record_cache_hit());
+            return Ok(value);
+        }
+        // A missing store file is noticed by reading it rather than checking beforehand,
+        // saving a system call on every read.
+        let fingerprint = self.get_cache_fingerprint().map_err(missing_store)?;
+        #[cfg(feature = "mmap")]
+        let mapped = self.map_store_file().map_err(missing_store)?;
+        #[cfg(feature = "mmap")]
+        let value = match mapped {
+            Some(map) => Some(self.parse_decoded(self.decode_mapped(&map))?),
+            None => None,
+        };
+        #[cfg(not(feature = "mmap"))]
+        let value = None;
+        let value = match value {
+            Some(value) => value,
+            None => {
+                let store_data = self.read_store_file().map_err(missing_store)?;
+                let journal = self.read_journal_file()?;
+                self.parse_journaled(store_data, journal.as_deref())?
+            }
+        };
+        if let Some(fingerprint) = fingerprint {
+            self.cache_value(fingerprint, &value);
+        }
+        self.publish_changes(&value, ChangeSource::External);
+        Ok(value)
+    }
+
+    /// Reads the raw contents of the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be read.
+    pub(crate) fn read_store_file(&self) -> Result<Vec<u8>> {
+        let store_path = self.get_store_path();
+        trace_span!(timer, "bland.read", path = %store_path.display());
+        #[cfg(feature = "test-util")]
+        let store_data = match &self.faults {
+            Some(faults) => faults.read(&store_path)?,
+            None => fs::read(&store_path).context(IoOp::Read, &store_path)?,
+        };
+        #[cfg(not(feature = "test-util"))]
+        let store_data = fs::read(&store_path).context(IoOp::Read, &store_path)?;
+        self.record_metrics(|metrics| metrics.record_bytes_read(store_data.len()));
+        trace_done!(timer, bytes = store_data.len(), "read store file");
+        Ok(store_data)
+    }
+
+    /// Decodes and parses the raw contents of a store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be decrypted, decompressed or deserialized.
+    fn parse_store_data(&self, store_data: Vec<u8>) -> Result<Value> {
+        self.parse_payload(self.verify_store_data(store_data)?)
+    }
+
+    /// Verifies the checksum and signature of the raw contents of a store file,
+    /// returning them without their footers.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the checksum or signature does not match.
+    fn verify_store_data(&self, store_data: Vec<u8>) -> Result<Vec<u8>> {
+        let store_data = match self.checksum {
+            true => checksum::verify_checksum(store_data)?,
+            false => store_data,
+        };
+        #[cfg(feature = "crypto")]
+        let store_data = match &self.signing_key {
+            Some(key) => crypto::verify_signature(store_data, key)?,
+            None => store_data,
+        };
+        Ok(store_data)
+    }
+
+    /// Decodes and parses the contents of a store file without its footers.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be decrypted, decompressed or deserialized.
+    fn parse_payload(&self, store_data: Vec<u8>) -> Result<Value> {
+        self.parse_decoded(self.decode(store_data)?)
+    }
+
+    /// Parses the decoded contents of a store file, decompressing them as they are parsed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the data cannot be decompressed, deserialized or its encrypted paths
+    /// cannot be decrypted.
+    pub(crate) fn parse_decoded(&self, decoded: Decoded) -> Result<Value> {
+        let value = match decoded {
+            Decoded::Plain(data) => self.parse_json(&data)?,
+            #[cfg(feature = "crypto")]
+            Decoded::Decrypted(data) => self.parse_json(&data)?,
+            #[cfg(feature = "compression")]
+            Decoded::Compressed(data, algorithm) => {
+                let reader = std::io::BufReader::new(compression::decompressor(&data, algorithm));
+                serde_json::from_reader(reader).map_err(|source| match source.is_io() {
+                    true => Error::from(std::io::Error::from(source)),
+                    false => Error::Corrupt {
+                        config: self.get_store_file_name(),
+                        source,
+                    },
+                })?
+            }
+        };
+        #[cfg(feature = "sharding")]
+        let value = self.load_shards(value)?;
+        #[cfg(feature = "crypto")]
+        let value = self.decrypt_fields(value)?;
+        Ok(value)
+    }
+
+    /// Returns the key the whole store file is encrypted with, which is `None`
+    /// if no key is set or only marked paths are encrypted.
+    #[cfg(feature = "crypto")]
+    fn get_whole_store_key(&self) -> Option<&crypto::EncryptionKey> {
+        match self.encrypted_paths.is_empty() {
+            true => self.encryption_key.as_ref(),
+            false => None,
+        }
+    }
+
+    /// Returns the header flags recording how the data was compressed before encryption.
+    #[cfg(feature = "crypto")]
+    fn get_header_flags(&self) -> u8 {
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return match self.compression_algorithm {
+                compression::CompressionAlgorithm::Gzip => crypto::FLAG_GZIP,
+                compression::CompressionAlgorithm::Deflate => crypto::FLAG_DEFLATE,
+            };
+        }
+        0
+    }
+
+    /// Encrypts the data with the given key and the store's cipher suite.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the key cannot be derived or the data cannot be encrypted.
+    #[cfg(feature = "crypto")]
+    fn encrypt(&self, key: &crypto::EncryptionKey, data: &[u8], flags: u8) -> Result<Vec<u8>> {
+        let kdf = match key {
+            crypto::EncryptionKey::Raw(_) => None,
+            crypto::EncryptionKey::Password(_) => Some(self.get_write_kdf_params()),
+        };
+        trace_span!(timer, "bland.encrypt", cipher = ?self.cipher_suite, password = kdf.is_some());
+        let key = self.resolve_encryption_key(key, kdf.as_ref())?;
+        let encrypted = crypto::encrypt_data(data, &key, self.cipher_suite, kdf.as_ref(), flags)?;
+        trace_done!(timer, bytes = encrypted.len(), "encrypted store");
+        Ok(encrypted)
+    }
+
+    /// Decrypts the data with the given key.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the header is invalid, the key cannot be derived or the data cannot be decrypted.
+    #[cfg(feature = "crypto")]
+    fn decrypt(&self, key: &crypto::EncryptionKey, data: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let header = crypto::read_header(data)?;
+        trace_span!(timer, "bland.decrypt", password = header.kdf.is_some());
+        let key = self.resolve_encryption_key(key, header.kdf.as_ref())?;
+        let decrypted = crypto::decrypt_data(data, &key)?;
+        trace_done!(timer, bytes = decrypted.len(), "decrypted store");
+        Ok(decrypted)
+    }
+
+    /// Replaces the values at the paths marked as encrypted with base64 encoded ciphertext.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a marked path is not a valid dot path or a value cannot be encrypted.
+    #[cfg(feature = "crypto")]
+    pub(crate) fn encrypt_fields(&self, mut value: Value) -> Result<Value> {
+        let key = match &self.encryption_key {
+            Some(key) => key,
+            None => return Ok(value),
+        };
+        for path in &self.encrypted_paths {
+            if let Some(field) = value.dot_get::<Value>(path)? {
+                let field = Zeroizing::new(field.to_string());
+                let encrypted_field = self.encrypt(key, field.as_bytes(), 0)?;
+                value.dot_set(
+                    path,
+                    base64::engine::general_purpose::STANDARD.encode(encrypted_field),
+                )?;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Replaces the base64 encoded ciphertext at the paths marked as encrypted with the decrypted values.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a marked path is not a valid dot path or a value cannot be decrypted.
+    #[cfg(feature = "crypto")]
+    fn decrypt_fields(&self, mut value: Value) -> Result<Value> {
+        let key = match &self.encryption_key {
+            Some(key) => key,
+            None => return Ok(value),
+        };
+        for path in &self.encrypted_paths {
+            // Values which are not encrypted were written before the path was marked, and
+            // are encrypted on the next write.
+            if let Some(Value::String(encrypted_field)) = value.dot_get::<Value>(path)? {
+                let encrypted_field = match base64::engine::general_purpose::STANDARD
+                    .decode(encrypted_field)
+                {
+                    Ok(encrypted_field) if crypto::has_header(&encrypted_field) => encrypted_field,
+                    _ => continue,
+                };
+                let field = self.decrypt(key, &encrypted_field)?;
+                let field: Value = serde_json::from_slice(&field).map_err(|_| Error::Decryption)?;
+                value.dot_set(path, field)?;
+            }
+        }
+        Ok(value)
+    }
+
+    /// Returns the key used to encrypt or decrypt data with the given key derivation parameters.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * A password is set but the data was not encrypted with a password.
+    /// * The key cannot be derived from the password.
+    #[cfg(feature = "crypto")]
+    fn resolve_encryption_key(
+        &self,
+        key: &crypto::EncryptionKey,
+        kdf: Option<&crypto::KdfParams>,
+    ) -> Result<Zeroizing<[u8; 32]>> {
+        let password = match key {
+            crypto::EncryptionKey::Raw(key) => return Ok(key.clone()),
+            crypto::EncryptionKey::Password(password) => password,
+        };
+        let kdf = kdf.ok_or(Error::Decryption)?;
+        let mut derived_key = self.derived_key.lock().unwrap_or_else(|e| e.into_inner());
+        match &*derived_key {
+            Some((cached_kdf, key)) if cached_kdf == kdf => Ok(key.clone()),
+            _ => {
+                let key = kdf.derive_key(password)?;
+                *derived_key = Some((*kdf, key.clone()));
+                Ok(key)
+            }
+        }
+    }
+
+    /// Returns the parameters to derive the password key with when writing the store.
+    /// The parameters of the existing store file are kept so every store has a single salt.
+    #[cfg(feature = "crypto")]
+    fn get_write_kdf_params(&self) -> crypto::KdfParams {
+        if let Some((kdf, _)) = *self.derived_key.lock().unwrap_or_else(|e| e.into_inner()) {
+            return kdf;
+        }
+        if let Ok(store_data) = fs::read(self.get_store_path()) {
+            if let Ok(crypto::Header { kdf: Some(kdf), .. }) = crypto::read_header(&store_data) {
+                return kdf;
+            }
+        }
+        crypto::KdfParams::generate()
+    }
+
+    fn parse_json(&self, store: &[u8]) -> Result<Value> {
+        lenient::from_slice(store).map_err(|source| Error::Corrupt {
+            config: self.get_store_file_name(),
+            source,
+        })
+    }
+
+    /// Returns the file name of the store file.
+    fn get_store_file_name(&self) -> String {
+        self.get_store_path()
+            .file_name()
+            .map(|file_name| file_name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    pub fn get_path(&self) -> PathBuf {
+        self.path.clone()
+    }
+
+    pub fn set_path(&mut self, new_path: PathBuf) {
+        self.path = new_path
+    }
+
+    pub fn set_project_name(&mut self, name: &str) {
+        self.project_name = name.to_owned();
+    }
+
+    pub fn get_project_name(&self) -> &str {
+        &self.project_name
+    }
+
+    pub fn set_config_name(&mut self, config_name: &str) {
+        self.config_name = config_name.to_owned();
+    }
+
+    pub fn get_config_name(&self) -> &str {
+        &self.config_name
+    }
+
+    pub fn set_project_suffix(&mut self, suffix: Option<&str>) {
+        self.project_suffix = suffix.map(str::to_owned);
+    }
+
+    pub fn get_project_suffix(&self) -> Option<&str> {
+        self.project_suffix.as_deref()
+    }
+
+    pub fn set_file_extension(&mut self, extension: &str) {
+        self.file_extension = extension.to_owned();
+    }
+
+    /// Sets whether a CRC32 checksum of the store is appended to the store file
+    /// and verified on every read, so bit rot and partial writes are detected.
+    ///
+    /// **NOTE:** Stores written without a checksum fail to be read with
+    /// [`Error::ChecksumMismatch`] once it is enabled.
+    pub fn set_checksum(&mut self, checksum: bool) {
+        self.checksum = checksum;
+    }
+
+    pub fn get_checksum(&self) -> bool {
+        self.checksum
+    }
+
+    /// Sets whether the store is read-only, in which case every method that would write
+    /// to the store directory returns [`Error::ReadOnly`] instead.
+    ///
+    /// Useful for inspection tools and system managed configuration files.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Error, Store};
+    /// let mut store = Store::new("read-only-app").unwrap();
+    /// store.set_read_only(true);
+    /// assert!(matches!(store.set("a", 1), Err(Error::ReadOnly)));
+    /// assert!(!store.store_dir_exists());
+    /// ```
+    pub fn set_read_only(&mut self, read_only: bool) {
+        self.read_only = read_only;
+    }
+
+    pub fn get_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Sets whether mutations such as [`set`](Store::set) create the store when it does not
+    /// exist yet, which is the default.
+    ///
+    /// When disabled, they return [`Error::NotFound`] instead and
+    /// [`init_store`](Store::init_store) is the only way to create the store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Error, Store};
+    /// let mut store = Store::new("create-missing-app").unwrap();
+    /// store.set_create_missing(false);
+    /// assert!(matches!(store.set("a", 1), Err(Error::NotFound)));
+    /// store.init_store().unwrap();
+    /// store.set("a", 1).unwrap();
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_create_missing(&mut self, create_missing: bool) {
+        self.create_missing = create_missing;
+    }
+
+    pub fn get_create_missing(&self) -> bool {
+        self.create_missing
+    }
+
+    /// Returns [`Error::NotFound`] if the store does not exist and may not be created
+    /// by mutations.
+    pub(crate) fn check_create_missing(&self) -> Result<()> {
+        match self.create_missing || self.store_exists() || self.is_dirty() {
+            true => Ok(()),
+            false => Err(Error::NotFound),
+        }
+    }
+
+    /// Returns [`Error::ReadOnly`] if the store is read-only.
+    pub(crate) fn check_writable(&self) -> Result<()> {
+        match self.read_only {
+            true => Err(Error::ReadOnly),
+            false => Ok(()),
+        }
+    }
+
+    /// Sets the active profile, e.g. `dev`, `staging` or `prod`.
+    pub fn set_profile(&mut self, profile: &str) {
+        self.profile = Some(profile.to_owned());
+    }
+
+    /// Clears the active profile so the default configuration file is used.
+    pub fn clear_profile(&mut self) {
+        self.profile = None;
+    }
+
+    pub fn get_profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Sets the active profile from the environment variable `key`.
+    /// The profile is left unchanged if the variable is unset or empty.
+    ///
+    /// Returns a boolean indicating whether the profile was set.
+    pub fn set_profile_from_env(&mut self, key: &str) -> bool {
+        match env::var(key) {
+            Ok(profile) if !profile.is_empty() => {
+                self.profile = Some(profile);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Sets the contents the store is created with, and reset to by [`Store::clear`].
+    pub fn set_defaults(&mut self, defaults: Value) {
+        self.defaults = Some(defaults);
+    }
+
+    pub fn get_defaults(&self) -> Option<&Value> {
+        self.defaults.as_ref()
+    }
+
+    /// Sets whether the store file is pretty printed, indented with two spaces.
+    /// [`set_pretty_style`](Store::set_pretty_style) sets a different layout.
+    pub fn set_pretty(&mut self, pretty: bool) {
+        self.pretty = pretty.then(PrettyStyle::default);
+    }
+
+    pub fn get_file_extension(&self) -> &str {
+        &self.file_extension
+    }
+
+    /// Sets the encryption key. The key must be less than or equal to 32 bytes.
+    ///
+    /// The key is zero padded to 32 bytes, which makes short keys weak.
+    /// Prefer [`set_password`](Store::set_password) for human chosen keys.
+    #[cfg(feature = "crypto")]
+    pub fn set_encryption_key(&mut self, key: &str) -> Result<()> {
+        let key_bytes = key.as_bytes();
+        if key_bytes.len() > 32 {
+            return Err(Error::InvalidKeyLength);
+        }
+        // The key is padded in place, so no copy of it outlives the key, which is wiped
+        // when dropped.
+        let mut encryption_key = crypto::EncryptionKey::Raw(Zeroizing::new([0; 32]));
+        if let crypto::EncryptionKey::Raw(final_bytes) = &mut encryption_key {
+            final_bytes[..key_bytes.len()].copy_from_slice(key_bytes);
+        }
+        self.replace_encryption_key(Some(encryption_key));
+        Ok(())
+    }
+
+    /// Sets a raw 32 byte encryption key, which is used without key derivation.
+    #[cfg(feature = "crypto")]
+    pub fn set_encryption_key_bytes(&mut self, key: [u8; 32]) {
+        self.replace_encryption_key(Some(crypto::EncryptionKey::Raw(Zeroizing::new(key))));
+    }
+
+    /// Sets the password the encryption key is derived from.
+    ///
+    /// The key is derived with Argon2id using a random salt, which is
+    /// generated when the store is first written and kept in the store file's header.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("password-app").unwrap();
+    /// store.set_password("correct horse battery staple");
+    /// store.set("token", "secret").unwrap();
+    /// assert_eq!(store.get("token").unwrap().unwrap(), "secret");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn set_password(&mut self, password: &str) {
+        self.replace_encryption_key(Some(crypto::EncryptionKey::Password(Zeroizing::new(
+            password.to_owned(),
+        ))));
+    }
+
+    /// Re-encrypts the store with `new_key` and makes it the store's key.
+    /// The store file is replaced atomically, so it is never left half rotated.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{EncryptionKey, Store, Zeroizing};
+    /// let mut store = Store::new("rotate-key-app").unwrap();
+    /// store.set_password("old password");
+    /// store.set("token", "secret").unwrap();
+    /// store.rotate_key(EncryptionKey::Password(Zeroizing::new("new password".to_owned()))).unwrap();
+    /// assert_eq!(store.get("token").unwrap().unwrap(), "secret");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store does not exist.
+    /// * The store cannot be decrypted with the current key.
+    /// * The new key cannot be derived or the store file fails to be written to, in which
+    ///   case the current key is kept.
+    #[cfg(feature = "crypto")]
+    pub fn rotate_key(&mut self, new_key: crypto::EncryptionKey) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let value = self.get_store_as_parsed_json()?;
+        let old_key = self.replace_encryption_key(Some(new_key));
+        if let Err(e) = self.write_with_new_salt(value) {
+            self.replace_encryption_key(old_key);
+            return Err(e);
+        }
+        trace_info!(path = %self.get_store_path().display(), "rotated encryption key");
+        Ok(())
+    }
+
+    /// Writes the contents of the store encrypted with the current key. A password gets
+    /// a new salt rather than the one in the existing file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the key cannot be derived or the store file fails to be written to.
+    #[cfg(feature = "crypto")]
+    fn write_with_new_salt(&mut self, value: Value) -> Result<()> {
+        if let Some(crypto::EncryptionKey::Password(password)) = &self.encryption_key {
+            let kdf = crypto::KdfParams::generate();
+            let key = kdf.derive_key(password)?;
+            *self
+                .derived_key
+                .get_mut()
+                .unwrap_or_else(|e| e.into_inner()) = Some((kdf, key));
+        }
+        self.write_value(value)
+    }
+
+    /// Decrypts the store with `old_key` and re-encrypts it with the current key.
+    ///
+    /// This recovers a store which is still encrypted with a previous key, for example
+    /// when a rotation was interrupted before the application saved its new key.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store does not exist.
+    /// * The store cannot be decrypted with `old_key`.
+    /// * The store file fails to be written to.
+    #[cfg(feature = "crypto")]
+    pub fn reencrypt_from(&mut self, old_key: crypto::EncryptionKey) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let current_key = self.replace_encryption_key(Some(old_key));
+        let value = self.get_store_as_parsed_json();
+        self.replace_encryption_key(current_key);
+        self.write_value(value?)?;
+        trace_info!(path = %self.get_store_path().display(), "re-encrypted store");
+        Ok(())
+    }
+
+    /// Encrypts an existing plain text or compressed store with `key` and makes it the
+    /// store's key, so encryption can be enabled for a store that already holds data.
+    ///
+    /// The store file is replaced atomically. The plain text it replaced is then
+    /// overwritten with zeros, as are the journal and any backups, which are removed.
+    /// Overwriting is best effort: file systems which copy on write and SSDs may keep
+    /// the old bytes elsewhere. Snapshots kept with the `history` feature are not changed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{EncryptionKey, Store, Zeroizing};
+    /// let mut store = Store::new("encrypt-existing-app").unwrap();
+    /// store.set("token", "secret").unwrap();
+    /// store.encrypt_existing(EncryptionKey::Password(Zeroizing::new("password".to_owned()))).unwrap();
+    /// assert_eq!(store.get("token").unwrap().unwrap(), "secret");
+    /// assert!(std::fs::read(store.get_store_path()).unwrap().starts_with(b"BLND"));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * The store cannot be read without encryption, for example because it is
+    ///   already encrypted.
+    /// * The replaced files cannot be opened, the key cannot be derived or the store file
+    ///   fails to be written to, in which case the current key is kept.
+    /// * The replaced files cannot be overwritten or removed.
+    #[cfg(feature = "crypto")]
+    pub fn encrypt_existing(&mut self, key: crypto::EncryptionKey) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let old_key = self.replace_encryption_key(None);
+        let replaced = match self.replace_plain_text(key) {
+            Ok(replaced) => replaced,
+            Err(e) => {
+                self.replace_encryption_key(old_key);
+                return Err(e);
+            }
+        };
+        for (file, path) in replaced {
+            overwrite_with_zeros(&file).context(IoOp::Write, &path)?;
+        }
+        for backup in self.list_backups()? {
+            wipe_file(&backup.path)?;
+        }
+        trace_info!(path = %self.get_store_path().display(), "encrypted existing store");
+        Ok(())
+    }
+
+    /// Reads the store without encryption and writes it encrypted with `key`, returning
+    /// the replaced store file and journal, kept open to overwrite them.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read, the replaced files cannot be opened, the key
+    /// cannot be derived or the store file fails to be written to.
+    #[cfg(feature = "crypto")]
+    fn replace_plain_text(&mut self, key: crypto::EncryptionKey) -> Result<Vec<(File, PathBuf)>> {
+        let value = self.get_store_as_parsed_json()?;
+        let mut replaced = Vec::new();
+        for path in [self.get_store_path(), self.get_journal_path()] {
+            match fs::OpenOptions::new().write(true).open(&path) {
+                Ok(file) => replaced.push((file, path)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e).context(IoOp::Write, &path),
+            }
+        }
+        self.replace_encryption_key(Some(key));
+        // The plain text is not copied to a backup on the way out.
+        let backup = std::mem::take(&mut self.backup);
+        let written = self.write_with_new_salt(value);
+        self.backup = backup;
+        written?;
+        Ok(replaced)
+    }
+
+    /// Replaces the encryption key, returning the previous one.
+    #[cfg(feature = "crypto")]
+    fn replace_encryption_key(
+        &mut self,
+        key: Option<crypto::EncryptionKey>,
+    ) -> Option<crypto::EncryptionKey> {
+        *self
+            .derived_key
+            .get_mut()
+            .unwrap_or_else(|e| e.into_inner()) = None;
+        self.invalidate_cache();
+        std::mem::replace(&mut self.encryption_key, key)
+    }
+
+    /// Sets the cipher used when writing encrypted stores, defaults to AES-256-GCM.
+    ///
+    /// Reads always use the cipher recorded in the store file,
+    /// so existing stores stay readable after the cipher is changed.
+    #[cfg(feature = "crypto")]
+    pub fn set_cipher_suite(&mut self, cipher_suite: crypto::CipherSuite) {
+        self.cipher_suite = cipher_suite;
+    }
+
+    #[cfg(feature = "crypto")]
+    pub fn get_cipher_suite(&self) -> crypto::CipherSuite {
+        self.cipher_suite
+    }
+
+    /// Marks the value at the given path to be encrypted on its own.
+    ///
+    /// Once a path is marked, the store file is written as plain JSON and only the
+    /// values at the marked paths are encrypted, as base64 encoded strings.
+    /// Paths have no effect unless an encryption key or password is set. Values stored
+    /// before their path was marked are read as they are and encrypted on the next write.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("mark-encrypted-app").unwrap();
+    /// store.set_password("hunter2");
+    /// store.mark_encrypted("credentials");
+    /// store.set("credentials.token", "secret").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// assert_eq!(store.get("credentials.token").unwrap().unwrap(), "secret");
+    ///
+    /// let contents = std::fs::read_to_string(store.get_store_path()).unwrap();
+    /// assert!(contents.contains("dark"));
+    /// assert!(!contents.contains("secret"));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn mark_encrypted(&mut self, path: &str) {
+        if !self.encrypted_paths.iter().any(|p| p == path) {
+            self.encrypted_paths.push(path.to_owned());
+        }
+    }
+
+    /// Returns the paths marked to be encrypted on their own.
+    #[cfg(feature = "crypto")]
+    pub fn get_encrypted_paths(&self) -> &[String] {
+        &self.encrypted_paths
+    }
+
+    /// Sets the key the store file is signed with.
+    ///
+    /// The store is written with an HMAC-SHA256 tag appended, and reads fail with
+    /// [`Error::SignatureInvalid`] if the file was changed without the key.
+    /// Unlike encryption, the store stays readable by humans.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Error, Store};
+    /// let mut store = Store::new("signing-key-app").unwrap();
+    /// store.set_signing_key(b"signing key");
+    /// store.set("theme", "dark").unwrap();
+    ///
+    /// // Hand edit the store file.
+    /// std::fs::write(store.get_store_path(), r#"{"theme":"pink"}"#).unwrap();
+    /// assert!(matches!(store.get("theme"), Err(Error::SignatureInvalid)));
+    ///
+    /// // Accept the hand edit.
+    /// store.resign().unwrap();
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "pink");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    #[cfg(feature = "crypto")]
+    pub fn set_signing_key(&mut self, key: &[u8]) {
+        self.signing_key = Some(key.to_vec());
+    }
+
+    /// Clears the signing key so the store file is no longer signed or verified.
+    #[cfg(feature = "crypto")]
+    pub fn clear_signing_key(&mut self) {
+        self.signing_key = None;
+    }
+
+    /// Accepts the current contents of the store file and signs it again,
+    /// for example after an expected hand edit.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    #[cfg(feature = "crypto")]
+    pub fn resign(&self) -> Result<()> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        let store_data = self.read_store_file()?;
+        let store_data = match self.checksum {
+            true => checksum::verify_checksum(store_data)?,
+            false => store_data,
+        };
+        let value = self.parse_payload(crypto::strip_signature(store_data))?;
+        self.write_value(value)
+    }
+
+    /// Returns the raw encryption key, or `None` if no key or a password is set.
+    #[cfg(feature = "crypto")]
+    pub fn get_encryption_key(&self) -> Option<[u8; 32]> {
+        match self.encryption_key {
+            Some(crypto::EncryptionKey::Raw(ref key)) => Some(**key),
+            _ => None,
+        }
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn set_compressed(&mut self, compressed: bool) {
+        self.compressed = compressed;
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn get_compressed(&self) -> bool {
+        self.compressed
+    }
+
+    /// Sets the compression level, from 0 (no compression) to 9 (best compression).
+    /// Levels above 9 are clamped to 9. Defaults to 6.
+    #[cfg(feature = "compression")]
+    pub fn set_compression_level(&mut self, level: u32) {
+        self.compression_level = level.min(9);
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn get_compression_level(&self) -> u32 {
+        self.compression_level
+    }
+
+    /// Sets the compression algorithm, defaults to gzip.
+    ///
+    /// **NOTE:** The store is read with the same algorithm, so changing it makes
+    /// existing compressed stores unreadable until they are rewritten with
+    /// [`repack`](Store::repack).
+    #[cfg(feature = "compression")]
+    pub fn set_compression_algorithm(&mut self, algorithm: compression::CompressionAlgorithm) {
+        self.compression_algorithm = algorithm;
+    }
+
+    #[cfg(feature = "compression")]
+    pub fn get_compression_algorithm(&self) -> compression::CompressionAlgorithm {
+        self.compression_algorithm
+    }
+
+    /// Rewrites the store file with the current compression, encryption and pretty
+    /// printing settings. Existing store files keep the encoding they were written with
+    /// until their next write, so this applies changed settings straight away.
+    ///
+    #[cfg_attr(
+        feature = "compression",
+        doc = "A store which was compressed, or not, before [`set_compressed`](Store::set_compressed)
+or [`set_compression_algorithm`](Store::set_compression_algorithm) was changed is
+read in its previous encoding."
+    )]
+    #[cfg_attr(
+        feature = "crypto",
+        doc = "Changing the encryption key is done with [`rotate_key`](Store::rotate_key) instead."
+    )]
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("repack-app").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// store.set_pretty(true);
+    /// store.repack().unwrap();
+    /// let contents = std::fs::read_to_string(store.get_store_path()).unwrap();
+    /// assert_eq!(contents, "{\n  \"theme\": \"dark\"\n}");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized in any encoding.
+    /// * The store file fails to be written to.
+    pub fn repack(&mut self) -> Result<()> {
+        let _lock = self.lock_store_file()?;
+        let value = match self.get_store_as_parsed_json() {
+            Ok(value) => value,
+            #[cfg(feature = "compression")]
+            Err(e) if self.store_exists() => self.parse_previous_compression().ok_or(e)?,
+            Err(e) => return Err(e),
+        };
+        self.write_value(value)?;
+        trace_info!(path = %self.get_store_path().display(), "repacked store");
+        Ok(())
+    }
+
+    /// Reads the store with each compression setting other than the current one, returning
+    /// the contents from the first that can be read.
+    #[cfg(feature = "compression")]
+    fn parse_previous_compression(&mut self) -> Option<Value> {
+        let current = (self.compressed, self.compression_algorithm);
+        let candidates = [
+            (false, current.1),
+            (true, compression::CompressionAlgorithm::Gzip),
+            (true, compression::CompressionAlgorithm::Deflate),
+        ];
+        let mut value = None;
+        for (compressed, algorithm) in candidates {
+            if (compressed, algorithm) == current || (!compressed && !current.0) {
+                continue;
+            }
+            self.compressed = compressed;
+            self.compression_algorithm = algorithm;
+            self.invalidate_cache();
+            value = self.get_store_as_parsed_json().ok();
+            if value.is_some() {
+                break;
+            }
+        }
+        (self.compressed, self.compression_algorithm) = current;
+        self.invalidate_cache();
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs,
+        path::PathBuf,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    #[cfg(feature = "test-util")]
+    use crate::testing::FaultInjector;
+    use crate::{
+        testing::TempStore, BackupPolicy, ChangeSource, DirProvider, Durability, Error, ErrorKind,
+        Flag, Flags, FreshnessPolicy, Indent, Interpolation, IoOp, Metrics, NamespaceOptions,
+        PatchOp, Permissions, PrettyStyle, Recovery, SharedStore, Store, StoreKind, StoreManager,
+    };
+
+    #[cfg(feature = "compression")]
+    use crate::CompressionAlgorithm;
+    #[cfg(feature = "crypto")]
+    use crate::{CipherSuite, EncryptionKey, Zeroizing};
+    #[cfg(feature = "crypto")]
+    use serde_json::{json, Value};
+
+    fn clean_store(x: &Store) {
+        if x.store_exists() {
+            x.delete_store_dir().unwrap();
+        }
+    }
+
+    #[test]
+    fn set_get() {
+        let mut x = TempStore::new("store_get_test").unwrap();
+        x.set_config_name("2");
+        let data_1 = "test1";
+        let data_2 = [4, 2, 7];
+        x.set("a.b", data_1).unwrap();
+        x.set("c", data_2).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), data_1);
+        assert_eq!(
+            x.get("c").unwrap().unwrap().as_array().unwrap().len(),
+            data_2.len()
+        );
+        assert_eq!(x.get("d").unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_set() {
+        let x = TempStore::new("store_invalid_set_test").unwrap();
+        x.set("x", "test1").unwrap();
+        match x.set("x.a", "test2") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(
+                e.to_string(),
+                json_dotpath::Error::BadPathElement.to_string()
+            ),
+        };
+    }
+
+    #[test]
+    fn delete() {
+        let x = TempStore::new("store_delete_test").unwrap();
+        let data = "test1";
+        x.set("a.b", data).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), data);
+        x.delete("a").unwrap();
+        assert_eq!(x.get("a").unwrap(), None);
+    }
+
+    #[test]
+    fn init() {
+        let x = TempStore::new("clear_test").unwrap();
+        let data_1 = "test1";
+        let data_2 = [4, 2, 7];
+
+        x.set("a.b", data_1).unwrap();
+        x.set("c", data_2).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), data_1);
+        assert_eq!(
+            x.get("c").unwrap().unwrap().as_array().unwrap().len(),
+            data_2.len()
+        );
+        x.init_store().unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), data_1);
+        x.clear().unwrap();
+        assert_eq!(x.get("a.b").unwrap(), None);
+        assert_eq!(x.get("c").unwrap(), None);
+    }
+
+    #[test]
+    fn set_get_config_name() {
+        let mut x = Store::new("store_set_config_name_test").unwrap();
+        x.set_config_name("test");
+        assert_eq!(x.get_config_name(), "test");
+        clean_store(&x)
+    }
+
+    #[test]
+    fn set_get_project_name() {
+        let mut x = Store::new("store_set_config_name_test").unwrap();
+        x.set_project_name("test");
+        assert_eq!(x.get_project_name(), "test");
+        clean_store(&x)
+    }
+
+    #[test]
+    fn set_get_project_suffix() {
+        let mut x = Store::new("store_set_project_suffix_test").unwrap();
+        x.set_project_suffix(Some("test"));
+        assert_eq!(x.get_project_suffix(), Some("test"));
+        clean_store(&x)
+    }
+
+    #[test]
+    fn set_get_file_extension() {
+        let mut x = Store::new("store_set_file_extension_test").unwrap();
+        x.set_file_extension("test");
+        assert_eq!(x.get_file_extension(), "test");
+        clean_store(&x)
+    }
+
+    #[test]
+    fn owned_names() {
+        fn assert_static<T: 'static + Send + Sync>(_: &T) {}
+
+        let x = {
+            let project_name = format!("store_{}_test", "owned_names");
+            let mut x = Store::new(&project_name).unwrap();
+            x.set_config_name(&String::from("settings"));
+            x
+        };
+        assert_static(&x);
+        assert_eq!(x.get_project_name(), "store_owned_names_test");
+        assert_eq!(x.get_config_name(), "settings");
+        clean_store(&x)
+    }
+
+    #[test]
+    fn with_project() {
+        let mut x = Store::with_project("com", "Example Org", "Store With Project Test").unwrap();
+        assert_eq!(x.get_project_suffix(), None);
+        #[cfg(target_os = "linux")]
+        assert_eq!(x.get_project_name(), "storewithprojecttest");
+        #[cfg(target_os = "macos")]
+        assert_eq!(
+            x.get_project_name(),
+            "com.Example-Org.Store-With-Project-Test"
+        );
+        #[cfg(target_os = "windows")]
+        assert_eq!(x.get_project_name(), "Example Org\\Store With Project Test");
+
+        // Missing parents of the store directory are created.
+        x.set_path(PathBuf::from("./store_with_project_test"));
+        x.set("a", 1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        fs::remove_dir_all("./store_with_project_test").unwrap();
+    }
+
+    #[test]
+    fn dir_provider() {
+        struct Fixed;
+
+        impl DirProvider for Fixed {
+            fn config_dir(&self) -> Option<PathBuf> {
+                Some(PathBuf::from("./"))
+            }
+        }
+
+        struct Missing;
+
+        impl DirProvider for Missing {
+            fn config_dir(&self) -> Option<PathBuf> {
+                None
+            }
+        }
+
+        let x = Store::with_dir_provider("store_dir_provider_test", Fixed).unwrap();
+        assert_eq!(x.get_path(), PathBuf::from("./"));
+        x.set("a", 1).unwrap();
+        assert!(PathBuf::from("./store_dir_provider_test-rs/config.json").exists());
+        assert!(matches!(
+            Store::with_dir_provider("store_dir_provider_test", Missing),
+            Err(Error::ConfigDir)
+        ));
+        clean_store(&x)
+    }
+
+    #[test]
+    fn store_kind() {
+        struct Split;
+
+        impl DirProvider for Split {
+            fn config_dir(&self) -> Option<PathBuf> {
+                Some(PathBuf::from("./config"))
+            }
+
+            fn cache_dir(&self) -> Option<PathBuf> {
+                Some(PathBuf::from("./cache"))
+            }
+
+            fn state_dir(&self) -> Option<PathBuf> {
+                None
+            }
+        }
+
+        let mut x = Store::with_dir_provider("store_store_kind_test", Split).unwrap();
+        assert_eq!(x.get_kind(), StoreKind::Config);
+        x.set_kind(StoreKind::Cache).unwrap();
+        assert_eq!(x.get_path(), PathBuf::from("./cache"));
+        x.set_kind(StoreKind::Data).unwrap();
+        assert_eq!(x.get_path(), PathBuf::from("./config"));
+        assert!(matches!(
+            x.set_kind(StoreKind::State),
+            Err(Error::ConfigDir)
+        ));
+        assert_eq!(x.get_kind(), StoreKind::Data);
+    }
+
+    #[test]
+    fn portable() {
+        let exe = std::env::current_exe().unwrap();
+        let mut x = Store::portable("store_portable_test").unwrap();
+        assert_eq!(x.get_path(), exe.parent().unwrap());
+        x.set_kind(StoreKind::Cache).unwrap();
+        assert_eq!(x.get_path(), exe.parent().unwrap());
+    }
+
+    #[test]
+    fn dir_env() {
+        let mut x = Store::new("store_dir_env_test").unwrap();
+        x.set_path(PathBuf::from("./"));
+        assert_eq!(x.get_dir_env(), None);
+        assert_eq!(x.get_default_dir_env(), "STORE_DIR_ENV_TEST_CONFIG_DIR");
+        x.set_dir_env(Some("BLAND_STORE_DIR_ENV_TEST_DIR"));
+        std::env::set_var(
+            "BLAND_STORE_DIR_ENV_TEST_DIR",
+            "./store_dir_env_test-override",
+        );
+        assert_eq!(
+            x.get_store_path(),
+            PathBuf::from("./store_dir_env_test-override/config.json")
+        );
+        x.set("a", 1).unwrap();
+        assert!(x.get_store_path().exists());
+        // A directory set by the environment may be shared, so it is never deleted whole.
+        assert_eq!(x.delete_store_dir().unwrap_err().kind(), ErrorKind::Io);
+        assert!(x.get_store_path().exists());
+        fs::remove_dir_all("./store_dir_env_test-override").unwrap();
+        std::env::set_var("BLAND_STORE_DIR_ENV_TEST_DIR", "");
+        assert_eq!(
+            x.get_store_dir_path(),
+            PathBuf::from("./store_dir_env_test-rs")
+        );
+        std::env::set_var("BLAND_STORE_DIR_ENV_TEST_DIR", "./elsewhere");
+        x.set_dir_env(None);
+        assert_eq!(
+            x.get_store_dir_path(),
+            PathBuf::from("./store_dir_env_test-rs")
+        );
+        std::env::remove_var("BLAND_STORE_DIR_ENV_TEST_DIR");
+    }
+
+    #[test]
+    fn configs() {
+        let mut x = TempStore::new("store_configs_test").unwrap();
+        assert!(x.list_configs().unwrap().is_empty());
+        x.set_backup(BackupPolicy { keep: 2 });
+        x.set("a", 1).unwrap();
+        x.set("a", 2).unwrap();
+        x.set_profile("dev");
+        x.set("a", 3).unwrap();
+        x.clear_profile();
+        x.set_undo_depth(5);
+        x.set_deferred(true);
+        x.set("a", 4).unwrap();
+        x.open_config("work").unwrap();
+        assert!(!x.undo().unwrap());
+        x.set("b", 1).unwrap();
+        x.flush().unwrap();
+        assert_eq!(x.list_configs().unwrap(), vec!["config", "work"]);
+        assert_eq!(x.get("a").unwrap(), None);
+        x.open_config("config").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 4);
+    }
+
+    #[test]
+    fn store_manager() {
+        let mut x = Store::new("store_store_manager_test").unwrap();
+        x.set_path(PathBuf::from("./"));
+        x.set_file_extension("conf");
+        x.set_sort_keys(true);
+        x.set("a", 1).unwrap();
+        x.set_profile("dev");
+        x.set("a", 2).unwrap();
+        x.set_read_only(true);
+        assert!(matches!(
+            StoreManager::from(x.clone_settings()).delete_all(),
+            Err(Error::ReadOnly)
+        ));
+        x.set_read_only(false);
+        let manager = StoreManager::from(x);
+        let work = manager.open("work");
+        assert_eq!(
+            work.get_store_path(),
+            PathBuf::from("./store_store_manager_test-rs/work.conf")
+        );
+        assert!(work.get_sort_keys());
+        work.set("b", 1).unwrap();
+        assert_eq!(manager.list_configs().unwrap(), vec!["config", "work"]);
+        // The three store files and their generation files.
+        let usage = manager.disk_usage().unwrap();
+        assert_eq!(usage, 7 + 7 + 7 + 3);
+        let backup_dir = PathBuf::from("./store_store_manager_test-backup");
+        let copies = manager.backup_all(&backup_dir).unwrap();
+        assert_eq!(copies.len(), 3);
+        assert!(backup_dir.join("config.dev.conf").exists());
+        assert!(backup_dir.join("config.dev.generation").exists());
+        fs::remove_dir_all(backup_dir).unwrap();
+        manager.delete_all().unwrap();
+        assert!(!manager.get_store_dir_path().exists());
+        assert_eq!(manager.disk_usage().unwrap(), 0);
+    }
+
+    #[cfg(feature = "sharding")]
+    #[test]
+    fn backup_all_sharded() {
+        let mut x = TempStore::new("store_backup_all_sharded_test").unwrap();
+        x.set_sharded(true);
+        x.set("a.b", 1).unwrap();
+        x.set("c", serde_json::json!([1, 2])).unwrap();
+        let backup_dir = x.path().join("backup");
+        let copies = StoreManager::from(x.clone_settings())
+            .backup_all(&backup_dir)
+            .unwrap();
+        assert_eq!(copies, vec![backup_dir.join("config.json")]);
+        assert_eq!(
+            fs::read_dir(backup_dir.join("config.shards"))
+                .unwrap()
+                .count(),
+            2
+        );
+
+        // The copies are a complete store.
+        let mut y = Store::new("store_backup_all_sharded_test").unwrap();
+        y.set_path(x.path().join("restored"));
+        fs::create_dir_all(y.get_store_dir_path()).unwrap();
+        for entry in fs::read_dir(&backup_dir).unwrap() {
+            let entry = entry.unwrap();
+            let to = y.get_store_dir_path().join(entry.file_name());
+            match entry.file_type().unwrap().is_dir() {
+                true => {
+                    fs::create_dir(&to).unwrap();
+                    for shard in fs::read_dir(entry.path()).unwrap() {
+                        let shard = shard.unwrap();
+                        fs::copy(shard.path(), to.join(shard.file_name())).unwrap();
+                    }
+                }
+                false => {
+                    fs::copy(entry.path(), to).unwrap();
+                }
+            }
+        }
+        assert_eq!(y.get("a.b").unwrap().unwrap(), 1);
+        assert_eq!(y.get("c.1").unwrap().unwrap(), 2);
+    }
+
+    #[test]
+    fn delete_config() {
+        let mut x = TempStore::new("store_delete_config_test").unwrap();
+        assert!(matches!(x.delete_config(), Err(Error::NotFound)));
+        x.set("a", 1).unwrap();
+        x.set_profile("dev");
+        x.set("a", 2).unwrap();
+        x.delete_config().unwrap();
+        assert!(!x.store_exists());
+        assert!(x.get_lock_path().exists());
+        x.clear_profile();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        assert!(x.list_profiles().unwrap().is_empty());
+        x.delete_store_dir().unwrap();
+        assert!(!x.store_dir_exists());
+    }
+
+    #[test]
+    fn rename_config() {
+        let mut x = TempStore::new("store_rename_config_test").unwrap();
+        assert!(matches!(
+            x.rename_config("config", "other"),
+            Err(Error::NotFound)
+        ));
+        x.set_deferred(true);
+        x.set("a", 1).unwrap();
+        x.duplicate_config("config", "copy").unwrap();
+        x.rename_config("config", "moved").unwrap();
+        assert_eq!(x.list_configs().unwrap(), vec!["copy", "moved"]);
+        x.open_config("moved").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.set_profile("dev");
+        x.set("a", 2).unwrap();
+        x.duplicate_config("moved", "copy").unwrap();
+        x.open_config("copy").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+        x.clear_profile();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+    }
+
+    #[test]
+    fn rename_config_versions() {
+        let mut x = TempStore::new("store_rename_config_versions_test").unwrap();
+        x.set_backup(BackupPolicy { keep: 2 });
+        x.set("a", 1).unwrap();
+        x.set("a", 2).unwrap();
+        x.duplicate_config("config", "copy").unwrap();
+        x.rename_config("config", "moved").unwrap();
+        assert!(x.list_backups().unwrap().is_empty());
+
+        // The backups move and are copied along with the configuration.
+        x.open_config("moved").unwrap();
+        assert_eq!(x.list_backups().unwrap().len(), 2);
+        x.restore_from_backup(1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.open_config("copy").unwrap();
+        x.restore_from_backup(1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+
+        // The backups of an overwritten configuration are removed with it.
+        x.set_backup(BackupPolicy { keep: 0 });
+        x.open_config("new").unwrap();
+        x.set("a", 3).unwrap();
+        x.rename_config("new", "copy").unwrap();
+        x.open_config("copy").unwrap();
+        assert!(x.list_backups().unwrap().is_empty());
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn rename_config_history() {
+        let mut x = TempStore::new("store_rename_config_history_test").unwrap();
+        x.set_history(2);
+        x.set("a", 1).unwrap();
+        x.duplicate_config("config", "copy").unwrap();
+        x.rename_config("config", "moved").unwrap();
+        assert!(x.snapshots().unwrap().is_empty());
+        x.open_config("moved").unwrap();
+        let snapshot = x.snapshots().unwrap().pop().unwrap();
+        x.set("a", 2).unwrap();
+        x.revert_to(snapshot.id).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.open_config("copy").unwrap();
+        assert_eq!(x.snapshots().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn rename_config_to_itself() {
+        let mut x = TempStore::new("store_rename_config_to_itself_test").unwrap();
+        x.set_journal(true);
+        x.set("a", "a".repeat(1000)).unwrap();
+        x.set("b", 2).unwrap();
+        assert!(x.get_journal_path().exists());
+        x.duplicate_config("config", "config").unwrap();
+        x.rename_config("config", "config").unwrap();
+        assert_eq!(x.list_configs().unwrap(), vec!["config"]);
+        assert!(x.get_journal_path().exists());
+        assert_eq!(x.get("a").unwrap().unwrap(), "a".repeat(1000));
+        assert_eq!(x.get("b").unwrap().unwrap(), 2);
+    }
+
+    #[cfg(all(feature = "crypto", feature = "compression"))]
+    #[test]
+    fn duplicate_encrypted_config() {
+        let mut x = TempStore::new("store_duplicate_encrypted_config_test").unwrap();
+        x.set_encryption_key_bytes([7; 32]);
+        x.set_compressed(true);
+        x.set("secret", "value").unwrap();
+        x.duplicate_config("config", "copy").unwrap();
+        x.open_config("copy").unwrap();
+        assert_eq!(x.get("secret").unwrap().unwrap(), "value");
+    }
+
+    #[test]
+    fn read_only() {
+        let mut x = TempStore::new("store_read_only_test").unwrap();
+        x.set("a", 1).unwrap();
+        x.set_read_only(true);
+        assert!(x.get_read_only());
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        assert!(matches!(x.set("a", 2), Err(Error::ReadOnly)));
+        assert!(matches!(x.delete("a"), Err(Error::ReadOnly)));
+        assert!(matches!(x.init_store(), Err(Error::ReadOnly)));
+        assert!(matches!(x.clear(), Err(Error::ReadOnly)));
+        assert!(matches!(x.delete_config(), Err(Error::ReadOnly)));
+        assert!(matches!(x.delete_store_dir(), Err(Error::ReadOnly)));
+        assert!(matches!(
+            x.duplicate_config("config", "copy"),
+            Err(Error::ReadOnly)
+        ));
+        x.set_profile("dev");
+        assert!(matches!(x.set("a", 2), Err(Error::ReadOnly)));
+        assert!(!x.store_exists());
+        x.clear_profile();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.set_read_only(false);
+    }
+
+    #[test]
+    fn create_missing() {
+        let mut x = TempStore::new("store_create_missing_test").unwrap();
+        x.set_create_missing(false);
+        assert!(!x.get_create_missing());
+        assert!(matches!(x.set("a", 1), Err(Error::NotFound)));
+        assert!(matches!(
+            x.merge("", serde_json::json!({ "a": 1 })),
+            Err(Error::NotFound)
+        ));
+        assert!(matches!(x.clear(), Err(Error::NotFound)));
+        {
+            assert!(matches!(x.edit(), Err(Error::NotFound)));
+        }
+        assert!(!x.store_dir_exists());
+        x.init_store().unwrap();
+        x.set("a", 1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn permissions() {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = |path: PathBuf| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+
+        let mut x = TempStore::new("store_permissions_test").unwrap();
+        assert_eq!(x.get_permissions(), Permissions::Private);
+        x.set("a", 1).unwrap();
+        assert_eq!(mode(x.get_store_dir_path()), 0o700);
+        assert_eq!(mode(x.get_store_path()), 0o600);
+        assert_eq!(mode(x.get_lock_path()), 0o600);
+
+        // Existing store files are given the new permissions when they are next written.
+        x.set_permissions(Permissions::Mode {
+            dir: 0o750,
+            file: 0o640,
+        });
+        x.set("a", 2).unwrap();
+        assert_eq!(mode(x.get_store_path()), 0o640);
+    }
+
+    #[test]
+    fn durability() {
+        let mut x = TempStore::new("store_durability_test").unwrap();
+        assert_eq!(x.get_durability(), Durability::Fast);
+        for durability in [Durability::Fsync, Durability::FsyncDir] {
+            x.set_durability(durability);
+            x.set("a", durability as u8).unwrap();
+            assert_eq!(x.get("a").unwrap().unwrap(), durability as u8);
+        }
+    }
+
+    #[cfg(feature = "test-util")]
+    #[test]
+    fn fault_injector() {
+        let faults = FaultInjector::new();
+        let mut x = TempStore::new("store_fault_injector_test").unwrap();
+        x.set_fault_injector(faults.clone());
+        x.set("a", 1).unwrap();
+        faults.fail_reads(true);
+        assert!(matches!(x.get("a"), Err(Error::Io { .. })));
+        faults.fail_reads(false);
+        faults.fail_writes(true);
+        assert!(matches!(x.set("a", 2), Err(Error::Io { .. })));
+        faults.fail_writes(false);
+        faults.corrupt_reads(Some(b"{\"a\":".to_vec()));
+        assert!(matches!(x.get("a"), Err(Error::Corrupt { .. })));
+        faults.reset();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+    }
+
+    #[cfg(all(feature = "test-util", feature = "crypto"))]
+    #[test]
+    fn fault_injector_decryption() {
+        let faults = FaultInjector::new();
+        let mut x = TempStore::new("store_fault_injector_decryption_test").unwrap();
+        x.set_encryption_key_bytes([3; 32]);
+        x.set_fault_injector(faults.clone());
+        x.set("a", 1).unwrap();
+        let mut store_data = std::fs::read(x.get_store_path()).unwrap();
+        let last = store_data.len() - 1;
+        store_data[last] ^= 0xff;
+        faults.corrupt_reads(Some(store_data));
+        assert!(matches!(x.get("a"), Err(Error::Decryption)));
+    }
+
+    #[test]
+    fn metrics() {
+        #[derive(Default)]
+        struct Counters {
+            gets: AtomicUsize,
+            cache_hits: AtomicUsize,
+            sets: AtomicUsize,
+            bytes_read: AtomicUsize,
+            bytes_written: AtomicUsize,
+        }
+
+        impl Metrics for Counters {
+            fn record_get(&self) {
+                self.gets.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn record_cache_hit(&self) {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn record_set(&self) {
+                self.sets.fetch_add(1, Ordering::Relaxed);
+            }
+
+            fn record_bytes_read(&self, bytes: usize) {
+                self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+            }
+
+            fn record_bytes_written(&self, bytes: usize) {
+                self.bytes_written.fetch_add(bytes, Ordering::Relaxed);
+            }
+        }
+
+        let counters = Arc::new(Counters::default());
+        let mut x = TempStore::new("store_metrics_test").unwrap();
+        x.set_metrics(counters.clone());
+        x.set("a", 1).unwrap();
+        x.set("a", 1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.set_deferred(true);
+        x.set("a", 2).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+        x.flush().unwrap();
+        assert_eq!(counters.gets.load(Ordering::Relaxed), 5);
+        assert_eq!(counters.cache_hits.load(Ordering::Relaxed), 1);
+        assert_eq!(counters.sets.load(Ordering::Relaxed), 2);
+        assert_eq!(counters.bytes_read.load(Ordering::Relaxed), 2 + 7 * 3);
+        assert_eq!(counters.bytes_written.load(Ordering::Relaxed), 2 + 7 + 7);
+    }
+
+    #[test]
+    fn io_error_context() {
+        let mut x = TempStore::new("store_io_error_context_test").unwrap();
+        let blocker = x.path().join("blocker");
+        fs::write(&blocker, "").unwrap();
+        x.set_path(blocker.clone());
+        match x.set("a", 1) {
+            Err(Error::Io { path, op, .. }) => {
+                assert_eq!(op, IoOp::CreateDir);
+                assert_eq!(path, blocker.join("store_io_error_context_test-rs"));
+            }
+            result => panic!("expected an I/O error, got {:?}", result),
+        }
+        let message = x.init_store().unwrap_err().to_string();
+        assert!(message.starts_with("Failed to create directory "));
+
+        let dir = x.path().to_path_buf();
+        x.set_path(dir);
+        x.set("a", 1).unwrap();
+        fs::write(x.get_store_path(), "{").unwrap();
+        match x.get("a") {
+            Err(Error::Corrupt { config, .. }) => assert_eq!(config, "config.json"),
+            result => panic!("expected a corrupt store, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn error_kind() {
+        use crate::ErrorKind;
+
+        let x = TempStore::new("store_error_kind_test").unwrap();
+        assert!(x.get("a").unwrap_err().is_not_found());
+        x.set("a", "text").unwrap();
+        let error = x.increment("a", 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::NotAnInteger);
+        assert!(!error.is_not_found());
+        fs::write(x.get_store_path(), "{").unwrap();
+        assert!(x.get("a").unwrap_err().is_corrupt());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            fs::set_permissions(x.get_store_path(), fs::Permissions::from_mode(0o000)).unwrap();
+            // Permissions are not enforced when running as root.
+            if fs::read(x.get_store_path()).is_err() {
+                assert!(x.get("a").unwrap_err().is_permission_denied());
+            }
+            fs::set_permissions(x.get_store_path(), fs::Permissions::from_mode(0o644)).unwrap();
+        }
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn decryption_failure_kind() {
+        let mut x = TempStore::new("store_decryption_failure_kind_test").unwrap();
+        x.set_encryption_key_bytes([1; 32]);
+        x.set("a", 1).unwrap();
+        x.set_encryption_key_bytes([2; 32]);
+        let error = x.get("a").unwrap_err();
+        assert!(error.is_wrong_key());
+        assert!(error.is_decryption_failure());
+
+        // A damaged tag is reported as a decryption failure rather than a wrong key.
+        x.set_encryption_key_bytes([1; 32]);
+        let mut store_data = fs::read(x.get_store_path()).unwrap();
+        let last = store_data.len() - 1;
+        store_data[last] ^= 0xff;
+        fs::write(x.get_store_path(), &store_data).unwrap();
+        let error = x.get("a").unwrap_err();
+        assert!(matches!(error, Error::Decryption));
+        assert!(!error.is_wrong_key());
+
+        // Stores in the original format without a header report wrong keys as decryption errors.
+        store_data[last] ^= 0xff;
+        store_data.drain(..16);
+        fs::write(x.get_store_path(), &store_data).unwrap();
+        x.set_encryption_key_bytes([2; 32]);
+        assert!(matches!(x.get("a"), Err(Error::Decryption)));
+    }
+
+    #[test]
+    fn profiles() {
+        let mut x = TempStore::new("store_profiles_test").unwrap();
+        x.set("a", "default").unwrap();
+        x.set_profile("dev");
+        assert_eq!(x.get_store_path().file_name().unwrap(), "config.dev.json");
+        x.set("a", "dev").unwrap();
+        x.copy_profile("dev", "staging").unwrap();
+        assert_eq!(x.list_profiles().unwrap(), vec!["dev", "staging"]);
+        x.set_profile("staging");
+        assert_eq!(x.get("a").unwrap().unwrap(), "dev");
+        x.clear_profile();
+        assert_eq!(x.get("a").unwrap().unwrap(), "default");
+        std::env::set_var("BLAND_PROFILES_TEST_PROFILE", "dev");
+        assert!(x.set_profile_from_env("BLAND_PROFILES_TEST_PROFILE"));
+        assert_eq!(x.get_profile(), Some("dev"));
+        assert!(!x.set_profile_from_env("BLAND_PROFILES_TEST_UNSET"));
+    }
+
+    #[test]
+    fn open_or_recover() {
+        let mut x = TempStore::new("store_open_or_recover_test").unwrap();
+        assert_eq!(x.open_or_recover().unwrap(), Recovery::Healthy);
+        x.set_backup(BackupPolicy { keep: 2 });
+        for i in 0..3 {
+            x.set("a", i).unwrap();
+        }
+        assert_eq!(x.open_or_recover().unwrap(), Recovery::Healthy);
+
+        // A truncated write.
+        std::fs::write(x.get_store_path(), "{\"a\": 3").unwrap();
+        assert!(matches!(x.get("a"), Err(Error::Corrupt { .. })));
+        // The newest backup is also corrupt, so the older one is restored.
+        std::fs::write(x.get_backup_path(1), "{\"a\":").unwrap();
+        assert_eq!(x.open_or_recover().unwrap(), Recovery::RestoredBackup(2));
+        assert_eq!(x.get("a").unwrap().unwrap(), 0);
+        assert_eq!(
+            std::fs::read_to_string(x.get_corrupt_path()).unwrap(),
+            "{\"a\": 3"
+        );
+
+        x.set_backup(BackupPolicy { keep: 0 });
+        std::fs::remove_file(x.get_backup_path(2)).unwrap();
+        std::fs::write(x.get_store_path(), "garbage").unwrap();
+        assert_eq!(x.open_or_recover().unwrap(), Recovery::Reinitialized);
+        assert_eq!(x.get("a").unwrap(), None);
+
+        // A read-only store keeps its corrupt store file.
+        std::fs::write(x.get_store_path(), "garbage").unwrap();
+        x.set_read_only(true);
+        assert!(matches!(x.open_or_recover(), Err(Error::ReadOnly)));
+        assert_eq!(std::fs::read(x.get_store_path()).unwrap(), b"garbage");
+        x.set_read_only(false);
+
+        // Encrypted and compressed stores opened without their settings are left alone.
+        for data in [
+            &b"BLND\x01\x00\x00\x00garbage"[..],
+            &[0x1f, 0x8b, 0x08, 0x00],
+        ] {
+            std::fs::write(x.get_store_path(), data).unwrap();
+            let error = x.open_or_recover().unwrap_err();
+            assert!(matches!(error, Error::FormatMismatch { .. }));
+            assert_eq!(error.kind(), ErrorKind::FormatMismatch);
+            assert_eq!(std::fs::read(x.get_store_path()).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn checksum() {
+        let mut x = TempStore::new("store_checksum_test").unwrap();
+        x.set_checksum(true);
+        assert!(x.get_checksum());
+        x.set("a", "test_data").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
+
+        let mut store_data = std::fs::read(x.get_store_path()).unwrap();
+        store_data[2] ^= 1;
+        std::fs::write(x.get_store_path(), &store_data).unwrap();
+        assert!(matches!(x.get("a"), Err(Error::ChecksumMismatch)));
+    }
+
+    #[test]
+    fn backup() {
+        let mut x = TempStore::new("store_backup_test").unwrap();
+        x.set_backup(BackupPolicy { keep: 2 });
+        for i in 0..4 {
+            x.set("a", i).unwrap();
+        }
+        let read_backup = |generation| {
+            let backup = std::fs::read_to_string(x.get_backup_path(generation)).unwrap();
+            serde_json::from_str::<serde_json::Value>(&backup).unwrap()["a"].clone()
+        };
+        assert_eq!(read_backup(1), 2);
+        assert_eq!(read_backup(2), 1);
+        assert!(!x.get_backup_path(3).exists());
+
+        let backups = x.list_backups().unwrap();
+        assert_eq!(backups.len(), 2);
+        assert_eq!(backups[0].generation, 1);
+        assert_eq!(backups[0].path, x.get_backup_path(1));
+        x.restore_from_backup(2).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        assert_eq!(read_backup(1), 3);
+        assert!(x.restore_from_backup(5).is_err());
+    }
+
+    #[test]
+    fn undo() {
+        let mut x = TempStore::new("store_undo_test").unwrap();
+        x.set("a", 1).unwrap();
+        assert!(!x.undo().unwrap());
+        x.set_undo_depth(2);
+        x.set("a", 2).unwrap();
+        x.set("b", 3).unwrap();
+        x.delete("a").unwrap();
+        assert!(x.undo().unwrap());
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+        assert!(x.undo().unwrap());
+        assert!(x.get("b").unwrap().is_none());
+        assert!(!x.undo().unwrap());
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+
+        x.set("a", 4).unwrap();
+        x.set_undo_depth(0);
+        assert!(!x.undo().unwrap());
+    }
+
+    #[test]
+    fn on_change() {
+        use std::sync::{Arc, Mutex};
+
+        let mut x = TempStore::new("store_on_change_test").unwrap();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let observed = changes.clone();
+        x.on_change("a.b", move |old, new| {
+            observed.lock().unwrap().push((old.cloned(), new.cloned()));
+        });
+        x.set("a.b", 1).unwrap();
+        x.set("a.c", 2).unwrap();
+        x.set("a.b", 1).unwrap();
+        x.set("a", serde_json::json!({ "b": 3 })).unwrap();
+        x.delete("a").unwrap();
+        x.set_undo_depth(1);
+        x.set("a.b", 4).unwrap();
+        x.undo().unwrap();
+        assert_eq!(
+            *changes.lock().unwrap(),
+            vec![
+                (None, Some(1.into())),
+                (Some(1.into()), Some(3.into())),
+                (Some(3.into()), None),
+                (None, Some(4.into())),
+                (Some(4.into()), None),
+            ]
+        );
+
+        x.clear_observers();
+        x.set("a.b", 5).unwrap();
+        assert_eq!(changes.lock().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn iter() {
+        let x = TempStore::new("store_iter_test").unwrap();
+        x.init_store().unwrap();
+        assert_eq!(x.iter().unwrap().count(), 0);
+        x.set(
+            "hosts",
+            serde_json::json!({ "empty": {}, "example.com": { "port": 80 } }),
+        )
+        .unwrap();
+        let leaves: Vec<_> = x.iter().unwrap().collect();
+        assert_eq!(
+            leaves[0],
+            ("hosts.empty".to_string(), serde_json::json!({}))
+        );
+        assert_eq!(
+            leaves[1],
+            ("hosts.example\\.com.port".to_string(), 80.into())
+        );
+        assert_eq!(x.get(&leaves[1].0).unwrap().unwrap(), 80);
+    }
+
+    #[test]
+    fn merge() {
+        let x = TempStore::new("store_merge_test").unwrap();
+        x.set(
+            "a",
+            serde_json::json!({ "b": "c", "d": { "e": 1, "f": 2 }, "g": [1] }),
+        )
+        .unwrap();
+        x.merge(
+            "a",
+            serde_json::json!({ "b": null, "d": { "e": null, "h": 3 }, "g": [2] }),
+        )
+        .unwrap();
+        assert_eq!(
+            x.get("a").unwrap().unwrap(),
+            serde_json::json!({ "d": { "f": 2, "h": 3 }, "g": [2] })
+        );
+        x.merge("", serde_json::json!({ "i": 4 })).unwrap();
+        assert_eq!(x.get("i").unwrap().unwrap(), 4);
+        for patch in [serde_json::Value::Null, serde_json::json!(5)] {
+            let error = x.merge("", patch).unwrap_err();
+            assert_eq!(error.kind(), ErrorKind::InvalidValue);
+        }
+        assert_eq!(x.get("i").unwrap().unwrap(), 4);
+        x.merge("a.d", serde_json::Value::Null).unwrap();
+        assert!(x.get("a.d").unwrap().is_none());
+        x.merge("a.b", serde_json::json!("s")).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), "s");
+    }
+
+    #[test]
+    fn json_patch() {
+        let x = TempStore::new("store_json_patch_test").unwrap();
+        x.set("a", serde_json::json!({ "b/c": [1, 2, 3], "d": "e" }))
+            .unwrap();
+        let target = serde_json::json!({ "a": { "b/c": [1, 4], "f~": null }, "g": true });
+        let ops = x.diff(&target).unwrap();
+        assert_eq!(
+            ops[0],
+            PatchOp::Replace {
+                path: "/a/b~1c/1".into(),
+                value: 4.into()
+            }
+        );
+        assert_eq!(
+            ops[1],
+            PatchOp::Remove {
+                path: "/a/b~1c/2".into()
+            }
+        );
+        x.apply_patch(&ops).unwrap();
+        assert_eq!(x.to_value().unwrap(), target);
+        assert!(x.diff(&target).unwrap().is_empty());
+
+        let ops: Vec<PatchOp> = serde_json::from_value(serde_json::json!([
+            { "op": "test", "path": "/g", "value": true },
+            { "op": "move", "from": "/a/b~1c", "path": "/h" },
+            { "op": "copy", "from": "/h/0", "path": "/h/-" },
+            { "op": "remove", "path": "/a" },
+        ]))
+        .unwrap();
+        x.apply_patch(&ops).unwrap();
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "g": true, "h": [1, 4, 1] })
+        );
+
+        let failing = [
+            PatchOp::Remove { path: "/g".into() },
+            PatchOp::Test {
+                path: "/h/0".into(),
+                value: 2.into(),
+            },
+        ];
+        assert!(matches!(x.apply_patch(&failing), Err(Error::TestFailed(_))));
+        assert_eq!(x.get("g").unwrap().unwrap(), true);
+        let failing = [PatchOp::Add {
+            path: "/h/4".into(),
+            value: 0.into(),
+        }];
+        assert!(matches!(
+            x.apply_patch(&failing),
+            Err(Error::InvalidPointer(_))
+        ));
+    }
+
+    #[test]
+    fn json_pointer() {
+        let x = TempStore::new("store_json_pointer_test").unwrap();
+        x.set_ptr("/a.b/c~1d", [1, 2]).unwrap();
+        assert_eq!(x.get("a\\.b.c/d.1").unwrap().unwrap(), 2);
+        x.set_ptr("/a.b/c~1d/-", 3).unwrap();
+        x.set_ptr("/a.b/c~1d/0", 0).unwrap();
+        assert_eq!(
+            x.get_ptr("/a.b/c~1d").unwrap().unwrap(),
+            serde_json::json!([0, 2, 3])
+        );
+        assert!(matches!(
+            x.set_ptr("/a.b/c~1d/5", 0),
+            Err(Error::InvalidPointer(_))
+        ));
+        assert!(matches!(x.get_ptr("a.b"), Err(Error::InvalidPointer(_))));
+        assert_eq!(x.delete_ptr("/a.b/c~1d/1").unwrap().unwrap(), 2);
+        assert!(x.delete_ptr("/missing").unwrap().is_none());
+        assert_eq!(
+            x.get_ptr("").unwrap().unwrap(),
+            serde_json::json!({ "a.b": { "c/d": [0, 3] } })
+        );
+    }
+
+    #[cfg(feature = "jsonpath")]
+    #[test]
+    fn query() {
+        let x = TempStore::new("store_query_test").unwrap();
+        x.set("accounts.work.port", 1).unwrap();
+        x.set("accounts.home.port", 2).unwrap();
+        assert_eq!(x.query("$.accounts.*.port").unwrap().len(), 2);
+        assert!(x.query("$.missing").unwrap().is_empty());
+        assert!(matches!(x.query("accounts"), Err(Error::JsonPath(_))));
+    }
+
+    #[test]
+    fn get_all() {
+        let x = TempStore::new("store_get_all_test").unwrap();
+        x.set(
+            "a",
+            serde_json::json!({ "*": [{ "b.c": 1 }, { "b.c": 2 }, {}], "d": 3 }),
+        )
+        .unwrap();
+        let matches = x.get_all("a.*.*.b\\.c").unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1], ("a.*.1.b\\.c".to_string(), 2.into()));
+        assert_eq!(x.get(&matches[1].0).unwrap().unwrap(), 2);
+        assert_eq!(x.get_all("a.\\*.0.b\\.c").unwrap().len(), 1);
+        assert_eq!(x.get_all("a.*").unwrap().len(), 2);
+        assert!(x.get_all("a.d.*").unwrap().is_empty());
+    }
+
+    #[test]
+    fn array_helpers() {
+        let x = TempStore::new("store_array_helpers_test").unwrap();
+        x.push("a.b", 1).unwrap();
+        x.push("a.b", 2).unwrap();
+        x.insert_at("a.b", 2, 3).unwrap();
+        x.insert_at("a.b", 0, 0).unwrap();
+        assert_eq!(
+            x.get("a.b").unwrap().unwrap(),
+            serde_json::json!([0, 1, 2, 3])
+        );
+        assert!(matches!(x.insert_at("a.b", 5, 5), Err(Error::DotPath(_))));
+        assert_eq!(x.remove_at("a.b", 1).unwrap().unwrap(), 1);
+        assert!(x.remove_at("a.b", 3).unwrap().is_none());
+        assert_eq!(x.pop("a.b").unwrap().unwrap(), 3);
+        assert!(x.pop("a.c").unwrap().is_none());
+        assert!(x.get("a.c").unwrap().is_none());
+        x.set("d", "e").unwrap();
+        assert!(matches!(x.push("d", 1), Err(Error::DotPath(_))));
+        assert!(matches!(x.pop("d"), Err(Error::DotPath(_))));
+        assert_eq!(x.get("a.b").unwrap().unwrap(), serde_json::json!([0, 2]));
+    }
+
+    #[test]
+    fn increment() {
+        let x = TempStore::new("store_increment_test").unwrap();
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let path = x.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let mut x = Store::new("store_increment_test").unwrap();
+                    x.set_path(path);
+                    for _ in 0..10 {
+                        x.increment("count", 1).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(x.get("count").unwrap().unwrap(), 80);
+        assert_eq!(x.decrement("count", 81).unwrap(), -1);
+        x.set("max", i64::MAX).unwrap();
+        assert!(matches!(x.increment("max", 1), Err(Error::NotAnInteger(_))));
+        x.set("float", 1.5).unwrap();
+        assert!(matches!(
+            x.increment("float", 1),
+            Err(Error::NotAnInteger(_))
+        ));
+    }
+
+    #[test]
+    fn escaped_keys() {
+        let x = TempStore::new("store_escaped_keys_test").unwrap();
+        let path = crate::DotPath::new().key("C:\\a.b").key("c");
+        x.set(path.as_str(), 1).unwrap();
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "C:\\a.b": { "c": 1 } })
+        );
+        assert_eq!(x.get(&path.to_string()).unwrap().unwrap(), 1);
+        let (leaf, _) = x.iter().unwrap().next().unwrap();
+        assert_eq!(leaf, path.as_str());
+        assert_eq!(x.delete(path.as_str()).unwrap().unwrap(), 1);
+        let parent = crate::escape_key("C:\\a.b");
+        assert_eq!(x.get(&parent).unwrap().unwrap(), serde_json::json!({}));
+    }
+
+    #[test]
+    fn edit() {
+        let mut x = TempStore::new("store_edit_test").unwrap();
+        x.set_undo_depth(1);
+        {
+            let mut edit = x.edit().unwrap();
+            edit.set("a", 1).unwrap();
+            edit.set("b", 2).unwrap();
+            assert_eq!(edit.get("a").unwrap().unwrap(), 1);
+            assert!(!x.store_exists());
+        }
+        assert_eq!(x.to_value().unwrap(), serde_json::json!({ "a": 1, "b": 2 }));
+
+        let mut edit = x.edit().unwrap();
+        assert_eq!(edit.delete("a").unwrap().unwrap(), 1);
+        edit.cancel();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+
+        let mut edit = x.edit().unwrap();
+        edit.set("a", 3).unwrap();
+        edit.commit().unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 3);
+        x.undo().unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+
+        // Writes made while the session is open are kept.
+        let mut edit = x.edit().unwrap();
+        edit.set("a", 4).unwrap();
+        x.set("c", 5).unwrap();
+        edit.commit().unwrap();
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": 4, "b": 2, "c": 5 })
+        );
+
+        x.set("e.f", 6).unwrap();
+        let mut edit = x.edit().unwrap();
+        edit.set("e.g", 7).unwrap();
+        x.delete("e").unwrap();
+        assert!(edit.commit().is_err());
+        assert_eq!(x.get("e").unwrap(), None);
+    }
+
+    #[test]
+    fn scoped() {
+        let x = TempStore::new("store_scoped_test").unwrap();
+        x.set("a", 1).unwrap();
+        let scoped = x.scoped("plugins.p");
+        assert_eq!(scoped.get_prefix(), "plugins.p");
+        scoped.set("b", 2).unwrap();
+        scoped.scoped("c").set("d", 3).unwrap();
+        assert_eq!(scoped.get("a").unwrap(), None);
+        assert_eq!(scoped.get("c.d").unwrap().unwrap(), 3);
+        assert_eq!(scoped.keys("").unwrap().unwrap(), ["b", "c"]);
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": 1, "plugins": { "p": { "b": 2, "c": { "d": 3 } } } })
+        );
+        assert_eq!(scoped.delete("b").unwrap().unwrap(), 2);
+        scoped.delete("").unwrap();
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": 1, "plugins": {} })
+        );
+    }
+
+    #[test]
+    fn namespace() {
+        let mut x = TempStore::new("store_namespace_test").unwrap();
+        x.register_namespace(
+            "limited",
+            NamespaceOptions {
+                max_size: Some(16),
+                schema: Some(Box::new(|value| match value.get("required") {
+                    Some(_) => Ok(()),
+                    None => Err("required is missing".to_string()),
+                })),
+            },
+        );
+        x.register_namespace("other.plugin", NamespaceOptions::default());
+        assert_eq!(x.namespaces(), ["limited", "other.plugin"]);
+
+        let limited = x.namespace("limited");
+        assert_eq!(limited.get_name(), "limited");
+        let error = limited.set("a", 1).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidValue);
+        limited.set("required", 1).unwrap();
+        assert!(limited
+            .set("a", "too long")
+            .unwrap_err()
+            .is_quota_exceeded());
+        assert_eq!(
+            limited.delete("required").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
+        assert_eq!(
+            limited.to_value().unwrap(),
+            serde_json::json!({ "required": 1 })
+        );
+
+        x.namespace("other.plugin").set("a", "unlimited").unwrap();
+        x.namespace("unregistered").set("a", 1).unwrap();
+        assert_eq!(
+            x.get(r"plugins.other\.plugin.a").unwrap().unwrap(),
+            "unlimited"
+        );
+
+        x.namespace("limited").reset().unwrap();
+        assert_eq!(
+            x.namespace("limited").to_value().unwrap(),
+            serde_json::json!({})
+        );
+        assert_eq!(
+            x.keys("plugins").unwrap().unwrap(),
+            ["other.plugin", "unregistered"]
+        );
+        x.unregister_namespace("limited");
+        assert_eq!(x.namespaces(), ["other.plugin"]);
+    }
+
+    #[test]
+    fn flags() {
+        let x = TempStore::new("store_flags_test").unwrap();
+        let mut flags = Flags::new(&x);
+        assert_eq!(flags.get_env_prefix(), "STORE_FLAGS_TEST_FLAG_");
+        flags.set_env_prefix("STORE_FLAGS_TEST_");
+        assert_eq!(flags.get("a"), None);
+        assert!(!flags.is_enabled("a"));
+
+        flags.set_default("a", true);
+        assert!(flags.is_enabled("a"));
+        flags.set("a", false).unwrap();
+        assert_eq!(x.get("flags.a").unwrap().unwrap(), false);
+        assert!(!flags.is_enabled("a"));
+        std::env::set_var("STORE_FLAGS_TEST_A", "on");
+        assert!(flags.is_enabled("a"));
+        std::env::set_var("STORE_FLAGS_TEST_A", "not a flag");
+        assert!(!flags.is_enabled("a"));
+        std::env::remove_var("STORE_FLAGS_TEST_A");
+        flags.remove("a").unwrap();
+        assert!(flags.is_enabled("a"));
+
+        // Machines have a stable id, generated if the system has none.
+        let machine_id = flags.get_machine_id().map(str::to_string);
+        assert!(machine_id.is_some());
+        assert_eq!(Flags::new(&x).get_machine_id(), machine_id.as_deref());
+
+        // Every machine in a rollout stays in larger rollouts.
+        flags.set_machine_id(None);
+        flags.set("b", Flag::Rollout(99)).unwrap();
+        assert!(!flags.is_enabled("b"));
+        let enabled = |flags: &mut Flags, percent| {
+            flags.set("b", Flag::Rollout(percent)).unwrap();
+            (0..100)
+                .filter(|i| {
+                    flags.set_machine_id(Some(&i.to_string()));
+                    flags.is_enabled("b")
+                })
+                .collect::<Vec<_>>()
+        };
+        let quarter = enabled(&mut flags, 25);
+        let half = enabled(&mut flags, 50);
+        assert!(!quarter.is_empty() && half.len() > quarter.len() && half.len() < 100);
+        assert!(quarter.iter().all(|i| half.contains(i)));
+        assert_eq!(enabled(&mut flags, 0).len(), 0);
+        assert_eq!(enabled(&mut flags, 100).len(), 100);
+        assert_eq!(flags.get("b"), Some(Flag::Rollout(100)));
+    }
+
+    #[test]
+    fn includes() {
+        let mut x = TempStore::new("store_includes_test").unwrap();
+        x.init_store().unwrap();
+        let dir = x.get_store_dir_path();
+        fs::create_dir(dir.join("shared")).unwrap();
+        fs::write(
+            dir.join("shared/base.json"),
+            r#"{ "$include": "../machine.json", "a": { "b": 1, "c": 1 }, "d": 1 }"#,
+        )
+        .unwrap();
+        fs::write(dir.join("machine.json"), r#"{ "a": { "b": 2 }, "e": 2 }"#).unwrap();
+        x.set("$include", ["shared/base.json"]).unwrap();
+        x.set("a.c", 3).unwrap();
+        assert_eq!(x.get("a.b").unwrap(), None);
+
+        x.set_includes(true);
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": { "b": 1, "c": 3 }, "d": 1, "e": 2 })
+        );
+        #[cfg(not(feature = "preserve_order"))]
+        assert_eq!(x.keys("").unwrap().unwrap(), ["a", "d", "e"]);
+        // Included keys come before the keys of the files including them.
+        #[cfg(feature = "preserve_order")]
+        assert_eq!(x.keys("").unwrap().unwrap(), ["a", "e", "d"]);
+        x.set("d", 4).unwrap();
+        assert_eq!(x.get("d").unwrap().unwrap(), 4);
+        // Writes which read the current value see the included values.
+        assert_eq!(x.increment("e", 1).unwrap(), 3);
+        assert!(x.compare_and_swap("a.b", 1, 5).unwrap());
+        assert_eq!(x.get_or_insert_with("a.c", || 6).unwrap(), 3);
+        x.set_includes(false);
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({
+                "$include": ["shared/base.json"],
+                "a": { "b": 5, "c": 3 },
+                "d": 4,
+                "e": 3
+            })
+        );
+
+        x.set_includes(true);
+        fs::write(
+            dir.join("machine.json"),
+            r#"{ "$include": "shared/base.json" }"#,
+        )
+        .unwrap();
+        assert_eq!(x.get("a").unwrap_err().kind(), ErrorKind::InvalidValue);
+        fs::remove_file(dir.join("machine.json")).unwrap();
+        assert!(x.get("a").unwrap_err().is_not_found());
+    }
+
+    #[test]
+    fn interpolation() {
+        let mut x = TempStore::new("store_interpolation_test").unwrap();
+        std::env::set_var("STORE_INTERPOLATION_TEST", "env");
+        x.set("a", "${STORE_INTERPOLATION_TEST}/${config:b.0}")
+            .unwrap();
+        x.set("b", serde_json::json!([1, "${config:c}", "$$a$b"]))
+            .unwrap();
+        x.set("c", "${config:d}").unwrap();
+        assert_eq!(
+            x.get("a").unwrap().unwrap(),
+            "${STORE_INTERPOLATION_TEST}/${config:b.0}"
+        );
+
+        x.set_interpolation(Interpolation::Lenient);
+        assert_eq!(x.get("a").unwrap().unwrap(), "env/1");
+        assert_eq!(
+            x.get("b").unwrap().unwrap(),
+            serde_json::json!([1, "", "$a$b"])
+        );
+        assert_eq!(x.get_all("b.*").unwrap()[1], ("b.1".to_string(), "".into()));
+        assert_eq!(x.iter().unwrap().nth(2).unwrap().1, "");
+
+        x.set_interpolation(Interpolation::Strict);
+        assert_eq!(x.get("a").unwrap().unwrap(), "env/1");
+        match x.get("b").unwrap_err() {
+            Error::InvalidValue { path, reason } => {
+                assert_eq!(path, "c");
+                assert_eq!(reason, "${config:d} is undefined");
+            }
+            e => panic!("unexpected error {}", e),
+        }
+        x.set("d", "${config:c}").unwrap();
+        assert_eq!(x.get("c").unwrap_err().kind(), ErrorKind::InvalidValue);
+        x.set("d", "${unterminated").unwrap();
+        assert_eq!(x.get("c").unwrap_err().kind(), ErrorKind::InvalidValue);
+        std::env::remove_var("STORE_INTERPOLATION_TEST");
+    }
+
+    #[test]
+    fn environment() {
+        let mut x = TempStore::new("store_environment_test").unwrap();
+        x.set(
+            "default",
+            serde_json::json!({ "a": { "b": 1, "c": 1 }, "d": 1 }),
+        )
+        .unwrap();
+        x.set("development.a.b", 2).unwrap();
+        x.set("production.d", serde_json::Value::Null).unwrap();
+        x.set("other", 1).unwrap();
+        assert_eq!(x.get("other").unwrap().unwrap(), 1);
+
+        std::env::set_var("STORE_ENVIRONMENT_TEST", "development");
+        assert!(x.set_environment_from_env("STORE_ENVIRONMENT_TEST"));
+        std::env::remove_var("STORE_ENVIRONMENT_TEST");
+        assert!(!x.set_environment_from_env("STORE_ENVIRONMENT_TEST"));
+        assert_eq!(x.get_environment(), Some("development"));
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": { "b": 2, "c": 1 }, "d": 1 })
+        );
+        assert_eq!(x.get("other").unwrap(), None);
+
+        x.set_environment(Some("production"));
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": { "b": 1, "c": 1 }, "d": null })
+        );
+        x.set_environment(Some("test"));
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 1);
+
+        // Writes go to the active environment's section, so they read back.
+        x.set("a.c", 5).unwrap();
+        assert_eq!(x.get("a.c").unwrap().unwrap(), 5);
+        assert_eq!(x.increment("d", 1).unwrap(), 2);
+        x.set_environment(None);
+        assert_eq!(
+            x.get("test").unwrap().unwrap(),
+            serde_json::json!({ "a": { "c": 5 }, "d": 2 })
+        );
+        assert_eq!(x.get("default.a.c").unwrap().unwrap(), 1);
+        assert_eq!(x.keys("").unwrap().unwrap().len(), 5);
+    }
+
+    #[test]
+    fn export_env() {
+        let x = TempStore::new("store_export_env_test").unwrap();
+        x.set(
+            "db",
+            serde_json::json!({ "host": "a\"$b\n", "ports": [1, 2], "read-only": true }),
+        )
+        .unwrap();
+        x.set("empty", serde_json::json!({})).unwrap();
+        x.set("none", serde_json::Value::Null).unwrap();
+        let vars = x.export_env("X_").unwrap();
+        let expected = [
+            ("X_DB_HOST", "a\"$b\n"),
+            ("X_DB_PORTS_0", "1"),
+            ("X_DB_PORTS_1", "2"),
+            ("X_DB_READ_ONLY", "true"),
+            ("X_EMPTY", "{}"),
+            ("X_NONE", ""),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        assert_eq!(vars, expected);
+
+        let dotenv = x.get_store_dir_path().join(".env");
+        x.export_dotenv(&dotenv, "").unwrap();
+        assert_eq!(
+            fs::read_to_string(&dotenv).unwrap(),
+            "DB_HOST=\"a\\\"\\$b\\n\"\nDB_PORTS_0=\"1\"\nDB_PORTS_1=\"2\"\n\
+             DB_READ_ONLY=\"true\"\nEMPTY=\"{}\"\nNONE=\"\"\n"
+        );
+    }
+
+    #[test]
+    fn import_dotenv() {
+        let x = TempStore::new("store_import_dotenv_test").unwrap();
+        x.set("db.port", 1).unwrap();
+        let dotenv = x.get_store_dir_path().join(".env");
+        let contents = [
+            "# comment",
+            "",
+            "export A=1 # comment",
+            "  B = \"x\\\"y\\n$z\" ",
+            "C='$literal\\n'",
+            "D=",
+            "E.F=dotted",
+        ];
+        fs::write(&dotenv, contents.join("\n")).unwrap();
+        assert_eq!(x.import_dotenv(&dotenv, "db").unwrap(), 5);
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({
+                "db": {
+                    "port": 1,
+                    "A": "1",
+                    "B": "x\"y\n$z",
+                    "C": "$literal\\n",
+                    "D": "",
+                    "E.F": "dotted"
+                }
+            })
+        );
+
+        // Exported files are imported unchanged.
+        x.export_dotenv(&dotenv, "").unwrap();
+        x.import_dotenv(&dotenv, "imported").unwrap();
+        assert_eq!(x.get("imported.DB_B").unwrap().unwrap(), "x\"y\n$z");
+
+        fs::write(&dotenv, "A=1\nB=\"unterminated").unwrap();
+        let error = x.import_dotenv(&dotenv, "").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("line 2: unterminated double quote"));
+        assert_eq!(x.get("A").unwrap(), None);
+    }
+
+    #[test]
+    fn deferred() {
+        let y = TempStore::new("store_deferred_test").unwrap();
+        let mut x = y.clone_settings();
+        x.set("a", 1).unwrap();
+        x.set_deferred(true);
+        x.set("a", 2).unwrap();
+        x.set("b", 3).unwrap();
+        assert!(x.is_dirty());
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+        let on_disk = std::fs::read_to_string(x.get_store_path()).unwrap();
+        assert_eq!(on_disk, r#"{"a":1}"#);
+
+        x.flush().unwrap();
+        assert!(!x.is_dirty());
+        let on_disk = std::fs::read_to_string(x.get_store_path()).unwrap();
+        assert_eq!(on_disk, r#"{"a":2,"b":3}"#);
+
+        x.delete("b").unwrap();
+        let store_path = x.get_store_path();
+        drop(x);
+        let on_disk = std::fs::read_to_string(&store_path).unwrap();
+        assert_eq!(on_disk, r#"{"a":2}"#);
+    }
+
+    #[test]
+    fn get_with_metadata() {
+        let mut x = TempStore::new("store_get_with_metadata_test").unwrap();
+        assert!(matches!(x.get_with_metadata("a"), Err(Error::NotFound)));
+        x.set_defaults(serde_json::json!({ "a": 1 }));
+        x.set("b", 2).unwrap();
+        let (a, metadata) = x.get_with_metadata("a").unwrap();
+        assert_eq!(a.unwrap(), 1);
+        assert!(metadata.from_defaults);
+        assert!(metadata.modified.is_some());
+        let (b, same) = x.get_with_metadata("b").unwrap();
+        assert_eq!(b.unwrap(), 2);
+        assert!(!same.from_defaults);
+        assert_eq!(metadata.generation, same.generation);
+
+        // Journaled changes change the generation too.
+        x.set_journal(true);
+        x.set("a", 3).unwrap();
+        let (a, changed) = x.get_with_metadata("a").unwrap();
+        assert_eq!(a.unwrap(), 3);
+        assert!(!changed.from_defaults);
+        assert_ne!(metadata.generation, changed.generation);
+        assert_eq!(x.get_with_metadata("c").unwrap().0, None);
+    }
+
+    #[test]
+    fn partial_get() {
+        let x = TempStore::new("store_partial_get_test").unwrap();
+        let contents = serde_json::json!({
+            "a": { "b": [{ "c": 1 }, null, 3], "d": null, "e.f": { "g": "h" } },
+            "i": 2,
+        });
+        x.set("a", &contents["a"]).unwrap();
+        x.set("i", &contents["i"]).unwrap();
+        assert!(x.reads_partially());
+        let paths = [
+            "a",
+            "a.b",
+            "a.b.0.c",
+            "a.b.1",
+            "a.b.>",
+            "a.b.3",
+            "a.b.x",
+            "a.d",
+            "a.d.e",
+            r"a.e\.f.g",
+            "a.e",
+            "i",
+            "i.j",
+            "j",
+            "j.k",
+            "",
+            "a.",
+            ".a",
+        ];
+        for path in paths {
+            let expected = json_dotpath::DotPaths::dot_get::<serde_json::Value>(&contents, path);
+            match x.get(path) {
+                Ok(value) => assert_eq!(Some(value), expected.ok(), "{}", path),
+                Err(_) => assert!(expected.is_err(), "{}", path),
+            }
+        }
+
+        // Values outside the path are still checked to be valid.
+        fs::write(x.get_store_path(), r#"{"a": 1, "b": [}"#).unwrap();
+        assert!(matches!(x.get("a"), Err(Error::Corrupt { .. })));
+    }
+
+    #[test]
+    fn stats() {
+        let mut x = TempStore::new("store_stats_test").unwrap();
+        assert!(matches!(x.stats(), Err(Error::NotFound)));
+        x.set_pretty(true);
+        x.set("a", serde_json::json!({ "b": [{ "c": 1 }, 2], "d": {} }))
+            .unwrap();
+        let stats = x.stats().unwrap();
+        assert_eq!(stats.keys, 4);
+        assert_eq!(stats.depth, 4);
+        assert_eq!(
+            stats.size_on_disk,
+            fs::metadata(x.get_store_path()).unwrap().len()
+        );
+        assert_eq!(stats.size_on_disk, stats.uncompressed_size);
+        assert!(stats.modified.is_some());
+        assert!(!stats.encrypted);
+        assert!(!stats.compressed);
+
+        x.set_journal(true);
+        x.set("e", 1).unwrap();
+        assert!(x.stats().unwrap().size_on_disk > stats.size_on_disk);
+    }
+
+    #[test]
+    fn set_if_generation() {
+        let x = TempStore::new("store_set_if_generation_test").unwrap();
+        assert!(matches!(
+            x.set_if_generation("a", 1, 0),
+            Err(Error::NotFound)
+        ));
+        x.set("a", 1).unwrap();
+        let generation = x.generation().unwrap();
+        assert_eq!(x.get_with_metadata("a").unwrap().1.generation, generation);
+
+        // Another writer changes the store in between.
+        let mut y = Store::new("store_set_if_generation_test").unwrap();
+        y.set_path(x.path().to_path_buf());
+        y.set("a", 2).unwrap();
+        let error = x.set_if_generation("a", 3, generation).unwrap_err();
+        assert!(matches!(error, Error::Conflict));
+        assert!(error.is_conflict());
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+
+        x.set_if_generation("a", 3, x.generation().unwrap())
+            .unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 3);
+
+        // Writing back earlier contents still advances the generation.
+        let generation = x.generation().unwrap();
+        y.set("a", 4).unwrap();
+        y.set("a", 3).unwrap();
+        assert!(x.generation().unwrap() > generation);
+        let error = x.set_if_generation("a", 5, generation).unwrap_err();
+        assert!(error.is_conflict());
+    }
+
+    #[test]
+    fn deferred_generation() {
+        let mut x = TempStore::new("store_deferred_generation_test").unwrap();
+        x.set("a", 1).unwrap();
+        let generation = x.generation().unwrap();
+        x.set_deferred(true);
+        x.set("a", 2).unwrap();
+        x.set("a", 3).unwrap();
+        assert_eq!(x.generation().unwrap(), generation + 2);
+        x.flush().unwrap();
+        assert_eq!(x.generation().unwrap(), generation + 2);
+        x.set("a", 4).unwrap();
+        assert_eq!(x.generation().unwrap(), generation + 3);
+    }
+
+    #[test]
+    fn compare_and_swap() {
+        let x = TempStore::new("store_compare_and_swap_test").unwrap();
+        assert!(!x.compare_and_swap("a", 0, 1).unwrap());
+        x.set("a", serde_json::json!({ "b": [1, 2] })).unwrap();
+        assert!(!x
+            .compare_and_swap("a", serde_json::json!({ "b": [1] }), 1)
+            .unwrap());
+        assert!(x
+            .compare_and_swap("a", serde_json::json!({ "b": [1, 2] }), 0)
+            .unwrap());
+        assert!(!x.compare_and_swap("c", serde_json::Value::Null, 1).unwrap());
+
+        // Increments from several instances are never lost.
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let path = x.path().to_path_buf();
+                std::thread::spawn(move || {
+                    let mut y = Store::new("store_compare_and_swap_test").unwrap();
+                    y.set_path(path);
+                    for _ in 0..10 {
+                        loop {
+                            let current = y.get("a").unwrap().unwrap().as_i64().unwrap();
+                            if y.compare_and_swap("a", current, current + 1).unwrap() {
+                                break;
+                            }
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(x.get("a").unwrap().unwrap(), 40);
+    }
+
+    #[test]
+    fn cache() {
+        let mut x = TempStore::new("store_cache_test").unwrap();
+        let mut y = Store::new("store_cache_test").unwrap();
+        y.set_path(x.path().to_path_buf());
+        x.set("a", 1).unwrap();
+
+        x.set_cache(Some(FreshnessPolicy::Always));
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        y.set("a", 22).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 22);
+
+        x.set_cache(Some(FreshnessPolicy::Never));
+        assert_eq!(x.get("a").unwrap().unwrap(), 22);
+        y.set("a", 333).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 22);
+        // Writes start from the current contents rather than the cached ones.
+        x.set("b", 1).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 333);
+
+        x.set_cache(Some(FreshnessPolicy::Interval(Duration::from_secs(3600))));
+        assert_eq!(x.get("a").unwrap().unwrap(), 333);
+        y.set("a", 4444).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 333);
+        // Changing the policy discards the cached contents.
+        x.set_cache(Some(FreshnessPolicy::Interval(Duration::ZERO)));
+        assert_eq!(x.get("a").unwrap().unwrap(), 4444);
+        y.set("a", 55555).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 55555);
+
+        x.set_cache(Some(FreshnessPolicy::Never));
+        assert_eq!(x.get("a").unwrap().unwrap(), 55555);
+        y.set("a", 6).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 55555);
+        assert_eq!(x.reload().unwrap()["a"], 6);
+        assert_eq!(x.get("a").unwrap().unwrap(), 6);
+    }
+
+    #[test]
+    fn subscribe() {
+        let mut x = TempStore::new("store_subscribe_test").unwrap();
+        let events = x.subscribe();
+        x.set("a.b", 1).unwrap();
+        x.set("a.c", 2).unwrap();
+        x.delete("a.b").unwrap();
+        let internal: Vec<_> = events.try_iter().collect();
+        assert_eq!(internal.len(), 3);
+        assert!(internal
+            .iter()
+            .all(|event| event.source == ChangeSource::Internal));
+        assert_eq!(internal[0].path, "a");
+        assert_eq!(internal[1].path, "a.c");
+        assert_eq!(internal[2].path, "a.b");
+        assert_eq!(internal[2].old, Some(serde_json::json!(1)));
+        assert_eq!(internal[2].new, None);
+
+        // Changes by another store are reported when the store is next read.
+        let mut y = Store::new("store_subscribe_test").unwrap();
+        y.set_path(x.path().to_path_buf());
+        y.set("a.c", 3).unwrap();
+        assert!(events.try_recv().is_err());
+        x.reload().unwrap();
+        let event = events.try_recv().unwrap();
+        assert_eq!(event.path, "a.c");
+        assert_eq!(event.new, Some(serde_json::json!(3)));
+        assert_eq!(event.source, ChangeSource::External);
+
+        // Deferred changes are reported when they are made rather than when flushed.
+        x.set_deferred(true);
+        x.set("d", 4).unwrap();
+        assert_eq!(events.try_recv().unwrap().path, "d");
+        x.flush().unwrap();
+        assert!(events.try_recv().is_err());
+
+        drop(events);
+        x.set("e", 5).unwrap();
+        assert!(!x.has_subscribers());
+    }
+
+    #[test]
+    fn journal() {
+        let mut x = TempStore::new("store_journal_test").unwrap();
+        x.set_journal(true);
+        // The first change is larger than the empty store, so it is compacted right away.
+        x.set("padding", "x".repeat(200)).unwrap();
+        assert!(!x.get_journal_path().exists());
+        x.set("list", serde_json::json!(["a"])).unwrap();
+        x.push("list", "b").unwrap();
+        x.push("list", "c").unwrap();
+        let on_disk = fs::read_to_string(x.get_store_path()).unwrap();
+        assert!(!on_disk.contains("list"));
+        assert!(x.get_journal_path().exists());
+
+        // Stores without journaling still read the journaled changes.
+        let mut y = Store::new("store_journal_test").unwrap();
+        y.set_path(x.path().to_path_buf());
+        assert_eq!(
+            y.get("list").unwrap().unwrap(),
+            serde_json::json!(["a", "b", "c"])
+        );
+
+        // A partially written last line is ignored and later entries still apply.
+        let journal = fs::read(x.get_journal_path()).unwrap();
+        let mut torn = journal.clone();
+        torn.extend_from_slice(br#"[{"op":"add","path":"/list/-","#);
+        fs::write(x.get_journal_path(), &torn).unwrap();
+        assert_eq!(x.get("list").unwrap().unwrap().as_array().unwrap().len(), 3);
+        x.set("b", 1).unwrap();
+        assert_eq!(x.get("b").unwrap().unwrap(), 1);
+
+        // Entries after a line which cannot be parsed are not applied.
+        let current = fs::read(x.get_journal_path()).unwrap();
+        let mut corrupt = current.clone();
+        corrupt.extend_from_slice(b"garbage\n[{\"op\":\"add\",\"path\":\"/z\",\"value\":1}]\n");
+        fs::write(x.get_journal_path(), &corrupt).unwrap();
+        assert_eq!(x.get("z").unwrap(), None);
+        fs::write(x.get_journal_path(), &current).unwrap();
+
+        // A journal left behind by an interrupted compaction is not applied twice.
+        x.compact().unwrap();
+        assert!(!x.get_journal_path().exists());
+        fs::write(x.get_journal_path(), &journal).unwrap();
+        assert_eq!(x.get("list").unwrap().unwrap().as_array().unwrap().len(), 3);
+        x.set("c", 1).unwrap();
+        assert_eq!(x.get("c").unwrap().unwrap(), 1);
+        assert_eq!(x.get("b").unwrap().unwrap(), 1);
+
+        // The journal is compacted once it is larger than the store file.
+        for i in 0..20 {
+            x.set("counter", i).unwrap();
+        }
+        let on_disk: serde_json::Value =
+            serde_json::from_slice(&fs::read(x.get_store_path()).unwrap()).unwrap();
+        assert!(on_disk["counter"].as_i64().unwrap() > 0);
+        assert_eq!(x.get("counter").unwrap().unwrap(), 19);
+    }
+
+    #[test]
+    fn restore_journaled_backup() {
+        let mut x = TempStore::new("store_restore_journaled_backup_test").unwrap();
+        x.set_journal(true);
+        x.set("padding", "x".repeat(200)).unwrap();
+        x.set("a", 1).unwrap();
+        x.set("b", 2).unwrap();
+        assert!(x.get_journal_path().exists());
+
+        // Stores which keep backups are rewritten in full, backing up the journaled changes.
+        x.set_backup(BackupPolicy { keep: 2 });
+        x.set("a", 3).unwrap();
+        assert!(!x.get_journal_path().exists());
+        x.set("b", 4).unwrap();
+        assert!(!x.get_journal_path().exists());
+        x.restore_from_backup(2).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        assert_eq!(x.get("b").unwrap().unwrap(), 2);
+        x.restore_from_backup(2).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 3);
+        assert_eq!(x.get("b").unwrap().unwrap(), 2);
+    }
+
+    #[cfg(feature = "sharding")]
+    #[test]
+    fn restore_sharded_backup() {
+        let mut x = TempStore::new("store_restore_sharded_backup_test").unwrap();
+        x.set_sharded(true);
+        x.set_backup(BackupPolicy { keep: 3 });
+        x.set("a.b", 1).unwrap();
+        x.set("a.b", 2).unwrap();
+        x.set("a.b", 3).unwrap();
+        x.set("a.b", 4).unwrap();
+
+        // Backups hold the whole store, as the shards they were taken with are removed.
+        x.restore_from_backup(2).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 2);
+        assert_eq!(fs::read_dir(x.get_shards_path()).unwrap().count(), 1);
+        x.set("c", 1).unwrap();
+        assert_eq!(fs::read_dir(x.get_shards_path()).unwrap().count(), 1);
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 2);
+        x.restore_from_backup(2).unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 4);
+    }
+
+    #[cfg(all(feature = "signals", unix))]
+    #[test]
+    fn reload_on_sighup() {
+        let y = TempStore::new("store_reload_on_sighup_test").unwrap();
+        y.set("a", 1).unwrap();
+        let x = SharedStore::new(y.clone_settings());
+        let events = x.read(|store| store.subscribe());
+        x.reload_on_sighup().unwrap();
+
+        y.set("a", 2).unwrap();
+        signal_hook::low_level::raise(signal_hook::consts::SIGHUP).unwrap();
+        let event = events.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(event.path, "a");
+        assert_eq!(event.new, Some(serde_json::json!(2)));
+        assert_eq!(event.source, ChangeSource::External);
+
+        x.stop_reload_on_sighup();
+    }
+
+    #[test]
+    fn shared_store() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedStore>();
+
+        let y = TempStore::new("store_shared_test").unwrap();
+        y.init_store().unwrap();
+        let x = SharedStore::new(y.clone_settings());
+        let handles: Vec<_> = (0..8)
+            .map(|_| {
+                let x = x.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..10 {
+                        x.write(|store| {
+                            let count = store.get("count")?.and_then(|v| v.as_u64());
+                            store.set("count", count.unwrap_or(0) + 1)
+                        })
+                        .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert_eq!(x.get("count").unwrap().unwrap(), 80);
+
+        x.set_autosave(Duration::from_millis(20));
+        x.set("count", 0).unwrap();
+        assert!(x.read(|store| store.is_dirty()));
+        std::thread::sleep(Duration::from_millis(100));
+        assert!(!x.read(|store| store.is_dirty()));
+        x.set("count", 1).unwrap();
+        x.stop_autosave().unwrap();
+        assert!(!x.read(|store| store.get_deferred() || store.is_dirty()));
+        assert_eq!(x.get("count").unwrap().unwrap(), 1);
+
+        x.write(|store| {
+            store.set_checksum(true);
+            Ok(())
+        })
+        .unwrap();
+        assert!(x.read(|store| store.get_checksum()));
+    }
+
+    #[cfg(feature = "history")]
+    #[test]
+    fn history() {
+        let mut x = TempStore::new("store_history_test").unwrap();
+        x.set("a", 1).unwrap();
+        assert!(x.snapshots().unwrap().is_empty());
+        x.set_history(4);
+        x.set("a", 2).unwrap();
+        x.set("b.c", true).unwrap();
+        x.delete("a").unwrap();
+        let snapshots = x.snapshots().unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert!(snapshots[0].id < snapshots[1].id);
+
+        let changes = x.diff_snapshot(snapshots[0].id).unwrap();
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "a");
+        assert_eq!(changes[0].old, Some(2.into()));
+        assert_eq!(changes[0].new, None);
+        assert_eq!(changes[1].path, "b");
+        assert_eq!(changes[1].old, None);
+
+        x.revert_to(snapshots[1].id).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 2);
+        assert_eq!(x.get("b.c").unwrap().unwrap(), true);
+        assert_eq!(x.snapshots().unwrap().len(), 4);
+        assert!(matches!(x.revert_to(0), Err(Error::NotFound)));
+
+        // The oldest snapshots are removed beyond the number kept.
+        x.set("a", 3).unwrap();
+        let pruned = x.snapshots().unwrap();
+        assert_eq!(pruned.len(), 4);
+        assert_eq!(pruned[0], snapshots[1]);
+        x.set_history(2);
+        x.set("a", 4).unwrap();
+        assert_eq!(x.snapshots().unwrap().len(), 2);
+        x.set_history(0);
+        x.set("a", 5).unwrap();
+        assert_eq!(x.snapshots().unwrap().len(), 2);
+
+        // Journaled changes are discarded by reverting.
+        x.set_journal(true);
+        x.set("a", "b".repeat(1000)).unwrap();
+        x.set("c", 1).unwrap();
+        assert!(x.get_journal_path().exists());
+        x.revert_to(pruned[3].id).unwrap();
+        assert!(!x.get_journal_path().exists());
+        assert_eq!(x.get("a").unwrap().unwrap(), 3);
+        assert_eq!(x.get("c").unwrap(), None);
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn set_encryption_key() {
+        let mut x = Store::new("set_encryption_key_test").unwrap();
+        x.set_encryption_key("test_key").unwrap();
+        assert_eq!(
+            x.get_encryption_key(),
+            Some([
+                116, 101, 115, 116, 95, 107, 101, 121, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                0, 0, 0, 0, 0, 0, 0, 0, 0
+            ])
+        )
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn invalid_encryption_key() {
+        let mut x = Store::new("invalid_encryption_key_test").unwrap();
+        match x.set_encryption_key("test_key_this_key_will_be_too_long") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(e.to_string(), Error::InvalidKeyLength.to_string()),
+        };
+    }
+
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn crypto() {
+        let mut x = TempStore::new("crypto_test").unwrap();
+        x.set_encryption_key("test_key").unwrap();
+        let data = "test_data";
+        x.set("a", data).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), data);
     }
 
-    fn parse_json(store: String) -> Result<Value> {
-        serde_json::from_str(&store).map_err(Error::from)
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encryption_key_bytes() {
+        let mut x = TempStore::new("encryption_key_bytes_test").unwrap();
+        x.set_encryption_key_bytes([7; 32]);
+        assert_eq!(x.get_encryption_key(), Some([7; 32]));
+        x.set("a", "test_data").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
+        x.set_encryption_key_bytes([8; 32]);
+        match x.get("a") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(e.to_string(), Error::WrongKey.to_string()),
+        };
     }
 
-    pub fn get_path(&self) -> PathBuf {
-        self.path.clone()
-    }
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn password() {
+        let mut x = TempStore::new("password_test").unwrap();
+        x.set_password("hunter2");
+        assert_eq!(x.get_encryption_key(), None);
+        x.set("a", "test_data").unwrap();
+        let read_kdf = |x: &Store| {
+            let store_data = std::fs::read(x.get_store_path()).unwrap();
+            crate::crypto::read_header(&store_data).unwrap().kdf
+        };
+        let kdf = read_kdf(&x);
+        assert!(kdf.is_some());
+        x.set("b", "more_data").unwrap();
+        assert_eq!(kdf, read_kdf(&x));
 
-    pub fn set_path(&mut self, new_path: PathBuf) {
-        self.path = new_path
+        let mut y = Store::new("password_test").unwrap();
+        y.set_path(x.path().to_path_buf());
+        y.set_password("hunter2");
+        assert_eq!(y.get("a").unwrap().unwrap(), "test_data");
+        y.set_password("hunter3");
+        match y.get("a") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(e.to_string(), Error::WrongKey.to_string()),
+        };
     }
 
-    pub fn set_project_name(&mut self, name: &'a str) {
-        self.project_name = name;
-    }
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn rotate_key() {
+        let mut x = TempStore::new("rotate_key_test").unwrap();
+        x.set_encryption_key_bytes([1; 32]);
+        x.set("a", "test_data").unwrap();
+        x.rotate_key(EncryptionKey::Raw(Zeroizing::new([2; 32])))
+            .unwrap();
+        assert_eq!(x.get_encryption_key(), Some([2; 32]));
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
 
-    pub fn get_project_name(&self) -> &str {
-        self.project_name
+        // Simulate a rotation where the new key was saved but the store wasn't rewritten.
+        x.set_encryption_key_bytes([3; 32]);
+        assert!(x.get("a").is_err());
+        x.reencrypt_from(EncryptionKey::Raw(Zeroizing::new([2; 32])))
+            .unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
     }
 
-    pub fn set_config_name(&mut self, config_name: &'a str) {
-        self.config_name = config_name;
-    }
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encrypt_existing() {
+        let mut x = TempStore::new("encrypt_existing_test").unwrap();
+        x.set_backup(BackupPolicy { keep: 2 });
+        x.set("token", "plain_text_secret").unwrap();
+        x.set("theme", "dark").unwrap();
+        assert!(!x.list_backups().unwrap().is_empty());
+        x.encrypt_existing(EncryptionKey::Raw(Zeroizing::new([1; 32])))
+            .unwrap();
+        assert!(x.list_backups().unwrap().is_empty());
+        let contents = fs::read(x.get_store_path()).unwrap();
+        assert!(!contents
+            .windows(b"plain_text_secret".len())
+            .any(|w| w == b"plain_text_secret"));
+        assert_eq!(x.get("token").unwrap().unwrap(), "plain_text_secret");
 
-    pub fn get_config_name(&self) -> &str {
-        self.config_name
+        // A store which is already encrypted is left as it is.
+        assert!(x
+            .encrypt_existing(EncryptionKey::Raw(Zeroizing::new([2; 32])))
+            .is_err());
+        assert_eq!(x.get_encryption_key(), Some([1; 32]));
+        assert_eq!(x.get("token").unwrap().unwrap(), "plain_text_secret");
     }
 
-    pub fn set_project_suffix(&mut self, suffix: Option<&'a str>) {
-        self.project_suffix = suffix;
-    }
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn redacted_debug() {
+        let mut x = TempStore::new("redacted_debug_test").unwrap();
+        x.set_password("hunter2");
+        x.set_signing_key(b"signing secret");
+        let debug = format!("{:?}", *x);
+        assert!(debug.contains("<redacted>"));
+        assert!(!debug.contains("hunter2"));
+        assert!(!debug.contains("signing secret"));
 
-    pub fn get_project_suffix(&self) -> Option<&str> {
-        self.project_suffix
+        let description = x.describe();
+        assert!(description.contains(&x.get_store_path().display().to_string()));
+        assert!(description.contains("encryption: password"));
+        assert!(description.contains("signed: true"));
+        assert!(!description.contains("hunter2"));
     }
 
-    pub fn set_file_extension(&mut self, extension: &'a str) {
-        self.file_extension = extension;
-    }
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn encrypted_header() {
+        let mut x = TempStore::new("encrypted_header_test").unwrap();
+        x.set("a", "test_data").unwrap();
+        x.set_encryption_key_bytes([1; 32]);
+        match x.get("a") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(e.to_string(), Error::NotEncrypted.to_string()),
+        };
+        std::fs::write(x.get_store_path(), b"BLND\x09garbage").unwrap();
+        match x.get("a") {
+            Ok(_) => panic!(),
+            Err(e) => assert_eq!(e.to_string(), Error::InvalidHeader.to_string()),
+        };
+        x.clear().unwrap();
+        x.set("a", "test_data").unwrap();
+        let store_data = std::fs::read(x.get_store_path()).unwrap();
+        assert!(store_data.starts_with(b"BLND\x01"));
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
 
-    pub fn set_pretty(&mut self, pretty: bool) {
-        self.pretty = pretty;
+        // The header is authenticated, so changing it fails decryption.
+        let mut tampered = store_data.clone();
+        tampered[5] ^= 0x80;
+        std::fs::write(x.get_store_path(), &tampered).unwrap();
+        assert!(matches!(x.get("a"), Err(Error::Decryption)));
+
+        // Key derivation parameters above the limits are rejected before deriving a key.
+        let mut header = b"BLND\x01\x00\x00\x01".to_vec();
+        header.extend_from_slice(&0x7fff_ffffu32.to_le_bytes());
+        header.extend_from_slice(&2u32.to_le_bytes());
+        header.extend_from_slice(&1u32.to_le_bytes());
+        header.extend_from_slice(&[0; 16 + 8 + 12 + 16]);
+        std::fs::write(x.get_store_path(), &header).unwrap();
+        x.set_password("hunter2");
+        assert!(matches!(x.get("a"), Err(Error::InvalidHeader)));
     }
 
-    pub fn get_file_extension(&self) -> &str {
-        self.file_extension
+    #[cfg(feature = "crypto")]
+    #[test]
+    fn mark_encrypted() {
+        let mut x = TempStore::new("mark_encrypted_test").unwrap();
+        x.set_encryption_key_bytes([1; 32]);
+        x.mark_encrypted("credentials");
+        x.mark_encrypted("credentials");
+        assert_eq!(x.get_encrypted_paths(), ["credentials"]);
+        x.set("credentials", json!({ "user": "me", "token": "secret" }))
+            .unwrap();
+        x.set("theme", "dark").unwrap();
+        assert_eq!(x.get("credentials.token").unwrap().unwrap(), "secret");
+        assert_eq!(x.get("theme").unwrap().unwrap(), "dark");
+
+        let contents: Value =
+            serde_json::from_slice(&std::fs::read(x.get_store_path()).unwrap()).unwrap();
+        assert_eq!(contents["theme"], "dark");
+        assert!(contents["credentials"].is_string());
+
+        // Plain text values stored before their path was marked are encrypted on the next write.
+        x.set("token", "plain").unwrap();
+        x.mark_encrypted("token");
+        assert_eq!(x.get("token").unwrap().unwrap(), "plain");
+        x.set("theme", "light").unwrap();
+        assert_eq!(x.get("token").unwrap().unwrap(), "plain");
+        let contents = std::fs::read_to_string(x.get_store_path()).unwrap();
+        assert!(!contents.contains("plain"));
     }
 
-    /// Sets the encryption key. The key must be less than or equal to 32 bytes.
     #[cfg(feature = "crypto")]
-    pub fn set_encryption_key(&mut self, key: &str) -> Result<()> {
-        let mut final_bytes = [0; 32];
-        let key_bytes = key.as_bytes().to_vec();
-        if key_bytes.len() > 32 {
-            return Err(Error::InvalidKeyLength);
-        }
-        // probably a better way of doing this...
-        for (i, byte) in key_bytes.iter().enumerate() {
-            final_bytes[i] = *byte;
-        }
+    #[test]
+    fn signing_key() {
+        let mut x = TempStore::new("signing_key_test").unwrap();
+        x.set_checksum(true);
+        x.set_signing_key(b"key");
+        x.set("a", "test_data").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
 
-        self.encryption_key = Some(final_bytes);
-        Ok(())
+        x.set_signing_key(b"other key");
+        assert!(matches!(x.get("a"), Err(Error::SignatureInvalid)));
+        x.resign().unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
+        x.clear_signing_key();
+        assert!(x.get("a").is_err());
     }
 
     #[cfg(feature = "crypto")]
-    pub fn get_encryption_key(&self) -> Option<[u8; 32]> {
-        self.encryption_key
+    #[test]
+    fn cipher_suite() {
+        let mut x = TempStore::new("cipher_suite_test").unwrap();
+        x.set_encryption_key_bytes([1; 32]);
+        x.set_cipher_suite(CipherSuite::XChaCha20Poly1305);
+        assert_eq!(x.get_cipher_suite(), CipherSuite::XChaCha20Poly1305);
+        x.set("a", "test_data").unwrap();
+        // The cipher is read from the header, not from the store's settings.
+        x.set_cipher_suite(CipherSuite::Aes256Gcm);
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
     }
 
     #[cfg(feature = "compression")]
-    pub fn set_compressed(&mut self, compressed: bool) {
-        self.compressed = compressed;
+    #[test]
+    fn compression() {
+        let mut x = TempStore::new("compression_test").unwrap();
+        x.set_compressed(true);
+        let data = "test_data";
+        x.set("a", data).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), data);
     }
 
     #[cfg(feature = "compression")]
-    pub fn get_compressed(&self) -> bool {
-        self.compressed
+    #[test]
+    fn compressed_large_store() {
+        let mut x = TempStore::new("store_compressed_large_test").unwrap();
+        x.set_compressed(true);
+        let items: Vec<_> = (0..10_000).map(|i| format!("item {}", i)).collect();
+        for algorithm in [CompressionAlgorithm::Gzip, CompressionAlgorithm::Deflate] {
+            x.set_compression_algorithm(algorithm);
+            if x.store_exists() {
+                x.repack().unwrap();
+            }
+            x.set("items", &items).unwrap();
+            assert_eq!(x.get("items.9999").unwrap().unwrap(), "item 9999");
+            assert_eq!(x.get("items").unwrap().unwrap(), serde_json::json!(items));
+        }
+
+        // A truncated store file is an error rather than partial contents.
+        let data = fs::read(x.get_store_path()).unwrap();
+        fs::write(x.get_store_path(), &data[..data.len() / 2]).unwrap();
+        assert!(x.get("items.0").is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::path::PathBuf;
+    #[cfg(feature = "blobs")]
+    #[test]
+    fn blobs() {
+        let mut x = TempStore::new("store_blobs_test").unwrap();
+        assert!(matches!(x.gc_blobs(), Err(Error::NotFound)));
+        x.put_blob("a", b"first").unwrap();
+        x.put_blob("b.c", b"first").unwrap();
+        x.put_blob("d", b"second").unwrap();
+        assert_eq!(fs::read_dir(x.get_blobs_path()).unwrap().count(), 2);
+        assert_eq!(x.get_blob("b.c").unwrap().unwrap(), b"first");
+        assert_eq!(x.get_blob("e").unwrap(), None);
+        x.set("e", 1).unwrap();
+        assert_eq!(x.get_blob("e").unwrap_err().kind(), ErrorKind::InvalidValue);
 
-    use crate::Store;
+        x.put_blob("d", b"third").unwrap();
+        x.delete("a").unwrap();
+        assert_eq!(x.gc_blobs().unwrap(), 1);
+        assert_eq!(x.get_blob("b.c").unwrap().unwrap(), b"first");
+        assert_eq!(x.get_blob("d").unwrap().unwrap(), b"third");
 
-    #[cfg(feature = "crypto")]
-    use crate::Error;
+        // Blobs referenced only through an include are kept.
+        let reference = x.get("b.c").unwrap().unwrap();
+        fs::write(
+            x.get_store_dir_path().join("base.json"),
+            serde_json::json!({ "f": reference }).to_string(),
+        )
+        .unwrap();
+        x.set_includes(true);
+        x.set("$include", "base.json").unwrap();
+        x.delete("b").unwrap();
+        assert_eq!(x.gc_blobs().unwrap(), 0);
+        assert_eq!(x.get_blob("f").unwrap().unwrap(), b"first");
 
-    fn clean_store(x: &Store) {
-        if x.store_exists() {
-            x.delete_store().unwrap();
-        }
+        // The blobs move and are copied along with the configuration.
+        x.rename_config("config", "archived").unwrap();
+        x.duplicate_config("archived", "config").unwrap();
+        assert_eq!(x.get_blob("d").unwrap().unwrap(), b"third");
+        x.open_config("archived").unwrap();
+        assert_eq!(x.get_blob("d").unwrap().unwrap(), b"third");
+        x.open_config("config").unwrap();
+
+        let hash = x.get("d.$blob").unwrap().unwrap();
+        fs::write(x.get_blobs_path().join(hash.as_str().unwrap()), "tampered").unwrap();
+        assert!(matches!(x.get_blob("d"), Err(Error::ChecksumMismatch)));
     }
 
+    #[cfg(feature = "preserve_order")]
     #[test]
-    fn set_get() {
-        let mut x = Store::new("store_get_test").unwrap();
-        x.set_path(PathBuf::from("./"));
-        x.set_config_name("2");
-        let data_1 = "test1";
-        let data_2 = [4, 2, 7];
-        x.set("a.b", data_1).unwrap();
-        x.set("c", data_2).unwrap();
-        assert_eq!(x.get("a.b").unwrap().unwrap(), data_1);
+    fn preserve_order() {
+        let x = TempStore::new("store_preserve_order_test").unwrap();
+        x.init_store().unwrap();
+        fs::write(x.get_store_path(), r#"{"c":1,"b":{"z":1,"y":2},"a":3}"#).unwrap();
+        x.set("b.x", 3).unwrap();
+        x.delete("c").unwrap();
+        x.set("d", 4).unwrap();
         assert_eq!(
-            x.get("c").unwrap().unwrap().as_array().unwrap().len(),
-            data_2.len()
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            r#"{"b":{"z":1,"y":2,"x":3},"a":3,"d":4}"#
         );
-        assert_eq!(x.get("d").unwrap(), None);
-        clean_store(&x);
+        x.merge("b", serde_json::json!({ "z": null })).unwrap();
+        x.apply_patch(&[PatchOp::Remove {
+            path: "/b/y".to_string(),
+        }])
+        .unwrap();
+        assert_eq!(x.keys("").unwrap().unwrap(), ["b", "a", "d"]);
+        assert_eq!(x.keys("b").unwrap().unwrap(), ["x"]);
     }
 
     #[test]
-    fn invalid_set() {
-        let mut x = Store::new("store_invalid_set_test").unwrap();
-        x.set_path(PathBuf::from("./"));
-        x.set("x", "test1").unwrap();
-        match x.set("x.a", "test2") {
-            Ok(_) => panic!(),
-            Err(e) => assert_eq!(
-                e.to_string(),
-                json_dotpath::Error::BadPathElement.to_string()
-            ),
-        };
-        clean_store(&x);
+    fn sort_keys() {
+        let mut x = TempStore::new("store_sort_keys_test").unwrap();
+        x.init_store().unwrap();
+        fs::write(x.get_store_path(), r#"{"c":1,"b":[{"z":1,"y":2}],"a":3}"#).unwrap();
+        x.set_sort_keys(true);
+        x.set("d", 4).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            r#"{"a":3,"b":[{"y":2,"z":1}],"c":1,"d":4}"#
+        );
+        x.set_pretty(true);
+        x.set("d", 5).unwrap();
+        let contents = fs::read_to_string(x.get_store_path()).unwrap();
+        assert!(contents.find("\"a\"").unwrap() < contents.find("\"b\"").unwrap());
     }
 
     #[test]
-    fn delete() {
-        let mut x = Store::new("store_delete_test").unwrap();
-        x.set_path(PathBuf::from("./"));
-        let data = "test1";
-        x.set("a.b", data).unwrap();
-        assert_eq!(x.get("a.b").unwrap().unwrap(), data);
+    fn pretty_style() {
+        let mut x = TempStore::new("store_pretty_style_test").unwrap();
+        x.set_pretty_style(PrettyStyle {
+            indent: Indent::Tabs,
+            trailing_newline: false,
+        });
+        x.set("a.b", [1]).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\n\t\"a\": {\n\t\t\"b\": [\n\t\t\t1\n\t\t]\n\t}\n}"
+        );
+        x.set_pretty(true);
+        assert_eq!(x.get_pretty_style(), Some(PrettyStyle::default()));
+        x.set("a.b", 2).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\n  \"a\": {\n    \"b\": 2\n  }\n}"
+        );
+        x.set_pretty(false);
+        assert_eq!(x.get_pretty_style(), None);
+    }
+
+    #[cfg(feature = "json5")]
+    #[test]
+    fn json5() {
+        let x = TempStore::new("store_json5_test").unwrap();
+        x.init_store().unwrap();
+        let contents =
+            "// Edited by hand\n{\n  theme: 'dark', /* for now */\n  ports: [80, 443,],\n}\n";
+        fs::write(x.get_store_path(), contents).unwrap();
+        assert_eq!(x.get("theme").unwrap().unwrap(), "dark");
+        assert_eq!(x.get("ports.1").unwrap().unwrap(), 443);
+        x.set("theme", "light").unwrap();
+        let written: serde_json::Value =
+            serde_json::from_slice(&fs::read(x.get_store_path()).unwrap()).unwrap();
+        assert_eq!(
+            written,
+            serde_json::json!({ "theme": "light", "ports": [80, 443] })
+        );
+
+        fs::write(x.get_store_path(), "{ theme: }").unwrap();
+        assert!(matches!(x.get("theme"), Err(Error::Corrupt { .. })));
+    }
+
+    #[test]
+    fn preserve_formatting() {
+        let mut x = TempStore::new("store_preserve_formatting_test").unwrap();
+        x.set_preserve_formatting(true);
+        x.init_store().unwrap();
+        let contents = "{\n  \"a\":   1,\n  \"b\": {\"c\": [1,2], \"d\": true},\n  \"e\": null,\n  \"f\": 2\n}\n";
+        fs::write(x.get_store_path(), contents).unwrap();
+        x.set("b.c", [3]).unwrap();
         x.delete("a").unwrap();
-        assert_eq!(x.get("a").unwrap(), None);
-        clean_store(&x);
+        x.set("b.g", "h").unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\n  \"b\": {\"c\": [3], \"d\": true, \"g\": \"h\"},\n  \"e\": null,\n  \"f\": 2\n}\n"
+        );
+        x.delete("e").unwrap();
+        x.delete("f").unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\n  \"b\": {\"c\": [3], \"d\": true, \"g\": \"h\"}\n}\n"
+        );
+        x.delete("b").unwrap();
+        x.set("i", serde_json::json!({ "j": 1 })).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\"i\":{\"j\":1}}\n"
+        );
+
+        // Files which cannot be scanned are rewritten whole.
+        fs::write(x.get_store_path(), "{\"i\": 1, \"i\": 2}").unwrap();
+        x.set("k", 3).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            r#"{"i":2,"k":3}"#
+        );
     }
 
+    #[cfg(feature = "json5")]
     #[test]
-    fn init() {
-        let mut x = Store::new("clear_test").unwrap();
-        x.set_path(PathBuf::from("./"));
-        let data_1 = "test1";
-        let data_2 = [4, 2, 7];
+    fn preserve_comments() {
+        let mut x = TempStore::new("store_preserve_comments_test").unwrap();
+        x.set_preserve_formatting(true);
+        x.set_pretty(true);
+        x.init_store().unwrap();
+        let contents = "// Settings\n{\n  // The theme\n  \"theme\": \"dark\", // or light\n  \"old\": 1, // unused\n  /* Sizes */ \"size\": 12,\n}\n";
+        fs::write(x.get_store_path(), contents).unwrap();
+        x.set("theme", "light").unwrap();
+        x.delete("old").unwrap();
+        x.set("font", serde_json::json!({ "name": "mono" }))
+            .unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "// Settings\n{\n  // The theme\n  \"theme\": \"light\", // or light\n  /* Sizes */ \"size\": 12,\n  \"font\": {\n    \"name\": \"mono\"\n  },\n}\n"
+        );
 
-        x.set("a.b", data_1).unwrap();
-        x.set("c", data_2).unwrap();
-        assert_eq!(x.get("a.b").unwrap().unwrap(), data_1);
+        // Emptying an object keeps the comments inside it.
+        fs::write(
+            x.get_store_path(),
+            "{ // keep me\n  \"a\": 1,\n  \"b\": 2\n}",
+        )
+        .unwrap();
+        x.delete("a").unwrap();
+        x.delete("b").unwrap();
         assert_eq!(
-            x.get("c").unwrap().unwrap().as_array().unwrap().len(),
-            data_2.len()
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{ // keep me\n}"
         );
+    }
+
+    #[cfg(feature = "arbitrary_precision")]
+    #[test]
+    fn arbitrary_precision() {
+        let x = TempStore::new("store_arbitrary_precision_test").unwrap();
+        let id = u128::MAX - 1;
+        x.set("id", id).unwrap();
+        assert_eq!(x.get_as::<u128>("id").unwrap(), Some(id));
+        x.set("big", i128::MIN).unwrap();
+        assert_eq!(x.get_as::<i128>("big").unwrap(), Some(i128::MIN));
+
         x.init_store().unwrap();
-        assert_eq!(x.get("a.b").unwrap(), None);
-        assert_eq!(x.get("c").unwrap(), None);
-        clean_store(&x);
+        let decimal = "0.1000000000000000000000000001";
+        fs::write(x.get_store_path(), format!(r#"{{"rate": {}}}"#, decimal)).unwrap();
+        x.set("other", 1).unwrap();
+        assert_eq!(x.get("rate").unwrap().unwrap().to_string(), decimal);
+        let contents = fs::read_to_string(x.get_store_path()).unwrap();
+        assert!(contents.contains(decimal));
     }
 
+    #[cfg(feature = "chrono")]
     #[test]
-    fn set_get_config_name() {
-        let mut x = Store::new("store_set_config_name_test").unwrap();
-        x.set_config_name("test");
-        assert_eq!(x.get_config_name(), "test");
-        clean_store(&x)
+    fn datetime() {
+        use chrono::{FixedOffset, TimeZone, Utc};
+
+        let x = TempStore::new("store_datetime_test").unwrap();
+        let offset = FixedOffset::east_opt(2 * 3600).unwrap();
+        let datetime = offset.with_ymd_and_hms(2024, 5, 1, 12, 30, 0).unwrap();
+        x.set_datetime("a", datetime).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "2024-05-01T12:30:00+02:00");
+        assert_eq!(x.get_datetime("a").unwrap().unwrap(), datetime);
+        assert_eq!(x.get_datetime("b").unwrap(), None);
+
+        let before = Utc::now();
+        x.touch("b").unwrap();
+        let touched = x.get_datetime("b").unwrap().unwrap();
+        assert!(touched >= before && touched <= Utc::now());
+
+        x.set("c", "yesterday").unwrap();
+        assert_eq!(
+            x.get_datetime("c").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
+        x.set("c", 1).unwrap();
+        assert_eq!(
+            x.get_datetime("c").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
     }
 
+    #[cfg(feature = "uuid")]
     #[test]
-    fn set_get_project_name() {
-        let mut x = Store::new("store_set_config_name_test").unwrap();
-        x.set_project_name("test");
-        assert_eq!(x.get_project_name(), "test");
-        clean_store(&x)
+    fn uuid() {
+        let x = TempStore::new("store_uuid_test").unwrap();
+        let uuid = uuid::Uuid::new_v4();
+        x.set_uuid("a", uuid).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), uuid.hyphenated().to_string());
+        assert_eq!(x.get_uuid("a").unwrap(), Some(uuid));
+        assert_eq!(x.get_uuid("b").unwrap(), None);
+        x.set("b", "not a uuid").unwrap();
+        assert_eq!(x.get_uuid("b").unwrap_err().kind(), ErrorKind::InvalidValue);
+
+        let id = x.install_id().unwrap();
+        assert_ne!(id, uuid);
+        assert_eq!(x.install_id().unwrap(), id);
+        assert_eq!(x.get_uuid("install_id").unwrap(), Some(id));
+
+        let y = TempStore::new("store_install_id_test").unwrap();
+        let id = y.install_id().unwrap();
+        assert_eq!(y.install_id().unwrap(), id);
     }
 
+    #[cfg(feature = "base64")]
     #[test]
-    fn set_get_project_suffix() {
-        let mut x = Store::new("store_set_project_suffix_test").unwrap();
-        x.set_project_suffix(Some("test"));
-        assert_eq!(x.get_project_suffix(), Some("test"));
-        clean_store(&x)
+    fn bytes() {
+        let x = TempStore::new("store_bytes_test").unwrap();
+        x.set_bytes("a", b"\x00\xffbland").unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "AP9ibGFuZA==");
+        assert_eq!(x.get_bytes("a").unwrap().unwrap(), b"\x00\xffbland");
+        x.set_bytes("b", &[]).unwrap();
+        assert_eq!(x.get_bytes("b").unwrap().unwrap(), b"");
+        assert_eq!(x.get_bytes("c").unwrap(), None);
+        x.set("c", "not base64!").unwrap();
+        assert_eq!(
+            x.get_bytes("c").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
+        x.set("c", [1, 2]).unwrap();
+        assert_eq!(
+            x.get_bytes("c").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
     }
 
     #[test]
-    fn set_get_file_extension() {
-        let mut x = Store::new("store_set_file_extension_test").unwrap();
-        x.set_file_extension("test");
-        assert_eq!(x.get_file_extension(), "test");
-        clean_store(&x)
+    fn typed_accessors() {
+        let mut x = TempStore::new("store_typed_accessors_test").unwrap();
+        x.merge(
+            "",
+            serde_json::json!({
+                "s": "text", "b": true, "i": -4, "f": 1.5,
+                "sb": "Off", "si": "42", "sf": "2.5", "n1": 1, "whole": 3.0,
+            }),
+        )
+        .unwrap();
+        assert_eq!(x.get_string("s").unwrap().unwrap(), "text");
+        assert_eq!(x.get_bool("b").unwrap(), Some(true));
+        assert_eq!(x.get_i64("i").unwrap(), Some(-4));
+        assert_eq!(x.get_f64("f").unwrap(), Some(1.5));
+        assert_eq!(x.get_f64("i").unwrap(), Some(-4.0));
+        assert_eq!(x.get_bool("missing").unwrap(), None);
+        let error = x.get_i64("f").unwrap_err();
+        assert!(matches!(error, Error::InvalidValue { ref path, .. } if path == "f"));
+        assert_eq!(
+            x.get_bool("sb").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
+        assert_eq!(
+            x.get_string("i").unwrap_err().kind(),
+            ErrorKind::InvalidValue
+        );
+
+        x.set_coerce_types(true);
+        assert_eq!(x.get_bool("sb").unwrap(), Some(false));
+        assert_eq!(x.get_bool("n1").unwrap(), Some(true));
+        assert_eq!(x.get_i64("si").unwrap(), Some(42));
+        assert_eq!(x.get_i64("whole").unwrap(), Some(3));
+        assert_eq!(x.get_f64("sf").unwrap(), Some(2.5));
+        assert_eq!(x.get_string("i").unwrap().unwrap(), "-4");
+        assert_eq!(x.get_i64("f").unwrap_err().kind(), ErrorKind::InvalidValue);
+        assert_eq!(x.get_bool("s").unwrap_err().kind(), ErrorKind::InvalidValue);
     }
 
-    #[cfg(feature = "crypto")]
     #[test]
-    fn set_encryption_key() {
-        let mut x = Store::new("set_encryption_key_test").unwrap();
-        x.set_encryption_key("test_key").unwrap();
+    fn max_size() {
+        let mut x = TempStore::new("store_max_size_test").unwrap();
+        x.set_max_size(Some(20));
+        x.set("a", "12345").unwrap();
+        let error = x.set("b", "12345").unwrap_err();
+        assert!(matches!(
+            error,
+            Error::QuotaExceeded { ref path, size: 25, limit: 20 } if path.is_empty()
+        ));
         assert_eq!(
-            x.get_encryption_key(),
-            Some([
-                116, 101, 115, 116, 95, 107, 101, 121, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 0, 0, 0, 0, 0
-            ])
-        )
+            error.to_string(),
+            "Store would be 25 bytes, exceeding its maximum size of 20 bytes"
+        );
+        assert_eq!(x.get("b").unwrap(), None);
+
+        x.set_journal(true);
+        assert!(x.set("b", "12345").unwrap_err().is_quota_exceeded());
+        x.set_deferred(true);
+        assert!(x.set("b", "12345").unwrap_err().is_quota_exceeded());
+        x.set_deferred(false);
+        x.set_max_size(None);
+        x.set("b", "12345").unwrap();
+        assert_eq!(x.get("b").unwrap().unwrap(), "12345");
+
+        // A store over its limit can shrink, but not grow.
+        x.set_max_size(Some(20));
+        assert!(x.set("c", 1).unwrap_err().is_quota_exceeded());
+        x.set("b", "1").unwrap();
+        x.delete("b").unwrap();
+        x.set("c", 1).unwrap();
+
+        // Blobs count towards the limit.
+        #[cfg(feature = "blobs")]
+        {
+            x.set_max_size(Some(110));
+            let error = x.put_blob("d", &[0; 100]).unwrap_err();
+            assert!(error.is_quota_exceeded());
+            assert!(!x.get_blobs_path().exists());
+            x.put_blob("d", &[0; 4]).unwrap();
+        }
     }
 
-    #[cfg(feature = "crypto")]
+    #[cfg(feature = "sharding")]
     #[test]
-    fn invalid_encryption_key() {
-        let mut x = Store::new("invalid_encryption_key_test").unwrap();
-        match x.set_encryption_key("test_key_this_key_will_be_too_long") {
-            Ok(_) => panic!(),
-            Err(e) => assert_eq!(e.to_string(), Error::InvalidKeyLength.to_string()),
-        };
+    fn sharding() {
+        let mut x = TempStore::new("store_sharding_test").unwrap();
+        x.set_sharded(true);
+        x.set("a.b", 1).unwrap();
+        x.set("c", serde_json::json!([1, 2])).unwrap();
+        x.set("d", "e").unwrap();
+        assert_eq!(fs::read_dir(x.get_shards_path()).unwrap().count(), 2);
+        let root: serde_json::Value =
+            serde_json::from_slice(&fs::read(x.get_store_path()).unwrap()).unwrap();
+        assert_eq!(root["a"]["$shard"], "1-a");
+        assert!(root["a"]["$hash"].is_string());
+        assert_eq!(root["d"], "e");
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 1);
+        assert_eq!(x.get("c.1").unwrap().unwrap(), 2);
+        assert_eq!(x.get("a").unwrap().unwrap(), serde_json::json!({"b": 1}));
+        assert_eq!(x.to_value().unwrap()["c"], serde_json::json!([1, 2]));
+
+        // Only the changed section is rewritten, and the old one is kept for readers of the
+        // previous store file until the next write.
+        let shard_path = x
+            .get_shards_path()
+            .join(root["c"]["$shard"].as_str().unwrap());
+        let old_shard_path = x
+            .get_shards_path()
+            .join(root["a"]["$shard"].as_str().unwrap());
+        x.set("a.b", 2).unwrap();
+        assert!(shard_path.exists());
+        assert!(old_shard_path.exists());
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 2);
+        x.set("d", "f").unwrap();
+        assert!(!old_shard_path.exists());
+        assert_eq!(fs::read_dir(x.get_shards_path()).unwrap().count(), 2);
+
+        // The sections move and are copied along with the configuration.
+        x.rename_config("config", "archived").unwrap();
+        x.duplicate_config("archived", "config").unwrap();
+        assert_eq!(x.get("c.1").unwrap().unwrap(), 2);
+        x.open_config("archived").unwrap();
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 2);
+        x.delete_config().unwrap();
+        x.open_config("config").unwrap();
+
+        x.set_sharded(false);
+        assert_eq!(x.get("c.0").unwrap().unwrap(), 1);
+        x.set("d", "g").unwrap();
+        x.set("d", "h").unwrap();
+        assert_eq!(fs::read_dir(x.get_shards_path()).unwrap().count(), 0);
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 2);
     }
 
-    #[cfg(feature = "crypto")]
+    #[cfg(feature = "mmap")]
     #[test]
-    fn crypto() {
-        let mut x = Store::new("crypto_test").unwrap();
-        x.set_encryption_key("test_key").unwrap();
-        let data = "test_data";
-        x.set("a", data).unwrap();
-        assert_eq!(x.get("a").unwrap().unwrap(), data);
-        clean_store(&x);
+    fn mmap() {
+        let mut x = TempStore::new("store_mmap_test").unwrap();
+        x.set_mmap(true);
+        x.set("a.b", 1).unwrap();
+        assert!(x.map_store_file().unwrap().is_some());
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 1);
+        assert_eq!(x.keys("a").unwrap().unwrap(), ["b"]);
+
+        // Journaled stores are read into memory.
+        x.set("padding", "-".repeat(1000)).unwrap();
+        x.set_journal(true);
+        x.set("a.c", 2).unwrap();
+        assert!(x.map_store_file().unwrap().is_none());
+        assert_eq!(x.get("a.c").unwrap().unwrap(), 2);
+        x.compact().unwrap();
+
+        fs::write(x.get_store_path(), "{\"a\":").unwrap();
+        assert!(matches!(x.get("a"), Err(Error::Corrupt { .. })));
+        x.delete_config().unwrap();
+        assert!(matches!(x.get("a"), Err(Error::NotFound)));
+    }
+
+    #[cfg(all(feature = "mmap", feature = "compression"))]
+    #[test]
+    fn mmap_compressed() {
+        let mut x = TempStore::new("store_mmap_compressed_test").unwrap();
+        x.set_mmap(true);
+        x.set_compressed(true);
+        x.set("a.b", 1).unwrap();
+        assert!(x.map_store_file().unwrap().is_some());
+        assert_eq!(x.get("a.b").unwrap().unwrap(), 1);
+        assert_eq!(
+            x.to_value().unwrap(),
+            serde_json::json!({ "a": { "b": 1 } })
+        );
     }
 
     #[cfg(feature = "compression")]
     #[test]
-    fn compression() {
-        let mut x = Store::new("compression_test").unwrap();
+    fn repack() {
+        let mut x = TempStore::new("store_repack_test").unwrap();
+        x.set("a", "test_data").unwrap();
+        x.set_compressed(true);
+        assert!(x.get("a").is_err());
+        x.repack().unwrap();
+        assert_eq!(
+            std::fs::read(x.get_store_path()).unwrap()[..2],
+            [0x1f, 0x8b]
+        );
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
+
+        x.set_compression_algorithm(CompressionAlgorithm::Deflate);
+        x.repack().unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), "test_data");
+        x.set_compressed(false);
+        x.repack().unwrap();
+        assert_eq!(
+            std::fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\"a\":\"test_data\"}"
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn compression_level_and_algorithm() {
+        let mut x = TempStore::new("compression_level_test").unwrap();
         x.set_compressed(true);
+        x.set_compression_level(12);
+        assert_eq!(x.get_compression_level(), 9);
+        x.set_compression_algorithm(CompressionAlgorithm::Deflate);
+        assert_eq!(x.get_compression_algorithm(), CompressionAlgorithm::Deflate);
         let data = "test_data";
         x.set("a", data).unwrap();
         assert_eq!(x.get("a").unwrap().unwrap(), data);
-        clean_store(&x);
+        // Deflate has no gzip header.
+        assert_ne!(
+            std::fs::read(x.get_store_path()).unwrap()[..2],
+            [0x1f, 0x8b]
+        );
+    }
+
+    // Stores written by electron-store and conf are read, and written in their format
+    #[cfg(feature = "electron")]
+    #[test]
+    fn electron_compat() {
+        let mut x = TempStore::new("store_electron_compat_test").unwrap();
+        x.set_electron_compat(true);
+        x.set("a", 1).unwrap();
+        assert_eq!(
+            fs::read_to_string(x.get_store_path()).unwrap(),
+            "{\n\t\"a\": 1\n}"
+        );
+
+        // A plain text store is read even with an encryption key, as conf does.
+        x.set_electron_encryption_key(Some("secret"));
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+
+        // Encrypted by conf, with an initialization vector that is not valid UTF-8.
+        let encrypted = base64::Engine::decode(
+            &base64::engine::general_purpose::STANDARD,
+            "AAECAwQFBgcICQoLDA0O/zoY6ZGoalu699dyzo3ZJKAv",
+        )
+        .unwrap();
+        fs::write(x.get_store_path(), encrypted).unwrap();
+        assert_eq!(x.get("a").unwrap().unwrap(), 1);
+        x.set("b", 2).unwrap();
+        let data = fs::read(x.get_store_path()).unwrap();
+        assert_eq!(data[16], b':');
+        assert_eq!(x.to_value().unwrap(), serde_json::json!({ "a": 1, "b": 2 }));
+
+        x.set_electron_encryption_key(Some("wrong"));
+        assert!(x.get("a").unwrap_err().is_corrupt());
+        x.set_electron_compat(false);
+        assert!(!x.get_electron_compat());
+
+        let store = Store::electron_store("Electron App").unwrap();
+        assert!(store.get_electron_compat());
+        assert_eq!(
+            store.get_store_path(),
+            dirs::config_dir()
+                .unwrap()
+                .join("Electron App")
+                .join("config.json")
+        );
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            Store::conf("node-app").unwrap().get_store_path(),
+            dirs::config_dir()
+                .unwrap()
+                .join("node-app-nodejs/config.json")
+        );
     }
 
-    // This test should prioritize the encryption over the compression
+    // The store is compressed and then encrypted, and the compression is recorded in the header
     #[cfg(feature = "compression")]
     #[cfg(feature = "crypto")]
     #[test]
     fn compression_and_encryption() {
-        let mut x = Store::new("compression_encryption_test").unwrap();
+        let mut x = TempStore::new("compression_encryption_test").unwrap();
         x.set_compressed(true);
         x.set_encryption_key("the encryption key").unwrap();
         let data = "test data";
         x.set("a", data).unwrap();
         x.set_compressed(false);
         assert_eq!(x.get("a").unwrap().unwrap(), data);
-        clean_store(&x);
+        let store_data = std::fs::read(x.get_store_path()).unwrap();
+        assert_eq!(store_data[..6], *b"BLND\x01\x01");
     }
 }
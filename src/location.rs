@@ -0,0 +1,276 @@
+use crate::{path::env_name, Error, IoOp, Result, Store};
+use std::{env, io, path::PathBuf};
+
+/// The kind of data a store holds, which decides the base directory it is kept in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StoreKind {
+    /// Configuration, kept in the configuration directory.
+    #[default]
+    Config,
+    /// Application data, kept in the data directory.
+    Data,
+    /// Data which can be regenerated, kept in the cache directory.
+    Cache,
+    /// State that should persist between runs but is not worth backing up,
+    /// such as window positions or recently opened files, kept in the state directory.
+    State,
+}
+
+impl Store {
+    /// Creates a new instance of the store named after the platform's conventions for
+    /// the given qualifier, organization and application, like the `directories` crate.
+    ///
+    /// The store directory within the configuration directory is
+    /// * `com.Example-Org.My-App` on macOS,
+    /// * `Example Org\My App` on Windows,
+    /// * `myapp` elsewhere.
+    ///
+    /// No project suffix is appended.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::with_project("com", "Example Org", "My App").unwrap();
+    /// # #[cfg(target_os = "linux")]
+    /// assert!(store.get_store_dir_path().ends_with("myapp"));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the config directory cannot be found.
+    pub fn with_project(qualifier: &str, organization: &str, application: &str) -> Result<Self> {
+        let mut store = Store::new(&project_folder(qualifier, organization, application))?;
+        store.set_project_suffix(None);
+        Ok(store)
+    }
+
+    /// Creates a new instance of the store kept next to the executable rather than in the
+    /// user's profile, for portable applications distributed as an archive or on a USB stick.
+    ///
+    /// The base directory is the `config` directory next to the executable if there is one,
+    /// otherwise the executable's directory. Every [`StoreKind`] shares this directory.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::portable("my-app").unwrap();
+    /// let exe_dir = std::env::current_exe().unwrap().parent().unwrap().to_path_buf();
+    /// assert!(store.get_path().starts_with(exe_dir));
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the path of the executable cannot be found.
+    pub fn portable(project_name: &str) -> Result<Self> {
+        Store::with_dir_provider(project_name, PortableDirs::new()?)
+    }
+
+    /// Sets the environment variable which overrides the store directory, or `None` to
+    /// ignore the environment, the default.
+    ///
+    /// When the variable is set and not empty, its value is used as the store directory
+    /// in place of the computed one, so the store can be redirected without code changes.
+    /// [`get_default_dir_env`](Store::get_default_dir_env) returns the conventional name.
+    ///
+    /// The directory may be shared with other applications, so
+    /// [`delete_store_dir`](Store::delete_store_dir) refuses to delete it.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use std::path::PathBuf;
+    /// let mut store = Store::new("my-app").unwrap();
+    /// assert_eq!(store.get_dir_env(), None);
+    /// assert_eq!(store.get_default_dir_env(), "MY_APP_CONFIG_DIR");
+    /// store.set_dir_env(Some("MY_APP_DIR_OVERRIDE"));
+    /// std::env::set_var("MY_APP_DIR_OVERRIDE", "/tmp/my-app");
+    /// assert_eq!(store.get_store_dir_path(), PathBuf::from("/tmp/my-app"));
+    /// ```
+    pub fn set_dir_env(&mut self, key: Option<&str>) {
+        self.dir_env = key.map(str::to_owned);
+    }
+
+    pub fn get_dir_env(&self) -> Option<&str> {
+        self.dir_env.as_deref()
+    }
+
+    /// Returns the conventional name of the variable overriding the store directory,
+    /// `<PROJECT_NAME>_CONFIG_DIR`, derived from the name the store was created with by
+    /// uppercasing it and replacing anything but letters and digits with `_`.
+    pub fn get_default_dir_env(&self) -> String {
+        format!("{}_CONFIG_DIR", env_name(&self.project_name))
+    }
+
+    /// Returns the store directory set by the [`dir_env`](Store::set_dir_env) variable.
+    pub(crate) fn get_dir_override(&self) -> Option<PathBuf> {
+        let key = self.dir_env.as_deref()?;
+        env::var_os(key)
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+    }
+
+    /// Checks that the store directory was not set by the [`dir_env`](Store::set_dir_env)
+    /// variable before it is deleted whole, as it may be shared with other applications.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory was set by the environment.
+    pub(crate) fn check_dir_deletable(&self) -> Result<()> {
+        match self.get_dir_override() {
+            Some(dir) => Err(Error::Io {
+                path: dir,
+                op: IoOp::Remove,
+                source: io::Error::other("the store directory is set by the environment"),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the kind of data the store holds and moves the store to the base directory
+    /// of that kind, replacing any path set with [`set_path`](Store::set_path).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Store, StoreKind};
+    /// let mut store = Store::new("kind-app").unwrap();
+    /// store.set_kind(StoreKind::Cache).unwrap();
+    /// assert_eq!(store.get_path(), dirs::cache_dir().unwrap());
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the directory provider cannot find a directory for the kind.
+    pub fn set_kind(&mut self, kind: StoreKind) -> Result<()> {
+        self.path = base_dir(self.dir_provider.as_ref(), kind).ok_or(Error::ConfigDir)?;
+        self.kind = kind;
+        Ok(())
+    }
+
+    pub fn get_kind(&self) -> StoreKind {
+        self.kind
+    }
+}
+
+/// Returns the platform specific name of a project's folder.
+#[cfg(target_os = "macos")]
+fn project_folder(qualifier: &str, organization: &str, application: &str) -> String {
+    [qualifier, organization, application]
+        .iter()
+        .filter(|part| !part.is_empty())
+        .map(|part| part.replace(' ', "-"))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Returns the platform specific name of a project's folder.
+#[cfg(target_os = "windows")]
+fn project_folder(_qualifier: &str, organization: &str, application: &str) -> String {
+    match organization.is_empty() {
+        true => application.to_owned(),
+        false => format!("{}\\{}", organization, application),
+    }
+}
+
+/// Returns the platform specific name of a project's folder.
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+fn project_folder(_qualifier: &str, _organization: &str, application: &str) -> String {
+    application
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Resolves the base directory stores are kept in.
+///
+/// Implement this to fully control where stores are located, for example in sandboxed
+/// environments or tests, and pass it to [`Store::with_dir_provider`].
+pub trait DirProvider: Send + Sync + 'static {
+    /// Returns the directory configuration stores are kept in, if there is one.
+    fn config_dir(&self) -> Option<PathBuf>;
+
+    /// Returns the directory data stores are kept in, the config directory by default.
+    fn data_dir(&self) -> Option<PathBuf> {
+        self.config_dir()
+    }
+
+    /// Returns the directory cache stores are kept in, the config directory by default.
+    fn cache_dir(&self) -> Option<PathBuf> {
+        self.config_dir()
+    }
+
+    /// Returns the directory state stores are kept in, the config directory by default.
+    fn state_dir(&self) -> Option<PathBuf> {
+        self.config_dir()
+    }
+}
+
+/// The platform's directories as resolved by the `dirs` crate, used by [`Store::new`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SystemDirs;
+
+impl DirProvider for SystemDirs {
+    fn config_dir(&self) -> Option<PathBuf> {
+        dirs::config_dir()
+    }
+
+    fn data_dir(&self) -> Option<PathBuf> {
+        dirs::data_dir()
+    }
+
+    fn cache_dir(&self) -> Option<PathBuf> {
+        dirs::cache_dir()
+    }
+
+    /// Returns `$XDG_STATE_HOME` or `~/.local/state` on Linux,
+    /// and the local data directory elsewhere.
+    fn state_dir(&self) -> Option<PathBuf> {
+        #[cfg(target_os = "linux")]
+        {
+            env::var_os("XDG_STATE_HOME")
+                .map(PathBuf::from)
+                .filter(|path| path.is_absolute())
+                .or_else(|| dirs::home_dir().map(|home| home.join(".local/state")))
+        }
+        #[cfg(not(target_os = "linux"))]
+        dirs::data_local_dir()
+    }
+}
+
+/// The directory next to the executable, used by [`Store::portable`].
+struct PortableDirs {
+    root: PathBuf,
+}
+
+impl PortableDirs {
+    fn new() -> Result<Self> {
+        let exe = env::current_exe()?;
+        let exe_dir = exe.parent().ok_or(Error::ConfigDir)?;
+        let config_dir = exe_dir.join("config");
+        let root = match config_dir.is_dir() {
+            true => config_dir,
+            false => exe_dir.to_path_buf(),
+        };
+        Ok(PortableDirs { root })
+    }
+}
+
+impl DirProvider for PortableDirs {
+    fn config_dir(&self) -> Option<PathBuf> {
+        Some(self.root.clone())
+    }
+}
+
+/// Returns the base directory of the given kind of store.
+pub(crate) fn base_dir(provider: &dyn DirProvider, kind: StoreKind) -> Option<PathBuf> {
+    match kind {
+        StoreKind::Config => provider.config_dir(),
+        StoreKind::Data => provider.data_dir(),
+        StoreKind::Cache => provider.cache_dir(),
+        StoreKind::State => provider.state_dir(),
+    }
+}
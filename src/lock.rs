@@ -0,0 +1,68 @@
+use crate::{
+    error::IoContext,
+    trace::{trace_done, trace_span},
+    IoOp, Result, Store,
+};
+use std::{
+    ffi::OsString,
+    fs::File,
+    path::{Path, PathBuf},
+};
+
+impl Store {
+    /// Get the path to the lock file held while the store file is read and written,
+    /// `<store file>.lock`.
+    pub fn get_lock_path(&self) -> PathBuf {
+        lock_path(&self.get_store_path())
+    }
+
+    /// Takes an exclusive lock on the store's lock file, released when the returned file is
+    /// dropped. This serializes read-modify-writes between threads and processes.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store directory or the lock file cannot be created, or the lock cannot be taken.
+    pub(crate) fn lock_store_file(&self) -> Result<File> {
+        self.lock_config_file(&self.get_store_path())
+    }
+
+    /// Takes an exclusive lock on the lock file of the configuration file at `config_path`
+    /// in the store directory, released when the returned file is dropped.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store directory or the lock file cannot be created, or the lock cannot be taken.
+    pub(crate) fn lock_config_file(&self, config_path: &Path) -> Result<File> {
+        self.check_writable()?;
+        let lock_path = lock_path(config_path);
+        trace_span!(timer, "bland.lock", path = %lock_path.display());
+        let store_dir_path = self.get_store_dir_path();
+        self.dir_builder()
+            .recursive(true)
+            .create(&store_dir_path)
+            .context(IoOp::CreateDir, &store_dir_path)?;
+        let lock_file = self
+            .file_options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .context(IoOp::Create, &lock_path)?;
+        lock_file.lock().context(IoOp::Lock, &lock_path)?;
+        // Other processes may have written while the cached contents were considered fresh.
+        self.revalidate_cache();
+        trace_done!(timer, "acquired store lock");
+        Ok(lock_file)
+    }
+}
+
+/// Returns the path to the lock file of the configuration file at `config_path`.
+fn lock_path(config_path: &Path) -> PathBuf {
+    let mut lock_path: OsString = config_path.into();
+    lock_path.push(".lock");
+    lock_path.into()
+}
@@ -0,0 +1,27 @@
+/// Creates a [`Store`](crate::Store) named after the calling crate, using
+/// [`Store::from_cargo_env`](crate::Store::from_cargo_env).
+///
+/// `store!()` names the store after `CARGO_PKG_NAME`, and `store!(version)` additionally
+/// keeps a separate configuration file per major version from `CARGO_PKG_VERSION_MAJOR`.
+///
+/// # Example
+///
+/// ```rust
+/// let store = bland::store!().unwrap();
+/// assert_eq!(store.get_project_name(), "bland");
+///
+/// let store = bland::store!(version).unwrap();
+/// assert_eq!(store.get_config_name(), format!("config-v{}", env!("CARGO_PKG_VERSION_MAJOR")));
+/// ```
+#[macro_export]
+macro_rules! store {
+    () => {
+        $crate::Store::from_cargo_env(env!("CARGO_PKG_NAME"), None)
+    };
+    (version) => {
+        $crate::Store::from_cargo_env(
+            env!("CARGO_PKG_NAME"),
+            Some(env!("CARGO_PKG_VERSION_MAJOR")),
+        )
+    };
+}
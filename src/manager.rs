@@ -0,0 +1,168 @@
+use crate::{error::IoContext, IoOp, Result, Store};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Manages every configuration file of an application, for "reset all settings"
+/// and diagnostics screens.
+///
+/// The manager locates stores like the [`Store`] it is created from,
+/// and opens them with the same settings.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::StoreManager;
+/// let manager = StoreManager::new("manager-app").unwrap();
+/// manager.open("work").set("theme", "dark").unwrap();
+/// manager.open("home").set("theme", "light").unwrap();
+/// assert_eq!(manager.list_configs().unwrap(), vec!["home", "work"]);
+/// assert!(manager.disk_usage().unwrap() > 0);
+/// manager.delete_all().unwrap();
+/// assert!(manager.list_configs().unwrap().is_empty());
+/// ```
+pub struct StoreManager {
+    store: Store,
+}
+
+impl StoreManager {
+    /// Creates a manager for the stores of the given project in the default location.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the config directory cannot be found.
+    pub fn new(project_name: &str) -> Result<Self> {
+        Ok(StoreManager {
+            store: Store::new(project_name)?,
+        })
+    }
+
+    /// Get the path to the directory where the project's stores are kept.
+    pub fn get_store_dir_path(&self) -> PathBuf {
+        self.store.get_store_dir_path()
+    }
+
+    /// Returns the names of all configuration files, sorted alphabetically.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be read.
+    pub fn list_configs(&self) -> Result<Vec<String>> {
+        self.store.list_configs()
+    }
+
+    /// Opens the configuration with the given name, which is created on the first write.
+    ///
+    /// The store has the settings of the store the manager was created from, such as its
+    /// encryption key, compression and permissions, without its profile. Callbacks,
+    /// namespaces and subscribers registered on that store are not carried over.
+    pub fn open(&self, config_name: &str) -> Store {
+        let mut store = self.store.clone_settings();
+        store.clear_profile();
+        store.set_config_name(config_name);
+        store
+    }
+
+    /// Copies every configuration file, including profiles, into the given directory
+    /// along with its journal, shards, blobs and generation, and returns the paths of the
+    /// copied configuration files.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory cannot be read or a file fails to be copied.
+    pub fn backup_all(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        fs::create_dir_all(dir).context(IoOp::CreateDir, dir)?;
+        let mut copies = Vec::new();
+        for path in self.config_files()? {
+            if let Some(file_name) = path.file_name() {
+                let copy = dir.join(file_name);
+                fs::copy(&path, &copy).context(IoOp::Copy, &path)?;
+                self.store.copy_sidecars(&path, &copy)?;
+                copies.push(copy);
+            }
+        }
+        Ok(copies)
+    }
+
+    /// Deletes the store directory along with every configuration file and any other
+    /// data kept there.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store is read-only.
+    /// * The store directory cannot be deleted, or was set by the
+    ///   [`dir_env`](Store::set_dir_env) variable.
+    pub fn delete_all(&self) -> Result<()> {
+        self.store.check_writable()?;
+        if !self.store.store_dir_exists() {
+            return Ok(());
+        }
+        self.store.check_dir_deletable()?;
+        let store_dir_path = self.get_store_dir_path();
+        fs::remove_dir_all(&store_dir_path).context(IoOp::Remove, &store_dir_path)?;
+        Ok(())
+    }
+
+    /// Returns the total size in bytes of every file in the store directory,
+    /// including backups and history.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store directory or a file's metadata cannot be read.
+    pub fn disk_usage(&self) -> Result<u64> {
+        match self.store.store_dir_exists() {
+            true => dir_size(&self.get_store_dir_path()),
+            false => Ok(0),
+        }
+    }
+
+    /// Returns the paths of every configuration file, including profiles.
+    fn config_files(&self) -> Result<Vec<PathBuf>> {
+        if !self.store.store_dir_exists() {
+            return Ok(Vec::new());
+        }
+        let suffix = format!(".{}", self.store.get_file_extension());
+        let mut files = Vec::new();
+        let store_dir_path = self.get_store_dir_path();
+        for entry in fs::read_dir(&store_dir_path).context(IoOp::ReadDir, &store_dir_path)? {
+            let entry = entry.context(IoOp::ReadDir, &store_dir_path)?;
+            let is_config = entry
+                .file_name()
+                .to_str()
+                .is_some_and(|file_name| file_name.ends_with(&suffix));
+            if is_config
+                && entry
+                    .file_type()
+                    .context(IoOp::Metadata, &entry.path())?
+                    .is_file()
+            {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+impl From<Store> for StoreManager {
+    /// Creates a manager for the stores located like the given store.
+    fn from(store: Store) -> Self {
+        StoreManager { store }
+    }
+}
+
+/// Returns the total size in bytes of every file below the directory.
+fn dir_size(dir: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(dir).context(IoOp::ReadDir, dir)? {
+        let entry = entry.context(IoOp::ReadDir, dir)?;
+        let metadata = entry.metadata().context(IoOp::Metadata, &entry.path())?;
+        size += match metadata.is_dir() {
+            true => dir_size(&entry.path())?,
+            false => metadata.len(),
+        };
+    }
+    Ok(size)
+}
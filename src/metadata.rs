@@ -0,0 +1,306 @@
+use crate::{error::IoContext, Error, IoOp, Result, Store};
+use json_dotpath::DotPaths;
+use serde::Serialize;
+use serde_json::Value;
+use std::{fs, io, path::PathBuf, time::SystemTime};
+
+/// Information about the store a value was read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoreMetadata {
+    /// The time the store file or its journal was last modified,
+    /// `None` if the platform does not record it.
+    pub modified: Option<SystemTime>,
+    /// The store's generation, a counter which grows with every write to the store file
+    /// or its journal, and every deferred write. Changes made to the files other than
+    /// through this library are not counted.
+    pub generation: u64,
+    /// Whether the value equals the value at the same path in the defaults, which are
+    /// written when the store is created, so it has not been changed since.
+    pub from_defaults: bool,
+}
+
+/// Statistics about a store, returned by [`Store::stats`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoreStats {
+    /// The size of the store file and its journal on disk, in bytes.
+    pub size_on_disk: u64,
+    /// The size of the store's contents serialized as JSON, before compression and
+    /// encryption, in bytes.
+    pub uncompressed_size: u64,
+    /// The number of keys in every object of the store.
+    pub keys: usize,
+    /// The deepest nesting of objects and arrays, `0` if the store holds a single value.
+    pub depth: usize,
+    /// The time the store file or its journal was last modified,
+    /// `None` if the platform does not record it.
+    pub modified: Option<SystemTime>,
+    /// Whether the store is written encrypted, as a whole or in part.
+    pub encrypted: bool,
+    /// Whether the store is written compressed.
+    pub compressed: bool,
+}
+
+impl Store {
+    /// Returns the value at the dot path like [`get`](Store::get), along with metadata
+    /// about the store it was read from.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let mut store = Store::new("metadata-app").unwrap();
+    /// store.set_defaults(json!({ "theme": "light" }));
+    /// store.set("volume", 3).unwrap();
+    ///
+    /// let (theme, metadata) = store.get_with_metadata("theme").unwrap();
+    /// assert_eq!(theme.unwrap(), "light");
+    /// assert!(metadata.from_defaults);
+    ///
+    /// store.set("theme", "dark").unwrap();
+    /// let (_, changed) = store.get_with_metadata("theme").unwrap();
+    /// assert!(!changed.from_defaults);
+    /// assert_ne!(metadata.generation, changed.generation);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file or its metadata.
+    /// * The store cannot be deserialized.
+    /// * The path is not a valid dot path.
+    pub fn get_with_metadata(&self, path: &str) -> Result<(Option<Value>, StoreMetadata)> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        self.record_metrics(|metrics| metrics.record_get());
+        // The generation is read first, so it is never newer than the contents.
+        let generation = self.generation()?;
+        let store_data = self.read_store_file()?;
+        let journal = self.read_journal_file()?;
+        let modified = self.get_modified()?;
+        let parsed_json = match self.get_pending_value() {
+            Some(value) => value,
+            None => self.parse_journaled(store_data, journal.as_deref())?,
+        };
+        let value = parsed_json.dot_get::<Value>(path)?;
+        let from_defaults = match (&value, &self.defaults) {
+            (Some(value), Some(defaults)) => {
+                defaults.dot_get::<Value>(path)?.as_ref() == Some(value)
+            }
+            _ => false,
+        };
+        let metadata = StoreMetadata {
+            modified,
+            generation,
+            from_defaults,
+        };
+        Ok((value, metadata))
+    }
+
+    /// Returns the store's current generation, see [`StoreMetadata::generation`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store does not exist or it fails to read the generation file.
+    pub fn generation(&self) -> Result<u64> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        let pending = *self
+            .pending_generations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        Ok(self.read_generation()? + pending)
+    }
+
+    /// Get the path to the file holding the store's generation, the store file's path with
+    /// the `generation` extension.
+    pub fn get_generation_path(&self) -> PathBuf {
+        self.get_store_path().with_extension("generation")
+    }
+
+    /// Counts a deferred write towards the store's generation.
+    pub(crate) fn defer_generation(&self) {
+        *self
+            .pending_generations
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) += 1;
+    }
+
+    /// Advances the store's generation after the store file or its journal was written,
+    /// by one and by the number of deferred writes it persisted.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the generation file cannot be read or written to.
+    pub(crate) fn advance_generation(&self) -> Result<()> {
+        let pending = std::mem::take(
+            &mut *self
+                .pending_generations
+                .lock()
+                .unwrap_or_else(|e| e.into_inner()),
+        );
+        let generation = self.read_generation()? + pending.max(1);
+        self.replace_file(
+            &self.get_generation_path(),
+            generation.to_string().as_bytes(),
+        )
+    }
+
+    /// Reads the generation of the store file, `0` if it was never written.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the generation file exists but cannot be read.
+    fn read_generation(&self) -> Result<u64> {
+        let generation_path = self.get_generation_path();
+        match fs::read_to_string(&generation_path) {
+            Ok(generation) => Ok(generation.trim().parse().unwrap_or_default()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(0),
+            Err(e) => Err(e).context(IoOp::Read, &generation_path),
+        }
+    }
+
+    /// Sets the value at the given path only if the store is still at the `expected`
+    /// generation, so a read-modify-write across processes doesn't need to hold the lock
+    /// while the new value is computed.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("set-if-generation-app").unwrap();
+    /// store.set("counter", 1).unwrap();
+    ///
+    /// let (counter, metadata) = store.get_with_metadata("counter").unwrap();
+    /// let counter = counter.unwrap().as_i64().unwrap();
+    /// store.set_if_generation("counter", counter + 1, metadata.generation).unwrap();
+    ///
+    /// // The generation changed with the write above.
+    /// let error = store.set_if_generation("counter", counter + 1, metadata.generation);
+    /// assert!(error.unwrap_err().is_conflict());
+    /// assert_eq!(store.get("counter").unwrap().unwrap(), 2);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store has changed since the expected generation, with [`Error::Conflict`].
+    /// * The store does not exist.
+    /// * It fails to read or write the store file.
+    /// * The path is not a valid dot path.
+    pub fn set_if_generation<T>(&self, path: &str, data: T, expected: u64) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        self.modify(|parsed_json| {
+            if self.generation()? != expected {
+                return Err(Error::Conflict);
+            }
+            parsed_json.dot_set(path, json_data).map_err(Error::from)
+        })
+    }
+
+    /// Returns statistics about the store, for diagnostics or to notice a store growing
+    /// without bound. Includes, environments and interpolation are not resolved.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let store = Store::new("stats-app").unwrap();
+    /// store.set("window.size", [800, 600]).unwrap();
+    /// store.set("theme", "dark").unwrap();
+    ///
+    /// let stats = store.stats().unwrap();
+    /// assert_eq!(stats.keys, 3);
+    /// assert_eq!(stats.depth, 3);
+    /// assert_eq!(stats.size_on_disk, stats.uncompressed_size);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file or its metadata.
+    /// * The store cannot be deserialized.
+    pub fn stats(&self) -> Result<StoreStats> {
+        if !self.store_exists() {
+            return Err(Error::NotFound);
+        }
+        let value = self.get_store_as_parsed_json()?;
+        let store_path = self.get_store_path();
+        let store_len = fs::metadata(&store_path)
+            .context(IoOp::Metadata, &store_path)?
+            .len();
+        let journal_len = fs::metadata(self.get_journal_path())
+            .map(|metadata| metadata.len())
+            .unwrap_or_default();
+        let (keys, depth) = count_keys(&value);
+        #[cfg(feature = "crypto")]
+        let encrypted = self.encryption_key.is_some();
+        #[cfg(not(feature = "crypto"))]
+        let encrypted = false;
+        #[cfg(feature = "electron")]
+        let encrypted = match &self.electron {
+            Some(_) => self.has_electron_encryption(),
+            None => encrypted,
+        };
+        #[cfg(feature = "compression")]
+        let compressed = self.compressed;
+        #[cfg(not(feature = "compression"))]
+        let compressed = false;
+        // electron-store's format is never compressed.
+        #[cfg(feature = "electron")]
+        let compressed = compressed && self.electron.is_none();
+        Ok(StoreStats {
+            size_on_disk: store_len + journal_len,
+            uncompressed_size: self.serialize_value(&value)?.len() as u64,
+            keys,
+            depth,
+            modified: self.get_modified()?,
+            encrypted,
+            compressed,
+        })
+    }
+
+    /// Returns the time the store file or its journal was last modified.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file's metadata cannot be read.
+    fn get_modified(&self) -> Result<Option<SystemTime>> {
+        let store_path = self.get_store_path();
+        let store_modified = fs::metadata(&store_path)
+            .context(IoOp::Metadata, &store_path)?
+            .modified()
+            .ok();
+        let journal_modified = fs::metadata(self.get_journal_path())
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        Ok(store_modified.max(journal_modified))
+    }
+}
+
+/// Returns the number of keys in every object within `value` and how deeply it is nested.
+fn count_keys(value: &Value) -> (usize, usize) {
+    let children: Box<dyn Iterator<Item = &Value>> = match value {
+        Value::Object(map) => Box::new(map.values()),
+        Value::Array(array) => Box::new(array.iter()),
+        _ => return (0, 0),
+    };
+    let own_keys = value.as_object().map_or(0, |map| map.len());
+    children.fold((own_keys, 1), |(keys, depth), child| {
+        let (child_keys, child_depth) = count_keys(child);
+        (keys + child_keys, depth.max(child_depth + 1))
+    })
+}
@@ -0,0 +1,70 @@
+use crate::Store;
+use std::sync::Arc;
+
+/// Receives counters of a store's activity, for monitoring configuration churn in production
+/// without depending on a specific metrics crate.
+///
+/// Every method does nothing by default, so only the counters of interest need implementing.
+/// Register an implementation with [`Store::set_metrics`].
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{Metrics, Store};
+/// # use std::sync::{atomic::{AtomicU64, Ordering}, Arc};
+/// #[derive(Default)]
+/// struct BytesWritten(AtomicU64);
+///
+/// impl Metrics for BytesWritten {
+///     fn record_bytes_written(&self, bytes: usize) {
+///         self.0.fetch_add(bytes as u64, Ordering::Relaxed);
+///     }
+/// }
+///
+/// let metrics = Arc::new(BytesWritten::default());
+/// let mut store = Store::new("metrics-app").unwrap();
+/// store.set_metrics(metrics.clone());
+/// store.set("a", 1).unwrap();
+/// // `{}` when the store is created, then `{"a":1}`.
+/// assert_eq!(metrics.0.load(Ordering::Relaxed), 2 + 7);
+/// # store.delete_store_dir().unwrap();
+/// ```
+pub trait Metrics: Send + Sync + 'static {
+    /// Called whenever the contents of the store are loaded, by reads and mutations alike.
+    fn record_get(&self) {}
+
+    /// Called whenever the contents of the store are loaded from memory rather than the
+    /// store file, such as deferred changes which are not flushed yet.
+    fn record_cache_hit(&self) {}
+
+    /// Called whenever a change to the store is written or deferred.
+    fn record_set(&self) {}
+
+    /// Called with the size of the store file whenever it is read.
+    fn record_bytes_read(&self, _bytes: usize) {}
+
+    /// Called with the size of the store file whenever it is written.
+    fn record_bytes_written(&self, _bytes: usize) {}
+}
+
+impl Store {
+    /// Sets the metrics receiving counters of the store's activity.
+    pub fn set_metrics(&mut self, metrics: Arc<dyn Metrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Stops recording metrics.
+    pub fn clear_metrics(&mut self) {
+        self.metrics = None;
+    }
+
+    /// Passes the store's metrics to `f`, if there are any.
+    pub(crate) fn record_metrics<F>(&self, f: F)
+    where
+        F: FnOnce(&dyn Metrics),
+    {
+        if let Some(metrics) = &self.metrics {
+            f(metrics.as_ref());
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{error::IoContext, Decoded, IoOp, Result, Store};
+use memmap2::Mmap;
+use std::{borrow::Cow, fs::File};
+
+impl Store {
+    /// Sets whether the store file is read through a memory map rather than copied into
+    /// memory, which saves a copy of large store files on every read. It is disabled by
+    /// default.
+    ///
+    /// Only plain and compressed store files are mapped. Stores with checksums,
+    /// signatures, encryption or a pending journal are read into memory as usual.
+    ///
+    /// **NOTE:** bland replaces the store file rather than writing to it, so its own
+    /// writes never change a mapped file. A program truncating the store file in place
+    /// while it is being read may crash the process with `SIGBUS`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("mmap-app").unwrap();
+    /// store.set_mmap(true);
+    /// store.set("cache.model", "large").unwrap();
+    /// assert_eq!(store.get("cache.model").unwrap().unwrap(), "large");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_mmap(&mut self, mmap: bool) {
+        self.mmap = mmap;
+    }
+
+    pub fn get_mmap(&self) -> bool {
+        self.mmap
+    }
+
+    /// Maps the store file, or returns `None` if it is read into memory instead because
+    /// mapping is disabled or the file has to be verified, decrypted or combined with a
+    /// journal before it is parsed.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store file cannot be opened or mapped.
+    pub(crate) fn map_store_file(&self) -> Result<Option<Mmap>> {
+        if !self.mmap || self.checksum || self.get_journal_path().exists() {
+            return Ok(None);
+        }
+        #[cfg(feature = "crypto")]
+        if self.get_whole_store_key().is_some() || self.signing_key.is_some() {
+            return Ok(None);
+        }
+        #[cfg(feature = "electron")]
+        if self.electron.is_some() {
+            return Ok(None);
+        }
+        #[cfg(feature = "test-util")]
+        if self.faults.is_some() {
+            return Ok(None);
+        }
+        let store_path = self.get_store_path();
+        let file = File::open(&store_path).context(IoOp::Read, &store_path)?;
+        // SAFETY: the store file is only ever replaced by renaming another file over it, so
+        // the mapped file is not modified by bland while the map is alive.
+        let map = unsafe { Mmap::map(&file) }.context(IoOp::Read, &store_path)?;
+        self.record_metrics(|metrics| metrics.record_bytes_read(map.len()));
+        Ok(Some(map))
+    }
+
+    /// Returns the contents of a mapped store file, decompressed as they are parsed if the
+    /// store is compressed.
+    pub(crate) fn decode_mapped<'a>(&self, map: &'a [u8]) -> Decoded<'a> {
+        #[cfg(feature = "compression")]
+        if self.compressed {
+            return Decoded::Compressed(Cow::Borrowed(map), self.compression_algorithm);
+        }
+        Decoded::Plain(Cow::Borrowed(map))
+    }
+}
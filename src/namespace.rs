@@ -0,0 +1,221 @@
+use crate::{
+    path::{join_path, take_path},
+    Error, Result, ScopedStore, Store,
+};
+use json_dotpath::DotPaths;
+use serde::Serialize;
+use serde_json::Value;
+
+/// The key under which every namespace is kept.
+const NAMESPACE_ROOT: &str = "plugins";
+
+/// Validates the contents of a namespace, returning the reason it is invalid.
+pub type NamespaceSchema = Box<dyn Fn(&Value) -> std::result::Result<(), String> + Send + Sync>;
+
+/// The limits of a namespace registered with [`Store::register_namespace`].
+#[derive(Default)]
+pub struct NamespaceOptions {
+    /// The maximum size of the namespace's contents in bytes of serialized JSON.
+    pub max_size: Option<usize>,
+    /// Validates the namespace's contents after every write to it.
+    pub schema: Option<NamespaceSchema>,
+}
+
+/// A plugin's namespace within the store, returned by [`Store::namespace`].
+///
+/// A namespace is a [`ScopedStore`] under `plugins.<name>` whose writes are checked
+/// against the quota and schema it was registered with.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{ErrorKind, NamespaceOptions, Store};
+/// let mut store = Store::new("namespace-app").unwrap();
+/// store.register_namespace(
+///     "spellcheck",
+///     NamespaceOptions {
+///         max_size: Some(64),
+///         schema: Some(Box::new(|value| match value["language"].is_string() {
+///             true => Ok(()),
+///             false => Err("language must be a string".to_string()),
+///         })),
+///     },
+/// );
+///
+/// let spellcheck = store.namespace("spellcheck");
+/// spellcheck.set("language", "en-GB").unwrap();
+/// let error = spellcheck.set("language", 1).unwrap_err();
+/// assert_eq!(error.kind(), ErrorKind::InvalidValue);
+/// assert!(spellcheck.set("words", ["a"; 32]).unwrap_err().is_quota_exceeded());
+/// assert_eq!(store.get("plugins.spellcheck.language").unwrap().unwrap(), "en-GB");
+///
+/// store.namespace("spellcheck").reset().unwrap();
+/// assert!(store.get("plugins.spellcheck").unwrap().is_none());
+/// # store.delete_store_dir().unwrap();
+/// ```
+pub struct Namespace<'s> {
+    name: String,
+    scoped: ScopedStore<'s>,
+    options: Option<&'s NamespaceOptions>,
+}
+
+impl<'s> Namespace<'s> {
+    /// Get the name of the namespace.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the value at the given path within the namespace, see [`Store::get`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn get(&self, path: &str) -> Result<Option<Value>> {
+        self.scoped.get(path)
+    }
+
+    /// Returns the keys at the given path within the namespace, see [`Store::keys`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn keys(&self, path: &str) -> Result<Option<Vec<String>>> {
+        self.scoped.keys(path)
+    }
+
+    /// Returns the contents of the namespace, an empty object if nothing was set in it.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    pub fn to_value(&self) -> Result<Value> {
+        Ok(self
+            .scoped
+            .get("")?
+            .unwrap_or_else(|| Value::Object(Default::default())))
+    }
+
+    /// Sets the given data at the given path within the namespace, see [`Store::set`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The namespace would exceed its quota, with [`Error::QuotaExceeded`].
+    /// * The namespace would not match its schema, with [`Error::InvalidValue`].
+    /// * The store fails to be created or written to.
+    /// * The data cannot be serialized.
+    /// * `path` is not a valid dot path.
+    pub fn set<T>(&self, path: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let json_data = serde_json::to_value(&data)?;
+        let path = self.scoped.scope_path(path);
+        self.modify(|parsed_json| parsed_json.dot_set(&path, json_data).map_err(Error::from))
+    }
+
+    /// Deletes the given path within the namespace, returning the deleted value,
+    /// see [`Store::delete`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The namespace would not match its schema, with [`Error::InvalidValue`].
+    /// * The store does not exist.
+    /// * It fails to read or write the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn delete(&self, path: &str) -> Result<Option<Value>> {
+        if !self.scoped.store().store_exists() {
+            return Err(Error::NotFound);
+        }
+        let path = self.scoped.scope_path(path);
+        self.modify(|parsed_json| take_path(parsed_json, &path))
+    }
+
+    /// Removes every value in the namespace, regardless of its schema.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read or written to.
+    pub fn reset(&self) -> Result<()> {
+        if !self.scoped.store().store_exists() {
+            return Ok(());
+        }
+        self.scoped.delete("").map(|_| ())
+    }
+
+    /// Modifies the contents of the store, checking the namespace against its quota and
+    /// schema before anything is written.
+    fn modify<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Value) -> Result<R>,
+    {
+        let prefix = self.scoped.get_prefix();
+        self.scoped.store().modify(|parsed_json| {
+            let result = f(parsed_json)?;
+            let options = match self.options {
+                Some(options) => options,
+                None => return Ok(result),
+            };
+            let value = parsed_json
+                .dot_get::<Value>(prefix)?
+                .unwrap_or_else(|| Value::Object(Default::default()));
+            if let Some(limit) = options.max_size {
+                let size = serde_json::to_vec(&value)?.len();
+                if size > limit {
+                    return Err(Error::QuotaExceeded {
+                        path: prefix.to_string(),
+                        size,
+                        limit,
+                    });
+                }
+            }
+            if let Some(schema) = &options.schema {
+                schema(&value).map_err(|reason| Error::InvalidValue {
+                    path: prefix.to_string(),
+                    reason,
+                })?;
+            }
+            Ok(result)
+        })
+    }
+}
+
+impl Store {
+    /// Grants a plugin a namespace with the given quota and schema, replacing any options
+    /// it was registered with before. Namespaces which are not registered have no limits.
+    pub fn register_namespace(&mut self, name: &str, options: NamespaceOptions) {
+        self.namespaces.insert(name.to_string(), options);
+    }
+
+    /// Removes a namespace from the registry, keeping its contents.
+    pub fn unregister_namespace(&mut self, name: &str) {
+        self.namespaces.remove(name);
+    }
+
+    /// Returns the names of the registered namespaces, sorted alphabetically.
+    pub fn namespaces(&self) -> Vec<&str> {
+        self.namespaces.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the namespace with the given name, see [`Namespace`].
+    pub fn namespace(&self, name: &str) -> Namespace<'_> {
+        Namespace {
+            name: name.to_string(),
+            scoped: self.scoped(&join_path(NAMESPACE_ROOT, name)),
+            options: self.namespaces.get(name),
+        }
+    }
+}
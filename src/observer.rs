@@ -0,0 +1,187 @@
+use crate::{path::join_path, Store};
+use json_dotpath::DotPaths;
+use serde_json::Value;
+use std::sync::mpsc::{self, Receiver};
+
+/// What caused a [`ChangeEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeSource {
+    /// A mutation through this `Store`.
+    Internal,
+    /// A change to the store file by another `Store` or process, detected when the store
+    /// was next read.
+    External,
+}
+
+/// A change to a value in the store, sent to the receivers returned by [`Store::subscribe`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ChangeEvent {
+    /// The dot path of the changed value, with dots in keys escaped.
+    pub path: String,
+    /// The value before the change, `None` if it was added.
+    pub old: Option<Value>,
+    /// The value after the change, `None` if it was deleted.
+    pub new: Option<Value>,
+    /// What caused the change.
+    pub source: ChangeSource,
+}
+
+/// A callback registered with [`Store::on_change`], receiving the old and new values.
+pub(crate) type ChangeCallback = Box<dyn Fn(Option<&Value>, Option<&Value>) + Send + Sync>;
+
+impl Store {
+    /// Registers a callback that is called after every successful mutation of the store
+    /// that changes the value at `prefix`, with the value before and after the change.
+    ///
+    /// An empty prefix observes the whole store.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use std::sync::{Arc, Mutex};
+    /// let mut store = Store::new("on-change-app").unwrap();
+    /// let seen = Arc::new(Mutex::new(Vec::new()));
+    /// let seen_by_callback = seen.clone();
+    /// store.on_change("window", move |old, new| {
+    ///     seen_by_callback.lock().unwrap().push((old.cloned(), new.cloned()));
+    /// });
+    /// store.set("window.width", 800).unwrap();
+    /// store.set("theme", "dark").unwrap();
+    ///
+    /// let seen = seen.lock().unwrap();
+    /// assert_eq!(seen.len(), 1);
+    /// assert_eq!(seen[0].0, None);
+    /// assert_eq!(seen[0].1, Some(serde_json::json!({ "width": 800 })));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn on_change<F>(&mut self, prefix: &str, callback: F)
+    where
+        F: Fn(Option<&Value>, Option<&Value>) + Send + Sync + 'static,
+    {
+        self.observers.push((prefix.to_owned(), Box::new(callback)));
+    }
+
+    /// Removes every callback registered with [`Store::on_change`].
+    pub fn clear_observers(&mut self) {
+        self.observers.clear();
+    }
+
+    /// Returns a receiver of an event for every changed value in the store, whether it was
+    /// changed through this `Store` or by another process.
+    ///
+    /// There is no file watcher, so external changes are reported when the store is next
+    /// read, for example by [`get`](Store::get) or [`reload`](Store::reload). Receivers
+    /// which are dropped are unsubscribed on the next change.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{ChangeSource, Store};
+    /// let store = Store::new("subscribe-app").unwrap();
+    /// let events = store.subscribe();
+    /// store.set("theme", "dark").unwrap();
+    ///
+    /// let event = events.try_recv().unwrap();
+    /// assert_eq!(event.path, "theme");
+    /// assert_eq!(event.old, None);
+    /// assert_eq!(event.new.unwrap(), "dark");
+    /// assert_eq!(event.source, ChangeSource::Internal);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn subscribe(&self) -> Receiver<ChangeEvent> {
+        let (sender, receiver) = mpsc::channel();
+        let baseline = match self.store_exists() {
+            true => self.get_store_as_parsed_json().ok(),
+            false => Some(Value::Object(Default::default())),
+        };
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        if subscribers.is_empty() {
+            *self.last_seen.lock().unwrap_or_else(|e| e.into_inner()) = baseline;
+        }
+        subscribers.push(sender);
+        receiver
+    }
+
+    /// Returns a boolean indicating whether any receivers were returned by
+    /// [`Store::subscribe`].
+    pub(crate) fn has_subscribers(&self) -> bool {
+        !self
+            .subscribers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .is_empty()
+    }
+
+    /// Sends an event for every difference between the contents last seen by the
+    /// subscribers and `value`, which becomes the contents last seen. Without a previous
+    /// version nothing is sent.
+    pub(crate) fn publish_changes(&self, value: &Value, source: ChangeSource) {
+        let mut subscribers = self.subscribers.lock().unwrap_or_else(|e| e.into_inner());
+        if subscribers.is_empty() {
+            return;
+        }
+        let mut last_seen = self.last_seen.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(old) = last_seen.as_ref() {
+            let mut events = Vec::new();
+            diff_values(
+                String::new(),
+                Some(old),
+                Some(value),
+                &mut |path, old, new| {
+                    events.push(ChangeEvent {
+                        path,
+                        old: old.cloned(),
+                        new: new.cloned(),
+                        source,
+                    })
+                },
+            );
+            for event in events {
+                subscribers.retain(|subscriber| subscriber.send(event.clone()).is_ok());
+            }
+        }
+        *last_seen = Some(value.clone());
+    }
+
+    /// Calls the observers whose prefix holds a different value in `old` and `new`.
+    pub(crate) fn notify_observers(&self, old: &Value, new: &Value) {
+        for (prefix, callback) in &self.observers {
+            let old_value = value_at(old, prefix);
+            let new_value = value_at(new, prefix);
+            if old_value != new_value {
+                callback(old_value.as_ref(), new_value.as_ref());
+            }
+        }
+    }
+}
+
+/// Returns the value at the given path, or the whole value if the path is empty.
+fn value_at(value: &Value, path: &str) -> Option<Value> {
+    match path.is_empty() {
+        true => Some(value.clone()),
+        false => value.dot_get(path).ok().flatten(),
+    }
+}
+
+/// Calls `f` with the dot path and the old and new values of every difference between two
+/// values, descending into objects present on both sides. Arrays and other values are
+/// compared as a whole.
+pub(crate) fn diff_values<F>(path: String, old: Option<&Value>, new: Option<&Value>, f: &mut F)
+where
+    F: FnMut(String, Option<&Value>, Option<&Value>),
+{
+    match (old, new) {
+        (Some(Value::Object(old)), Some(Value::Object(new))) => {
+            for key in old
+                .keys()
+                .chain(new.keys().filter(|key| !old.contains_key(*key)))
+            {
+                let key_path = join_path(&path, key);
+                diff_values(key_path, old.get(key), new.get(key), f);
+            }
+        }
+        (old, new) if old != new => f(path, old, new),
+        _ => {}
+    }
+}
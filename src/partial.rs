@@ -0,0 +1,120 @@
+use crate::{path::split_first, Error, Interpolation, Result, Store};
+use json_dotpath::DotPaths;
+use serde_json::{value::RawValue, Value};
+use std::collections::HashMap;
+
+impl Store {
+    /// Returns a boolean indicating whether [`get`](Store::get) can read the value at a path
+    /// without parsing the whole store, because nothing needs its entire contents.
+    pub(crate) fn reads_partially(&self) -> bool {
+        #[cfg(feature = "crypto")]
+        if !self.encrypted_paths.is_empty() {
+            return false;
+        }
+        self.cache_policy.is_none()
+            && !self.includes
+            && self.environment.is_none()
+            && self.interpolation == Interpolation::Off
+            && !self.is_dirty()
+            && !self.has_subscribers()
+    }
+
+    /// Returns the value at the dot path like [`get`](Store::get), parsing only the objects
+    /// leading to it and the value itself. Every other value in the store file is checked
+    /// to be valid JSON but is not deserialized.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be decoded or deserialized.
+    /// * `path` is not a valid dot path.
+    pub(crate) fn get_partial(&self, path: &str) -> Result<Option<Value>> {
+        self.record_metrics(|metrics| metrics.record_get());
+        #[cfg(feature = "mmap")]
+        if let Some(map) = self.map_store_file().map_err(crate::missing_store)? {
+            return self.get_raw(&self.decode_mapped(&map).into_bytes()?, path);
+        }
+        let store_data = self.read_store_file().map_err(crate::missing_store)?;
+        // Operations in the journal may touch any part of the store.
+        if let Some(journal) = self.read_journal_file()? {
+            let parsed_json = self.parse_journaled(store_data, Some(&journal))?;
+            return Ok(parsed_json.dot_get::<Value>(path)?);
+        }
+        let json = self
+            .decode(self.verify_store_data(store_data)?)?
+            .into_bytes()?;
+        self.get_raw(&json, path)
+    }
+
+    /// Returns the value at the dot path within the decoded contents of the store file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be deserialized or `path` is not a valid dot path.
+    fn get_raw(&self, json: &[u8], path: &str) -> Result<Option<Value>> {
+        self.get_raw_in(json, path, true)
+    }
+
+    /// Returns the value at the dot path within JSON, reading the shard a value refers to if
+    /// the JSON is the root of the store file.
+    #[cfg_attr(not(feature = "sharding"), allow(unused_variables))]
+    fn get_raw_in(&self, json: &[u8], path: &str, root: bool) -> Result<Option<Value>> {
+        let corrupt = |source| Error::Corrupt {
+            config: self.get_store_file_name(),
+            source,
+        };
+        let mut raw: &RawValue = match serde_json::from_slice(json) {
+            Ok(raw) => raw,
+            // Files which are not plain JSON are parsed whole.
+            #[cfg(feature = "json5")]
+            Err(e) if e.is_syntax() => {
+                let value = self.parse_decoded(crate::Decoded::Plain(json.into()))?;
+                return Ok(value.dot_get::<Value>(path)?);
+            }
+            Err(e) => return Err(corrupt(e)),
+        };
+        let mut path = path;
+        #[cfg(feature = "sharding")]
+        let mut path_is_root = root;
+        loop {
+            let (key, rest) = split_first(path);
+            // Arrays, scalars and invalid keys are left to `dot_get`.
+            if key.is_empty() || !raw.get().starts_with('{') {
+                break;
+            }
+            let mut object: HashMap<String, &RawValue> =
+                serde_json::from_str(raw.get()).map_err(corrupt)?;
+            raw = match object.remove(&key) {
+                Some(child) if child.get() != "null" => child,
+                _ => return Ok(None),
+            };
+            #[cfg(feature = "sharding")]
+            if path_is_root {
+                if let Some(hash) = crate::shard::raw_shard_reference(raw) {
+                    let shard = self.read_shard(&hash)?;
+                    return match rest {
+                        Some(rest) => self.get_raw_in(&shard, rest, false),
+                        None => Ok(Some(self.parse_json(&shard)?)),
+                    };
+                }
+            }
+            #[cfg(feature = "sharding")]
+            {
+                path_is_root = false;
+            }
+            match rest {
+                Some(rest) => path = rest,
+                None => return Ok(Some(serde_json::from_str(raw.get()).map_err(corrupt)?)),
+            }
+        }
+        let value: Value = serde_json::from_str(raw.get()).map_err(corrupt)?;
+        #[cfg(feature = "sharding")]
+        let value = match path_is_root {
+            true => self.load_shards(value)?,
+            false => value,
+        };
+        Ok(value.dot_get::<Value>(path)?)
+    }
+}
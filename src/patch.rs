@@ -0,0 +1,187 @@
+use crate::{path::remove_key, pointer, Error, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// A JSON Patch operation as defined by [RFC 6902](https://www.rfc-editor.org/rfc/rfc6902),
+/// with paths given as JSON Pointers.
+///
+/// Operations serialize to and from their standard JSON representation,
+/// e.g. `{ "op": "add", "path": "/a/b", "value": 42 }`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum PatchOp {
+    /// Adds a value to an object or inserts it into an array.
+    Add { path: String, value: Value },
+    /// Removes the value at the path.
+    Remove { path: String },
+    /// Replaces the value at the path, which must exist.
+    Replace { path: String, value: Value },
+    /// Removes the value at `from` and adds it at the path.
+    Move { from: String, path: String },
+    /// Copies the value at `from` to the path.
+    Copy { from: String, path: String },
+    /// Checks that the value at the path equals the given value.
+    Test { path: String, value: Value },
+}
+
+/// Applies a JSON Patch operation to `target`.
+///
+/// # Errors
+///
+/// Errors if a path is invalid or does not exist, or a `test` operation fails.
+pub(crate) fn apply_op(target: &mut Value, op: &PatchOp) -> Result<()> {
+    match op {
+        PatchOp::Add { path, value } => pointer::add(target, path, value.clone()),
+        PatchOp::Remove { path } => pointer::remove(target, path).map(|_| ()),
+        PatchOp::Replace { path, value } => match target.pointer_mut(path) {
+            Some(replaced) => {
+                *replaced = value.clone();
+                Ok(())
+            }
+            None => Err(Error::InvalidPointer(path.clone())),
+        },
+        PatchOp::Move { from, path } => {
+            if path.starts_with(&format!("{}/", from)) {
+                return Err(Error::InvalidPointer(path.clone()));
+            }
+            let value = pointer::remove(target, from)?;
+            pointer::add(target, path, value)
+        }
+        PatchOp::Copy { from, path } => {
+            let value = pointer::get(target, from)?
+                .cloned()
+                .ok_or_else(|| Error::InvalidPointer(from.clone()))?;
+            pointer::add(target, path, value)
+        }
+        PatchOp::Test { path, value } => match pointer::get(target, path)? {
+            Some(actual) if actual == value => Ok(()),
+            _ => Err(Error::TestFailed(path.clone())),
+        },
+    }
+}
+
+/// Collects the JSON Patch operations that turn `old` into `new` into `ops`,
+/// descending into objects and arrays present on both sides.
+pub(crate) fn diff(path: &str, old: &Value, new: &Value, ops: &mut Vec<PatchOp>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let key_path = format!("{}/{}", path, pointer::escape_token(key));
+                match new.get(key) {
+                    Some(new_value) => diff(&key_path, old_value, new_value, ops),
+                    None => ops.push(PatchOp::Remove { path: key_path }),
+                }
+            }
+            for (key, new_value) in new.iter().filter(|(key, _)| !old.contains_key(*key)) {
+                ops.push(PatchOp::Add {
+                    path: format!("{}/{}", path, pointer::escape_token(key)),
+                    value: new_value.clone(),
+                });
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for (i, (old_value, new_value)) in old.iter().zip(new).enumerate() {
+                diff(&format!("{}/{}", path, i), old_value, new_value, ops);
+            }
+            // Remove from the end so the indices of the remaining elements stay valid.
+            for i in (new.len()..old.len()).rev() {
+                ops.push(PatchOp::Remove {
+                    path: format!("{}/{}", path, i),
+                });
+            }
+            for value in new.iter().skip(old.len()) {
+                ops.push(PatchOp::Add {
+                    path: format!("{}/-", path),
+                    value: value.clone(),
+                });
+            }
+        }
+        (old, new) if old != new => ops.push(PatchOp::Replace {
+            path: path.to_owned(),
+            value: new.clone(),
+        }),
+        _ => {}
+    }
+}
+
+/// Merges `overrides` into `target`, recursing into objects present in both and
+/// replacing any other value. Unlike [`merge_patch`], `null` is kept as a value.
+pub(crate) fn merge_values(target: &mut Value, overrides: Value) {
+    match (target, overrides) {
+        (Value::Object(target), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match target.get_mut(&key) {
+                    Some(existing) => merge_values(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, overrides) => *target = overrides,
+    }
+}
+
+/// Applies the changes turning `old` into `new` to `target`, recursing into objects present
+/// in both. Members missing from `new` are removed from `target` if it has them, and any
+/// other changed value replaces the one in `target`.
+pub(crate) fn apply_changes(target: &mut Value, old: &Value, new: &Value) {
+    let (old, new) = match (old, new) {
+        (Value::Object(old), Value::Object(new)) => (old, new),
+        (old, new) => {
+            if old != new {
+                *target = new.clone();
+            }
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, new_value) in new {
+            match old.get(key) {
+                Some(old_value) if old_value == new_value => {}
+                Some(old_value) if old_value.is_object() && new_value.is_object() => {
+                    let entry = target
+                        .entry(key.clone())
+                        .or_insert_with(|| Value::Object(Default::default()));
+                    apply_changes(entry, old_value, new_value);
+                }
+                _ => {
+                    target.insert(key.clone(), new_value.clone());
+                }
+            }
+        }
+        for key in old.keys().filter(|key| !new.contains_key(*key)) {
+            remove_key(target, key);
+        }
+    }
+}
+
+/// Applies a JSON Merge Patch to `target` following RFC 7386.
+///
+/// Objects in the patch are merged into the target recursively, `null` members delete the
+/// corresponding member of the target and any other value replaces the target.
+pub(crate) fn merge_patch(target: &mut Value, patch: Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *target = patch;
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            match value {
+                Value::Null => {
+                    remove_key(target, &key);
+                }
+                value => merge_patch(target.entry(key).or_insert(Value::Null), value),
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+use crate::Result;
+use json_dotpath::DotPaths;
+use serde_json::{Map, Value};
+use std::{fmt, mem};
+
+/// A builder for dot paths whose keys may contain dots or backslashes.
+///
+/// Dots within a key are escaped as `\.` and backslashes as `\\`, the convention every
+/// dot path method of [`Store`](crate::Store) understands.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{DotPath, Store};
+/// let store = Store::new("dot-path-app").unwrap();
+/// let path = DotPath::new().key("hosts").key("example.com").key("port");
+/// assert_eq!(path.as_str(), r"hosts.example\.com.port");
+/// store.set(path.as_str(), 443).unwrap();
+/// assert_eq!(store.get(r"hosts.example\.com.port").unwrap().unwrap(), 443);
+/// assert_eq!(store.keys("hosts").unwrap().unwrap(), ["example.com"]);
+/// # store.delete_store_dir().unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DotPath {
+    path: String,
+}
+
+impl DotPath {
+    pub fn new() -> Self {
+        DotPath::default()
+    }
+
+    /// Appends a key, escaping any dots and backslashes in it.
+    pub fn key(mut self, key: &str) -> Self {
+        self.path = join_path(&self.path, key);
+        self
+    }
+
+    /// Appends an array index.
+    pub fn index(self, index: usize) -> Self {
+        self.key(&index.to_string())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.path
+    }
+}
+
+impl fmt::Display for DotPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.path)
+    }
+}
+
+impl From<DotPath> for String {
+    fn from(path: DotPath) -> String {
+        path.path
+    }
+}
+
+/// Escapes dots and backslashes in a key so it is treated as a single dot path segment.
+///
+/// # Example
+///
+/// ```rust
+/// assert_eq!(bland::escape_key("example.com"), r"example\.com");
+/// ```
+pub fn escape_key(key: &str) -> String {
+    key.replace('\\', "\\\\").replace('.', "\\.")
+}
+
+/// Appends a key to a dot path, escaping dots and backslashes in the key.
+pub(crate) fn join_path(path: &str, key: &str) -> String {
+    let key = escape_key(key);
+    match path.is_empty() {
+        true => key,
+        false => format!("{}.{}", path, key),
+    }
+}
+
+/// Splits the first key off a dot path, removing escapes, along with the rest of the path
+/// if there is any.
+pub(crate) fn split_first(path: &str) -> (String, Option<&str>) {
+    let mut key = String::new();
+    let mut escaped = false;
+    for (i, c) in path.char_indices() {
+        match c {
+            _ if escaped => {
+                key.push(c);
+                escaped = false;
+            }
+            '\\' => escaped = true,
+            '.' => return (key, Some(&path[i + 1..])),
+            c => key.push(c),
+        }
+    }
+    (key, None)
+}
+
+/// Removes the value at the dot path like `dot_take`, returning it if it was not `null`.
+/// Keys of the objects along the path keep their order when `preserve_order` is enabled.
+///
+/// # Errors
+///
+/// Errors if `path` is not a valid dot path.
+pub(crate) fn take_path(value: &mut Value, path: &str) -> Result<Option<Value>> {
+    let (key, rest) = split_first(path);
+    match (value, rest) {
+        (Value::Object(map), None) if !key.is_empty() => {
+            Ok(remove_key(map, &key).filter(|value| !value.is_null()))
+        }
+        (Value::Object(map), Some(rest)) if !key.is_empty() => match map.get_mut(&key) {
+            Some(child) => take_path(child, rest),
+            None => Ok(None),
+        },
+        (value, _) => Ok(value.dot_take(path)?),
+    }
+}
+
+/// Removes a key from an object, shifting the keys after it forward rather than moving the
+/// last key into its place when `preserve_order` is enabled.
+pub(crate) fn remove_key(map: &mut Map<String, Value>, key: &str) -> Option<Value> {
+    #[cfg(feature = "preserve_order")]
+    return map.shift_remove(key);
+    #[cfg(not(feature = "preserve_order"))]
+    return map.remove(key);
+}
+
+/// Converts a name to the form used in environment variables, upper case with every
+/// character other than letters and digits replaced by `_`.
+pub(crate) fn env_name(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c.to_ascii_uppercase(),
+            false => '_',
+        })
+        .collect()
+}
+
+/// A segment of a dot path pattern.
+#[derive(Debug, PartialEq)]
+pub(crate) enum Segment {
+    /// A key of an object or index of an array.
+    Key(String),
+    /// A `*` matching every key of an object or index of an array.
+    Wildcard,
+}
+
+/// Splits a dot path pattern into its segments, removing escapes. An escaped `\*` is a key.
+pub(crate) fn split_pattern(pattern: &str) -> Vec<Segment> {
+    let segment = |key: String, escaped: bool| match (key.as_str(), escaped) {
+        ("*", false) => Segment::Wildcard,
+        _ => Segment::Key(key),
+    };
+    let mut segments = Vec::new();
+    let mut key = String::new();
+    let mut escaped = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                escaped = true;
+                key.extend(chars.next());
+            }
+            '.' => segments.push(segment(mem::take(&mut key), mem::take(&mut escaped))),
+            c => key.push(c),
+        }
+    }
+    segments.push(segment(key, escaped));
+    segments
+}
+
+/// Collects every value below `value` matching the pattern segments into `matches`,
+/// along with its concrete dot path.
+pub(crate) fn select(
+    path: String,
+    value: &Value,
+    segments: &[Segment],
+    matches: &mut Vec<(String, Value)>,
+) {
+    let (segment, rest) = match segments.split_first() {
+        Some(split) => split,
+        None => {
+            matches.push((path, value.clone()));
+            return;
+        }
+    };
+    match (segment, value) {
+        (Segment::Wildcard, Value::Object(map)) => {
+            for (key, child) in map {
+                select(join_path(&path, key), child, rest, matches);
+            }
+        }
+        (Segment::Wildcard, Value::Array(array)) => {
+            for (i, child) in array.iter().enumerate() {
+                select(join_path(&path, &i.to_string()), child, rest, matches);
+            }
+        }
+        (Segment::Key(key), Value::Object(map)) => {
+            if let Some(child) = map.get(key) {
+                select(join_path(&path, key), child, rest, matches);
+            }
+        }
+        (Segment::Key(key), Value::Array(array)) => {
+            if let Some(child) = key.parse::<usize>().ok().and_then(|i| array.get(i)) {
+                select(join_path(&path, key), child, rest, matches);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Collects every leaf below `value` as a dot path and value pair into `leaves`.
+///
+/// Objects and arrays are descended into, empty ones are leaves themselves.
+pub(crate) fn flatten(path: String, value: Value, leaves: &mut Vec<(String, Value)>) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (key, value) in map {
+                flatten(join_path(&path, &key), value, leaves);
+            }
+        }
+        Value::Array(array) if !array.is_empty() => {
+            for (i, value) in array.into_iter().enumerate() {
+                flatten(join_path(&path, &i.to_string()), value, leaves);
+            }
+        }
+        value => leaves.push((path, value)),
+    }
+}
@@ -0,0 +1,244 @@
+use crate::Store;
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt};
+#[cfg(windows)]
+use std::os::windows::fs::OpenOptionsExt;
+use std::{
+    fs::{DirBuilder, OpenOptions},
+    io,
+    path::Path,
+};
+
+/// The permissions given to the directories and files the store creates.
+///
+/// On Unix permissions are modes, which are still masked by the process's umask. On Windows
+/// [`Private`](Permissions::Private) directories and files are given an access control list
+/// which only lets their owner access them, while other permissions inherit the access
+/// control list of their parent. On other platforms the permissions are not applied.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Permissions {
+    /// Only the owner can access the store, `0700` for directories and `0600` for files.
+    #[default]
+    Private,
+    /// The platform's defaults, which usually lets other users read the store.
+    Inherit,
+    /// The given Unix modes for directories and files, the platform's defaults on Windows.
+    Mode {
+        /// The mode of created directories.
+        dir: u32,
+        /// The mode of created files.
+        file: u32,
+    },
+}
+
+impl Permissions {
+    /// Returns the modes of created directories and files, or `None` to use the defaults.
+    #[cfg_attr(not(unix), allow(dead_code))]
+    fn modes(self) -> Option<(u32, u32)> {
+        match self {
+            Permissions::Private => Some((0o700, 0o600)),
+            Permissions::Inherit => None,
+            Permissions::Mode { dir, file } => Some((dir, file)),
+        }
+    }
+}
+
+/// Creates directories with the store's permissions, like [`DirBuilder`].
+pub(crate) struct StoreDirBuilder {
+    permissions: Permissions,
+    recursive: bool,
+}
+
+impl StoreDirBuilder {
+    /// Sets whether missing parents are created as well, with the same permissions, and an
+    /// existing directory is not an error.
+    pub(crate) fn recursive(&mut self, recursive: bool) -> &mut Self {
+        self.recursive = recursive;
+        self
+    }
+
+    /// Creates the directory at `path`.
+    pub(crate) fn create(&self, path: &Path) -> io::Result<()> {
+        #[cfg(windows)]
+        if self.permissions == Permissions::Private {
+            return self.create_private(path);
+        }
+        let mut builder = DirBuilder::new();
+        builder.recursive(self.recursive);
+        #[cfg(unix)]
+        if let Some((dir, _)) = self.permissions.modes() {
+            builder.mode(dir);
+        }
+        builder.create(path)
+    }
+
+    /// Creates the directory at `path`, and its missing parents when recursive, which only
+    /// the owner can access.
+    #[cfg(windows)]
+    fn create_private(&self, path: &Path) -> io::Result<()> {
+        if self.recursive {
+            if path.is_dir() {
+                return Ok(());
+            }
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                self.create_private(parent)?;
+            }
+        }
+        match windows::create_private_dir(path) {
+            // Another thread or process created the directory first.
+            Err(_) if self.recursive && path.is_dir() => Ok(()),
+            result => result,
+        }
+    }
+}
+
+impl Store {
+    /// Sets the permissions of the directories and files the store creates,
+    /// defaults to [`Permissions::Private`] as the store may hold secrets.
+    ///
+    /// Existing directories keep their permissions, while the store file is given
+    /// the new permissions the next time it is written.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{Permissions, Store};
+    /// let mut store = Store::new("permissions-app").unwrap();
+    /// store.set_permissions(Permissions::Mode { dir: 0o750, file: 0o640 });
+    /// store.set("theme", "dark").unwrap();
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_permissions(&mut self, permissions: Permissions) {
+        self.permissions = permissions;
+    }
+
+    pub fn get_permissions(&self) -> Permissions {
+        self.permissions
+    }
+
+    /// Returns a directory builder which creates directories with the store's permissions.
+    pub(crate) fn dir_builder(&self) -> StoreDirBuilder {
+        StoreDirBuilder {
+            permissions: self.permissions,
+            recursive: false,
+        }
+    }
+
+    /// Returns options which create files with the store's permissions.
+    pub(crate) fn file_options(&self) -> OpenOptions {
+        #[allow(unused_mut)]
+        let mut options = OpenOptions::new();
+        #[cfg(unix)]
+        if let Some((_, file)) = self.permissions.modes() {
+            options.mode(file);
+        }
+        #[cfg(windows)]
+        if self.permissions == Permissions::Private {
+            options.security_attributes(windows::private_attributes());
+        }
+        options
+    }
+}
+
+/// Access control lists for Windows, which are applied when directories and files are
+/// created so they are never accessible to other users.
+#[cfg(windows)]
+mod windows {
+    use std::{
+        ffi::{c_void, OsStr},
+        io, mem,
+        os::windows::ffi::OsStrExt,
+        path::Path,
+        ptr,
+        sync::OnceLock,
+    };
+
+    /// A protected access control list, in the security descriptor definition language,
+    /// which gives the owner full access to a directory or file and the directories and
+    /// files created in it, and no one else any access.
+    const PRIVATE_DACL: &str = "D:P(A;OICI;FA;;;OW)";
+
+    /// The revision of the security descriptor definition language.
+    const SDDL_REVISION_1: u32 = 1;
+
+    /// The `SECURITY_ATTRIBUTES` structure passed when creating directories and files.
+    #[repr(C)]
+    struct SecurityAttributes {
+        length: u32,
+        security_descriptor: *mut c_void,
+        inherit_handle: i32,
+    }
+
+    // SAFETY: the security descriptor is never changed or freed once it has been created.
+    unsafe impl Send for SecurityAttributes {}
+    unsafe impl Sync for SecurityAttributes {}
+
+    #[link(name = "advapi32")]
+    extern "system" {
+        fn ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            string_security_descriptor: *const u16,
+            string_sd_revision: u32,
+            security_descriptor: *mut *mut c_void,
+            security_descriptor_size: *mut u32,
+        ) -> i32;
+    }
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn CreateDirectoryW(
+            path_name: *const u16,
+            security_attributes: *const SecurityAttributes,
+        ) -> i32;
+    }
+
+    /// Returns a pointer to the security attributes which only let the owner access a
+    /// directory or file, created on first use and kept for the lifetime of the process.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the security descriptor cannot be allocated.
+    pub(super) fn private_attributes() -> *mut c_void {
+        static ATTRIBUTES: OnceLock<SecurityAttributes> = OnceLock::new();
+        let attributes = ATTRIBUTES.get_or_init(|| {
+            let dacl = to_wide(OsStr::new(PRIVATE_DACL));
+            let mut security_descriptor = ptr::null_mut();
+            // SAFETY: `dacl` is null terminated and the descriptor is written to a local.
+            let converted = unsafe {
+                ConvertStringSecurityDescriptorToSecurityDescriptorW(
+                    dacl.as_ptr(),
+                    SDDL_REVISION_1,
+                    &mut security_descriptor,
+                    ptr::null_mut(),
+                )
+            };
+            // The access control list is constant, so this only fails when out of memory.
+            assert!(
+                converted != 0,
+                "failed to create the private security descriptor: {}",
+                io::Error::last_os_error()
+            );
+            SecurityAttributes {
+                length: mem::size_of::<SecurityAttributes>() as u32,
+                security_descriptor,
+                inherit_handle: 0,
+            }
+        });
+        attributes as *const SecurityAttributes as *mut c_void
+    }
+
+    /// Creates the directory at `path`, which only the owner can access.
+    pub(super) fn create_private_dir(path: &Path) -> io::Result<()> {
+        let path = to_wide(path.as_os_str());
+        // SAFETY: `path` is null terminated and the attributes live for the whole process.
+        let created = unsafe { CreateDirectoryW(path.as_ptr(), private_attributes().cast()) };
+        match created {
+            0 => Err(io::Error::last_os_error()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the string as a null terminated wide string.
+    fn to_wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(Some(0)).collect()
+    }
+}
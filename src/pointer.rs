@@ -0,0 +1,136 @@
+use crate::{path::remove_key, Error, Result};
+use serde_json::Value;
+
+/// Escapes a key for use as a JSON Pointer reference token following RFC 6901.
+pub(crate) fn escape_token(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Splits a JSON Pointer into the pointer to its parent and its unescaped last reference token,
+/// `None` for the empty pointer which refers to the whole document.
+///
+/// # Errors
+///
+/// Errors if the pointer is not empty and does not start with `/`.
+pub(crate) fn split_pointer(pointer: &str) -> Result<Option<(&str, String)>> {
+    if pointer.is_empty() {
+        return Ok(None);
+    }
+    if !pointer.starts_with('/') {
+        return Err(Error::InvalidPointer(pointer.to_owned()));
+    }
+    let index = pointer.rfind('/').unwrap_or_default();
+    let token = pointer[index + 1..].replace("~1", "/").replace("~0", "~");
+    Ok(Some((&pointer[..index], token)))
+}
+
+/// Returns the value the JSON Pointer refers to.
+///
+/// # Errors
+///
+/// Errors if the pointer is invalid.
+pub(crate) fn get<'v>(value: &'v Value, pointer: &str) -> Result<Option<&'v Value>> {
+    split_pointer(pointer)?;
+    Ok(value.pointer(pointer))
+}
+
+/// Adds a value at the location the JSON Pointer refers to, following the `add` operation of
+/// RFC 6902: object members are inserted or replaced and array elements are inserted,
+/// with `-` appending to the array.
+///
+/// # Errors
+///
+/// Errors if the pointer is invalid, its parent does not exist or the array index is out of bounds.
+pub(crate) fn add(value: &mut Value, pointer: &str, new_value: Value) -> Result<()> {
+    let (parent, token) = match split_pointer(pointer)? {
+        Some(split) => split,
+        None => {
+            *value = new_value;
+            return Ok(());
+        }
+    };
+    match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, new_value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => {
+            let index = match token.as_str() {
+                "-" => array.len(),
+                token => parse_index(token, array.len() + 1, pointer)?,
+            };
+            array.insert(index, new_value);
+            Ok(())
+        }
+        _ => Err(Error::InvalidPointer(pointer.to_owned())),
+    }
+}
+
+/// Sets the value at the location the JSON Pointer refers to, creating missing parent objects.
+/// Array elements are replaced, with `-` or the array's length appending to the array.
+///
+/// # Errors
+///
+/// Errors if the pointer is invalid, a parent is neither an object nor an array
+/// or the array index is out of bounds.
+pub(crate) fn set(value: &mut Value, pointer: &str, new_value: Value) -> Result<()> {
+    let (parent, token) = match split_pointer(pointer)? {
+        Some(split) => split,
+        None => {
+            *value = new_value;
+            return Ok(());
+        }
+    };
+    if value.pointer(parent).is_none() {
+        set(value, parent, Value::Object(Default::default()))?;
+    }
+    match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.insert(token, new_value);
+            Ok(())
+        }
+        Some(Value::Array(array)) => {
+            let index = match token.as_str() {
+                "-" => array.len(),
+                token => parse_index(token, array.len() + 1, pointer)?,
+            };
+            match array.get_mut(index) {
+                Some(element) => *element = new_value,
+                None => array.push(new_value),
+            }
+            Ok(())
+        }
+        _ => Err(Error::InvalidPointer(pointer.to_owned())),
+    }
+}
+
+/// Removes and returns the value the JSON Pointer refers to.
+///
+/// # Errors
+///
+/// Errors if the pointer is invalid, refers to the whole document or there is no value at it.
+pub(crate) fn remove(value: &mut Value, pointer: &str) -> Result<Value> {
+    let (parent, token) =
+        split_pointer(pointer)?.ok_or_else(|| Error::InvalidPointer(pointer.to_owned()))?;
+    let removed = match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => remove_key(map, &token),
+        Some(Value::Array(array)) => {
+            let index = parse_index(&token, array.len(), pointer)?;
+            Some(array.remove(index))
+        }
+        _ => None,
+    };
+    removed.ok_or_else(|| Error::InvalidPointer(pointer.to_owned()))
+}
+
+/// Parses an array index reference token, which must be below `len`.
+///
+/// # Errors
+///
+/// Errors if the token is not a valid index or is out of bounds.
+fn parse_index(token: &str, len: usize, pointer: &str) -> Result<usize> {
+    match token.parse::<usize>() {
+        Ok(index) if index < len && (token == "0" || !token.starts_with('0')) => Ok(index),
+        _ => Err(Error::InvalidPointer(pointer.to_owned())),
+    }
+}
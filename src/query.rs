@@ -0,0 +1,37 @@
+use crate::{Error, Result, Store};
+use serde_json::Value;
+use serde_json_path::JsonPath;
+
+impl Store {
+    /// Returns every value matching the given [JSONPath](https://www.rfc-editor.org/rfc/rfc9535)
+    /// expression, in document order.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// # use serde_json::json;
+    /// let store = Store::new("query-app").unwrap();
+    /// store.set("servers", json!([
+    ///     { "host": "a.example.com", "enabled": true },
+    ///     { "host": "b.example.com", "enabled": false },
+    /// ])).unwrap();
+    /// let hosts = store.query("$.servers[?(@.enabled == true)].host").unwrap();
+    /// assert_eq!(hosts, [json!("a.example.com")]);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `expression` is not a valid JSONPath expression.
+    pub fn query(&self, expression: &str) -> Result<Vec<Value>> {
+        let json_path = JsonPath::parse(expression).map_err(Error::from)?;
+        let parsed_json = self.get_resolved_json()?;
+        let matches = json_path.query(&parsed_json).all();
+        Ok(matches.into_iter().cloned().collect())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{Error, Result, Store};
+use serde_json::Value;
+
+impl Store {
+    /// Sets the largest size in bytes the serialized store may grow to, or `None` for no
+    /// limit, the default. A write which would make the store larger fails with
+    /// [`Error::QuotaExceeded`] and leaves it unchanged, so code writing to the store cannot
+    /// fill the user's config directory.
+    ///
+    /// The size is that of the store's contents serialized as they are written, before
+    /// compression and encryption. Writes which don't grow a store over its limit are
+    /// allowed, so it can be brought back under the limit with [`delete`](Store::delete).
+    #[cfg_attr(
+        feature = "blobs",
+        doc = "The size of the store's [blobs](Store::put_blob) counts towards the limit."
+    )]
+    /// Per-namespace quotas are set with [`register_namespace`](Store::register_namespace).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{ErrorKind, Store};
+    /// let mut store = Store::new("max-size-app").unwrap();
+    /// store.set_max_size(Some(64));
+    /// store.set("theme", "dark").unwrap();
+    /// let error = store.set("notes", "-".repeat(64)).unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::QuotaExceeded);
+    /// assert_eq!(store.get("notes").unwrap(), None);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_max_size(&mut self, max_size: Option<usize>) {
+        self.max_size = max_size;
+    }
+
+    pub fn get_max_size(&self) -> Option<usize> {
+        self.max_size
+    }
+
+    /// Checks that the store's contents are within its size limit, or no larger than its
+    /// current contents.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the contents cannot be serialized or are larger than the limit, with
+    /// [`Error::QuotaExceeded`].
+    pub(crate) fn check_max_size(&self, value: &Value) -> Result<()> {
+        let limit = match self.max_size {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let contents_size = self.serialize_value(value)?.len();
+        #[cfg(feature = "blobs")]
+        let size = contents_size + self.get_blobs_size()?;
+        #[cfg(not(feature = "blobs"))]
+        let size = contents_size;
+        // Writes which don't grow the store are allowed, so a store over its limit can be
+        // brought back under it.
+        if size <= limit || contents_size <= self.get_current_size()? {
+            return Ok(());
+        }
+        Err(quota_exceeded(size, limit))
+    }
+
+    /// Checks that adding a blob of `len` bytes keeps the store within its size limit.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read or serialized, or would be larger than the limit
+    /// with the blob, with [`Error::QuotaExceeded`].
+    #[cfg(feature = "blobs")]
+    pub(crate) fn check_blob_size(&self, len: usize) -> Result<()> {
+        let limit = match self.max_size {
+            Some(limit) => limit,
+            None => return Ok(()),
+        };
+        let size = self.get_current_size()? + self.get_blobs_size()? + len;
+        match size > limit {
+            true => Err(quota_exceeded(size, limit)),
+            false => Ok(()),
+        }
+    }
+
+    /// Returns the size in bytes of the store's current contents serialized, `0` if the
+    /// store does not exist.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the store cannot be read or serialized.
+    fn get_current_size(&self) -> Result<usize> {
+        match self.get_store_as_parsed_json() {
+            Ok(value) => Ok(self.serialize_value(&value)?.len()),
+            Err(Error::NotFound) => Ok(0),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+fn quota_exceeded(size: usize, limit: usize) -> Error {
+    Error::QuotaExceeded {
+        path: String::new(),
+        size,
+        limit,
+    }
+}
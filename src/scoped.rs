@@ -0,0 +1,128 @@
+use crate::{Result, Store};
+use serde::Serialize;
+use serde_json::Value;
+
+/// A view of the store limited to the values under a dot path, returned by
+/// [`Store::scoped`].
+///
+/// Every path given to the view is relative to its prefix, so it can be handed to plugin or
+/// library code without that code being able to read or change the rest of the store.
+/// An empty path refers to the whole scope.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::Store;
+/// let store = Store::new("scoped-app").unwrap();
+/// store.set("theme", "dark").unwrap();
+///
+/// let plugin = store.scoped("plugins.myplugin");
+/// plugin.set("enabled", true).unwrap();
+/// assert_eq!(plugin.get("enabled").unwrap().unwrap(), true);
+/// assert!(plugin.get("theme").unwrap().is_none());
+/// assert_eq!(store.get("plugins.myplugin.enabled").unwrap().unwrap(), true);
+///
+/// plugin.delete("").unwrap();
+/// assert!(store.get("plugins.myplugin").unwrap().is_none());
+/// assert_eq!(store.get("theme").unwrap().unwrap(), "dark");
+/// # store.delete_store_dir().unwrap();
+/// ```
+pub struct ScopedStore<'s> {
+    store: &'s Store,
+    prefix: String,
+}
+
+impl<'s> ScopedStore<'s> {
+    /// Get the dot path the view is limited to.
+    pub fn get_prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns a view limited to a path within this view.
+    pub fn scoped(&self, path: &str) -> ScopedStore<'s> {
+        ScopedStore {
+            store: self.store,
+            prefix: self.scope_path(path),
+        }
+    }
+
+    /// Returns the value at the given path within the view, see [`Store::get`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn get(&self, path: &str) -> Result<Option<Value>> {
+        self.store.get(&self.scope_path(path))
+    }
+
+    /// Sets the given data at the given path within the view, see [`Store::set`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store fails to be created or written to.
+    /// * The data cannot be serialized.
+    /// * `path` is not a valid dot path.
+    pub fn set<T>(&self, path: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.store.set(&self.scope_path(path), data)
+    }
+
+    /// Deletes the given path within the view, returning the deleted value,
+    /// see [`Store::delete`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read or write the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn delete(&self, path: &str) -> Result<Option<Value>> {
+        self.store.delete(&self.scope_path(path))
+    }
+
+    /// Returns the keys at the given path within the view, see [`Store::keys`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * `path` is not a valid dot path.
+    pub fn keys(&self, path: &str) -> Result<Option<Vec<String>>> {
+        self.store.keys(&self.scope_path(path))
+    }
+
+    /// Returns the store the view is of.
+    pub(crate) fn store(&self) -> &'s Store {
+        self.store
+    }
+
+    /// Prefixes a path within the view with the view's prefix.
+    pub(crate) fn scope_path(&self, path: &str) -> String {
+        match (self.prefix.is_empty(), path.is_empty()) {
+            (_, true) => self.prefix.clone(),
+            (true, false) => path.to_string(),
+            (false, false) => format!("{}.{}", self.prefix, path),
+        }
+    }
+}
+
+impl Store {
+    /// Returns a view of the store whose paths are relative to the given dot path,
+    /// see [`ScopedStore`].
+    pub fn scoped(&self, path: &str) -> ScopedStore<'_> {
+        ScopedStore {
+            store: self,
+            prefix: path.to_string(),
+        }
+    }
+}
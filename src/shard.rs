@@ -0,0 +1,307 @@
+use crate::{error::IoContext, DecodedBytes, IoOp, Result, Store};
+use serde::Deserialize;
+use serde_json::{value::RawValue, Map, Value};
+use sha2::{Digest, Sha256};
+use std::{collections::HashMap, fmt::Write as _, fs, io, path::PathBuf};
+
+/// The key of the object referring to a shard in the store file, holding its file name.
+const SHARD_KEY: &str = "$shard";
+
+/// The key of the object referring to a shard in the store file, holding the hash of its
+/// section.
+const HASH_KEY: &str = "$hash";
+
+impl Store {
+    /// Sets whether each top-level object and array of the store is written to its own
+    /// file, a shard, in the directory returned by
+    /// [`get_shards_path`](Store::get_shards_path). It is disabled by default.
+    ///
+    /// The store file then holds the other top-level values and a reference to each shard.
+    /// Shards are named by a number followed by their key, and a write only writes the
+    /// sections that changed, while [`get`](Store::get) only reads the shard holding the
+    /// value. Every shard is compressed, encrypted, signed and checksummed like the store
+    /// file, which is replaced last, so a write is never seen half done. Replaced shards are
+    /// removed by the next write, once no reader can still be reading them.
+    ///
+    /// Shards are read whether or not sharding is enabled, and inlined again by the first
+    /// write once it is disabled.
+    ///
+    /// **NOTE:** Backups and history snapshots hold only the store file, so they cannot be
+    /// restored once the shards they refer to were replaced.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("sharded-app").unwrap();
+    /// store.set_sharded(true);
+    /// store.set("servers.eu", "eu.example.com").unwrap();
+    /// store.set("ui.theme", "dark").unwrap();
+    /// store.set("version", 2).unwrap();
+    /// assert_eq!(store.get("ui.theme").unwrap().unwrap(), "dark");
+    /// assert_eq!(std::fs::read_dir(store.get_shards_path()).unwrap().count(), 2);
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_sharded(&mut self, sharded: bool) {
+        self.sharded = sharded;
+    }
+
+    pub fn get_sharded(&self) -> bool {
+        self.sharded
+    }
+
+    /// Returns a boolean indicating whether writes split the store into shards, which
+    /// electron-store's format does not allow.
+    pub(crate) fn shards_apply(&self) -> bool {
+        #[cfg(feature = "electron")]
+        if self.electron.is_some() {
+            return false;
+        }
+        self.sharded
+    }
+
+    /// Returns the path to the directory the shards of the store are kept in, next to the
+    /// store file.
+    pub fn get_shards_path(&self) -> PathBuf {
+        self.get_store_path().with_extension("shards")
+    }
+
+    /// Writes the top-level objects and arrays of the store's contents to their shards,
+    /// returning the contents with references in their place. Sections which are unchanged
+    /// since the store file being replaced keep their shards.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a shard cannot be encoded or written.
+    pub(crate) fn write_shards(&self, value: Value, current: &mut CurrentShards) -> Result<Value> {
+        let map = match value {
+            Value::Object(map) => map,
+            value => return Ok(value),
+        };
+        let shards_path = self.get_shards_path();
+        self.dir_builder()
+            .recursive(true)
+            .create(&shards_path)
+            .context(IoOp::CreateDir, &shards_path)?;
+        let mut root = Map::new();
+        for (key, value) in map {
+            if !value.is_object() && !value.is_array() {
+                root.insert(key, value);
+                continue;
+            }
+            let hash = shard_hash(&value)?;
+            let name = match current.references.get(&key) {
+                Some(reference) if reference.hash == hash => reference.name.clone(),
+                _ => {
+                    let name = shard_name(current.next_number, &key);
+                    current.next_number += 1;
+                    self.replace_file(&shards_path.join(&name), &self.encode_file(&value)?)?;
+                    name
+                }
+            };
+            let mut reference = Map::new();
+            reference.insert(SHARD_KEY.to_string(), Value::String(name));
+            reference.insert(HASH_KEY.to_string(), Value::String(hash));
+            root.insert(key, Value::Object(reference));
+        }
+        Ok(Value::Object(root))
+    }
+
+    /// Removes the shards the store file about to be replaced does not refer to, and returns
+    /// the ones it does. Must be called with the store's lock file held.
+    ///
+    /// Shards are only removed once the store file referring to them was itself replaced,
+    /// so a reader which read the store file before a write still finds its shards. If the
+    /// store file cannot be decoded, for example because its key was just rotated, no shard
+    /// is removed or kept for reuse.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the shards directory cannot be read or a shard cannot be removed.
+    pub(crate) fn remove_stale_shards(&self) -> Result<CurrentShards> {
+        let mut current = CurrentShards {
+            references: HashMap::new(),
+            next_number: 1,
+        };
+        let shards_path = self.get_shards_path();
+        let entries = match fs::read_dir(&shards_path) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(current),
+            Err(e) => return Err(e).context(IoOp::ReadDir, &shards_path),
+        };
+        let references = self.read_shard_references();
+        for entry in entries {
+            let entry = entry.context(IoOp::ReadDir, &shards_path)?;
+            let name = entry.file_name();
+            let name = name.to_str().unwrap_or_default();
+            let referenced = match &references {
+                Some(references) => references.values().any(|reference| reference.name == name),
+                None => true,
+            };
+            if referenced {
+                current.next_number = current.next_number.max(shard_number(name) + 1);
+                continue;
+            }
+            let shard_path = entry.path();
+            fs::remove_file(&shard_path).context(IoOp::Remove, &shard_path)?;
+        }
+        current.references = references.unwrap_or_default();
+        Ok(current)
+    }
+
+    /// Removes every shard of the store.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the shards directory cannot be removed.
+    pub(crate) fn remove_shards(&self) -> Result<()> {
+        let shards_path = self.get_shards_path();
+        match fs::remove_dir_all(&shards_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(e).context(IoOp::Remove, &shards_path)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the shard references at the top level of the store file by key, an empty map
+    /// if there is no store file, or `None` if it cannot be read or decoded.
+    fn read_shard_references(&self) -> Option<HashMap<String, ShardReference>> {
+        let data = match self.read_store_file() {
+            Ok(data) => data,
+            Err(e) if e.is_not_found() => return Some(HashMap::new()),
+            Err(_) => return None,
+        };
+        let data = self.verify_store_data(data).ok()?;
+        let data = self.decode(data).ok()?.into_bytes().ok()?;
+        let root: HashMap<String, &RawValue> = serde_json::from_slice(&data).ok()?;
+        Some(
+            root.into_iter()
+                .filter_map(|(key, raw)| Some((key, parse_shard_reference(raw)?)))
+                .collect(),
+        )
+    }
+
+    /// Replaces the references to shards at the top level of the store's contents with the
+    /// contents of the shards.
+    ///
+    /// # Errors
+    ///
+    /// Errors if a shard cannot be read, decoded or deserialized.
+    pub(crate) fn load_shards(&self, value: Value) -> Result<Value> {
+        let mut map = match value {
+            Value::Object(map) => map,
+            value => return Ok(value),
+        };
+        for value in map.values_mut() {
+            if let Some(name) = shard_reference(value) {
+                *value = self.parse_json(&self.read_shard(name)?)?;
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// Returns the decoded contents of the shard with the given file name.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the shard cannot be read or decoded.
+    pub(crate) fn read_shard(&self, name: &str) -> Result<DecodedBytes<'static>> {
+        let shard_path = self.get_shards_path().join(name);
+        let data = fs::read(&shard_path).context(IoOp::Read, &shard_path)?;
+        self.record_metrics(|metrics| metrics.record_bytes_read(data.len()));
+        self.decode(self.verify_store_data(data)?)?.into_bytes()
+    }
+}
+
+/// The shards of the store file about to be replaced.
+pub(crate) struct CurrentShards {
+    /// The shard references of the store file by top-level key.
+    references: HashMap<String, ShardReference>,
+    /// The number the next shard written is named with, above that of every shard kept.
+    next_number: u64,
+}
+
+/// Returns the file name of a new shard holding the section with the given key, its number
+/// followed by the key with characters which may not be valid in file names replaced.
+///
+/// The number keeps names unique, so a shard is never replaced while a reader may still be
+/// reading it, and the key only makes the shards directory readable.
+fn shard_name(number: u64, key: &str) -> String {
+    let key: String = key
+        .chars()
+        .map(
+            |c| match c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                true => c,
+                false => '_',
+            },
+        )
+        .take(64)
+        .collect();
+    format!("{}-{}", number, key)
+}
+
+/// Returns the number of the shard with the given file name, `0` if it has none.
+fn shard_number(name: &str) -> u64 {
+    name.split('-')
+        .next()
+        .and_then(|number| number.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Returns the SHA-256 of a section as compact JSON in lower case hex, which tells whether
+/// it changed. It is kept in the store file, which is encrypted along with the shards.
+///
+/// # Errors
+///
+/// Errors if the contents cannot be serialized.
+fn shard_hash(value: &Value) -> Result<String> {
+    let mut hasher = Sha256::new();
+    serde_json::to_writer(&mut hasher, value)?;
+    Ok(hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut hash, byte| {
+            // Writing to a string cannot fail.
+            let _ = write!(hash, "{:02x}", byte);
+            hash
+        }))
+}
+
+/// A reference to a shard, deserialized without reading past the first other key.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ShardReference {
+    /// The file name of the shard.
+    #[serde(rename = "$shard")]
+    name: String,
+    /// The hash of the section held by the shard.
+    #[serde(rename = "$hash")]
+    hash: String,
+}
+
+/// Parses an unparsed top-level value as a shard reference, or returns `None` if it is not
+/// a reference.
+fn parse_shard_reference(raw: &RawValue) -> Option<ShardReference> {
+    if !raw.get().starts_with('{') {
+        return None;
+    }
+    serde_json::from_str(raw.get()).ok()
+}
+
+/// Returns the file name of the shard an unparsed top-level value refers to, or `None` if it
+/// is not a reference.
+pub(crate) fn raw_shard_reference(raw: &RawValue) -> Option<String> {
+    parse_shard_reference(raw).map(|reference| reference.name)
+}
+
+/// Returns the file name of the shard a top-level value refers to, or `None` if it is not a
+/// reference.
+fn shard_reference(value: &Value) -> Option<&str> {
+    match value.as_object() {
+        Some(map) if map.len() == 2 && map.get(HASH_KEY)?.is_string() => {
+            map.get(SHARD_KEY)?.as_str()
+        }
+        _ => None,
+    }
+}
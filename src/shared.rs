@@ -0,0 +1,185 @@
+use crate::{Result, Store};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, RwLock, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+/// A thread-safe handle to a [`Store`] that is cheap to clone.
+///
+/// Writers are serialized between threads sharing the handle, and every mutation of the
+/// store holds its lock file, serializing writers between processes.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{SharedStore, Store};
+/// let store = SharedStore::new(Store::new("shared-app").unwrap());
+/// let handles: Vec<_> = (0..4)
+///     .map(|i| {
+///         let store = store.clone();
+///         std::thread::spawn(move || store.set(&format!("thread{}", i), i).unwrap())
+///     })
+///     .collect();
+/// for handle in handles {
+///     handle.join().unwrap();
+/// }
+/// assert_eq!(store.get("thread3").unwrap().unwrap(), 3);
+/// # store.read(|store| store.delete_store_dir()).unwrap();
+/// ```
+#[derive(Clone)]
+pub struct SharedStore {
+    inner: Arc<RwLock<Store>>,
+    /// Stops the autosave thread when dropped or sent to.
+    autosave: Arc<Mutex<Option<Sender<()>>>>,
+    /// Stops the thread reloading the store on SIGHUP when dropped.
+    #[cfg(all(feature = "signals", unix))]
+    pub(crate) sighup: Arc<Mutex<Option<crate::signals::SighupReloader>>>,
+}
+
+impl SharedStore {
+    pub fn new(store: Store) -> Self {
+        SharedStore {
+            inner: Arc::new(RwLock::new(store)),
+            autosave: Arc::new(Mutex::new(None)),
+            #[cfg(all(feature = "signals", unix))]
+            sighup: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Defers writes and persists them on a background thread at most once per `interval`,
+    /// coalescing rapid changes into a single write.
+    ///
+    /// Deferred changes are flushed one last time when the last handle is dropped.
+    /// Calling this again replaces the previous interval.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{SharedStore, Store};
+    /// # use std::time::Duration;
+    /// let store = SharedStore::new(Store::new("autosave-app").unwrap());
+    /// store.set_autosave(Duration::from_millis(10));
+    /// for i in 0..100 {
+    ///     store.set("counter", i).unwrap();
+    /// }
+    /// std::thread::sleep(Duration::from_millis(50));
+    /// assert!(!store.read(|store| store.is_dirty()));
+    /// # store.stop_autosave().unwrap();
+    /// # store.read(|store| store.delete_store_dir()).unwrap();
+    /// ```
+    pub fn set_autosave(&self, interval: Duration) {
+        self.inner
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .set_deferred(true);
+        let (stop, stopped) = mpsc::channel();
+        let store = self.downgrade();
+        thread::spawn(move || autosave(store, interval, stopped));
+        // Dropping the previous sender stops the previous thread.
+        *self.autosave.lock().unwrap_or_else(|e| e.into_inner()) = Some(stop);
+    }
+
+    /// Stops autosaving, flushes any deferred changes and goes back to writing immediately.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the deferred changes cannot be written.
+    pub fn stop_autosave(&self) -> Result<()> {
+        self.autosave
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take();
+        self.write(|store| {
+            store.set_deferred(false);
+            store.flush()
+        })
+    }
+
+    /// Returns a weak reference to the store, for background threads which should stop
+    /// once the last handle is dropped.
+    pub(crate) fn downgrade(&self) -> Weak<RwLock<Store>> {
+        Arc::downgrade(&self.inner)
+    }
+
+    /// Calls `f` with shared access to the store.
+    pub fn read<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&Store) -> R,
+    {
+        let store = self.inner.read().unwrap_or_else(|e| e.into_inner());
+        f(&store)
+    }
+
+    /// Calls `f` with exclusive access to the store.
+    ///
+    /// `f` is exclusive between threads sharing the handle, but not between processes, as
+    /// each mutation it makes takes the store's lock file on its own. Use
+    /// [`Store::update`] for a read-modify-write held under the lock file.
+    ///
+    /// # Errors
+    ///
+    /// Errors if `f` errors.
+    pub fn write<R, F>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&mut Store) -> Result<R>,
+    {
+        let mut store = self.inner.write().unwrap_or_else(|e| e.into_inner());
+        f(&mut store)
+    }
+
+    /// Returns the value at the given path from the store, see [`Store::get`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::get`] errors.
+    pub fn get(&self, path: &str) -> Result<Option<Value>> {
+        self.read(|store| store.get(path))
+    }
+
+    /// Sets the value at the given path in the store, see [`Store::set`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::set`] errors.
+    pub fn set<T>(&self, path: &str, data: T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        self.write(|store| store.set(path, data))
+    }
+
+    /// Deletes the given path from the store, see [`Store::delete`].
+    ///
+    /// # Errors
+    ///
+    /// Errors if [`Store::delete`] errors.
+    pub fn delete(&self, path: &str) -> Result<Option<Value>> {
+        self.write(|store| store.delete(path))
+    }
+}
+
+impl From<Store> for SharedStore {
+    fn from(store: Store) -> Self {
+        SharedStore::new(store)
+    }
+}
+
+/// Flushes the store every `interval` while it is dirty, until told to stop,
+/// the sender is dropped or the store is gone.
+fn autosave(store: Weak<RwLock<Store>>, interval: Duration, stopped: mpsc::Receiver<()>) {
+    while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+        let store = match store.upgrade() {
+            Some(store) => store,
+            None => return,
+        };
+        let store = store.write().unwrap_or_else(|e| e.into_inner());
+        // A failed write keeps the changes dirty, so the next tick retries.
+        let _ = store.flush();
+    }
+}
@@ -0,0 +1,72 @@
+use crate::{SharedStore, Store};
+use signal_hook::{
+    consts::SIGHUP,
+    iterator::{Handle, Signals},
+};
+use std::{
+    sync::{RwLock, Weak},
+    thread,
+};
+
+/// Stops the thread reloading the store on SIGHUP when dropped.
+pub(crate) struct SighupReloader(Handle);
+
+impl Drop for SighupReloader {
+    fn drop(&mut self) {
+        self.0.close();
+    }
+}
+
+impl SharedStore {
+    /// Reloads the store whenever the process receives SIGHUP, following the daemon
+    /// convention of sending HUP to reload the configuration.
+    ///
+    /// The reload sends [`ChangeSource::External`](crate::ChangeSource::External) events
+    /// to the receivers returned by [`Store::subscribe`] for everything that changed.
+    /// Reloading stops when the last handle is dropped or
+    /// [`stop_reload_on_sighup`](SharedStore::stop_reload_on_sighup) is called.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{SharedStore, Store};
+    /// let store = SharedStore::new(Store::new("sighup-app").unwrap());
+    /// let events = store.read(|store| store.subscribe());
+    /// store.reload_on_sighup().unwrap();
+    /// // `kill -HUP <pid>` now reloads the store and sends the changes to `events`.
+    /// # drop(events);
+    /// # store.stop_reload_on_sighup();
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Errors if the signal handler cannot be installed.
+    pub fn reload_on_sighup(&self) -> crate::Result<()> {
+        let mut signals = Signals::new([SIGHUP])?;
+        let reloader = SighupReloader(signals.handle());
+        let store = self.downgrade();
+        thread::spawn(move || reload_on_signals(store, &mut signals));
+        // Dropping the previous reloader stops the previous thread.
+        *self.sighup.lock().unwrap_or_else(|e| e.into_inner()) = Some(reloader);
+        Ok(())
+    }
+
+    /// Stops reloading the store on SIGHUP.
+    pub fn stop_reload_on_sighup(&self) {
+        self.sighup.lock().unwrap_or_else(|e| e.into_inner()).take();
+    }
+}
+
+/// Reloads the store for every signal received, until the signals are closed
+/// or the store is gone.
+fn reload_on_signals(store: Weak<RwLock<Store>>, signals: &mut Signals) {
+    for _ in signals.forever() {
+        let store = match store.upgrade() {
+            Some(store) => store,
+            None => return,
+        };
+        let store = store.read().unwrap_or_else(|e| e.into_inner());
+        // A failed reload keeps the previous contents, the next signal retries.
+        let _ = store.reload();
+    }
+}
@@ -0,0 +1,190 @@
+//! Helpers for testing code that uses a [`Store`].
+use crate::{error::IoContext, IoOp, Result, Store};
+use std::{
+    env, fs,
+    ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+#[cfg(feature = "test-util")]
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+/// The number of temporary stores created by this process, keeping their directories unique.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// A store in a unique temporary directory which is deleted when the `TempStore` is dropped.
+///
+/// It dereferences to the [`Store`], so it can be used and configured like one.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::testing::TempStore;
+/// let dir = {
+///     let mut store = TempStore::new("temp-app").unwrap();
+///     store.set_pretty(true);
+///     store.set("a", 1).unwrap();
+///     assert!(store.get_store_path().starts_with(store.path()));
+///     store.path().to_path_buf()
+/// };
+/// assert!(!dir.exists());
+/// ```
+pub struct TempStore {
+    store: Store,
+    dir: PathBuf,
+}
+
+impl TempStore {
+    /// Creates a store for the given project in a new directory within the system's
+    /// temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the temporary directory cannot be created.
+    pub fn new(project_name: &str) -> Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos())
+            .unwrap_or_default();
+        let dir = env::temp_dir().join(format!(
+            "bland-{}-{}-{}",
+            process::id(),
+            nanos,
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir).context(IoOp::CreateDir, &dir)?;
+        let mut store = Store::new(project_name)?;
+        store.set_path(dir.clone());
+        Ok(TempStore { store, dir })
+    }
+
+    /// Get the path to the temporary directory containing the store.
+    pub fn path(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Deref for TempStore {
+    type Target = Store;
+
+    fn deref(&self) -> &Store {
+        &self.store
+    }
+}
+
+impl DerefMut for TempStore {
+    fn deref_mut(&mut self) -> &mut Store {
+        &mut self.store
+    }
+}
+
+impl Drop for TempStore {
+    fn drop(&mut self) {
+        // Deferred changes would otherwise be flushed into the deleted directory.
+        self.store.clear_pending_value();
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Faults injected into a store's reads and writes of its store file, for testing how code
+/// handles I/O errors, corrupt stores and decryption failures.
+///
+/// The injector is shared between its clones, so faults can be programmed after it is
+/// registered with [`Store::set_fault_injector`]. Backups and snapshots are not affected.
+///
+/// # Example
+///
+/// ```rust
+/// # use bland::{testing::{FaultInjector, TempStore}, Error};
+/// let faults = FaultInjector::new();
+/// let mut store = TempStore::new("faults-app").unwrap();
+/// store.set_fault_injector(faults.clone());
+/// store.set("a", 1).unwrap();
+/// faults.corrupt_reads(Some(b"not json".to_vec()));
+/// assert!(matches!(store.get("a"), Err(Error::Corrupt { .. })));
+/// ```
+#[cfg(feature = "test-util")]
+#[derive(Clone, Debug, Default)]
+pub struct FaultInjector {
+    faults: Arc<Mutex<Faults>>,
+}
+
+/// The faults currently injected by a [`FaultInjector`].
+#[cfg(feature = "test-util")]
+#[derive(Debug, Default)]
+struct Faults {
+    fail_reads: bool,
+    fail_writes: bool,
+    corrupt_data: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "test-util")]
+impl FaultInjector {
+    /// Creates an injector without any faults.
+    pub fn new() -> Self {
+        FaultInjector::default()
+    }
+
+    /// Sets whether reads of the store file fail with [`Error::Io`](crate::Error::Io).
+    pub fn fail_reads(&self, fail: bool) {
+        self.faults().fail_reads = fail;
+    }
+
+    /// Sets whether writes of the store file fail with [`Error::Io`](crate::Error::Io).
+    pub fn fail_writes(&self, fail: bool) {
+        self.faults().fail_writes = fail;
+    }
+
+    /// Sets the bytes returned by reads of the store file in place of its contents,
+    /// or `None` to read the file again.
+    pub fn corrupt_reads(&self, data: Option<Vec<u8>>) {
+        self.faults().corrupt_data = data;
+    }
+
+    /// Removes every fault.
+    pub fn reset(&self) {
+        *self.faults() = Faults::default();
+    }
+
+    /// Reads the file at the path, subject to the injected faults.
+    pub(crate) fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let faults = self.faults();
+        if faults.fail_reads {
+            return Err(io::Error::other("injected read failure")).context(IoOp::Read, path);
+        }
+        match &faults.corrupt_data {
+            Some(data) => Ok(data.clone()),
+            None => fs::read(path).context(IoOp::Read, path),
+        }
+    }
+
+    /// Returns an error if writes are programmed to fail.
+    pub(crate) fn check_write(&self, path: &Path) -> Result<()> {
+        match self.faults().fail_writes {
+            true => Err(io::Error::other("injected write failure")).context(IoOp::Write, path),
+            false => Ok(()),
+        }
+    }
+
+    fn faults(&self) -> std::sync::MutexGuard<'_, Faults> {
+        self.faults.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(feature = "test-util")]
+impl Store {
+    /// Injects the faults of the given injector into reads and writes of the store file.
+    pub fn set_fault_injector(&mut self, faults: FaultInjector) {
+        self.faults = Some(faults);
+    }
+
+    /// Stops injecting faults.
+    pub fn clear_fault_injector(&mut self) {
+        self.faults = None;
+    }
+}
@@ -0,0 +1,30 @@
+//! Instrumentation with `tracing`, compiled away unless the `tracing` feature is enabled.
+
+/// Enters a debug span for the rest of the scope and starts the timer read by [`trace_done`].
+macro_rules! trace_span {
+    ($timer:ident, $name:literal $(, $($fields:tt)+)?) => {
+        #[cfg(feature = "tracing")]
+        let $timer = (
+            tracing::debug_span!($name $(, $($fields)+)?).entered(),
+            std::time::Instant::now(),
+        );
+    };
+}
+
+/// Emits a debug event with the microseconds elapsed since [`trace_span`].
+macro_rules! trace_done {
+    ($timer:ident, $($fields:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(elapsed_us = $timer.1.elapsed().as_micros() as u64, $($fields)+);
+    };
+}
+
+/// Emits an info event.
+macro_rules! trace_info {
+    ($($fields:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::info!($($fields)+);
+    };
+}
+
+pub(crate) use {trace_done, trace_info, trace_span};
@@ -0,0 +1,149 @@
+use crate::{Error, Result, Store};
+use serde_json::Value;
+
+impl Store {
+    /// Sets whether the typed accessors such as [`get_bool`](Store::get_bool) convert values
+    /// stored as another type, disabled by default. Hand edited and imported stores often
+    /// hold `"true"` or `"42"` where a boolean or a number is meant.
+    ///
+    /// Strings are converted to booleans from `true`, `false`, `yes`, `no`, `on`, `off`, `1`
+    /// and `0` in any case, and to numbers when they parse as one. Numbers are converted to
+    /// booleans from `1` and `0`, and to integers when they have no fractional part.
+    /// [`get_string`](Store::get_string) returns numbers and booleans as JSON.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::{ErrorKind, Store};
+    /// let mut store = Store::new("coerce-types-app").unwrap();
+    /// store.set("telemetry", "yes").unwrap();
+    /// store.set("port", " 8080 ").unwrap();
+    /// let error = store.get_bool("telemetry").unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::InvalidValue);
+    ///
+    /// store.set_coerce_types(true);
+    /// assert_eq!(store.get_bool("telemetry").unwrap(), Some(true));
+    /// assert_eq!(store.get_i64("port").unwrap(), Some(8080));
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_coerce_types(&mut self, coerce_types: bool) {
+        self.coerce_types = coerce_types;
+    }
+
+    pub fn get_coerce_types(&self) -> bool {
+        self.coerce_types
+    }
+
+    /// Returns the string at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not a string and cannot be converted to one, with
+    ///   [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_string(&self, path: &str) -> Result<Option<String>> {
+        self.get_typed(path, "a string", |value, coerce| match value {
+            Value::String(s) => Some(s),
+            Value::Number(_) | Value::Bool(_) if coerce => Some(value.to_string()),
+            _ => None,
+        })
+    }
+
+    /// Returns the boolean at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not a boolean and cannot be converted to one, with
+    ///   [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_bool(&self, path: &str) -> Result<Option<bool>> {
+        self.get_typed(path, "a boolean", |value, coerce| match value {
+            Value::Bool(b) => Some(b),
+            Value::String(s) if coerce => match s.trim().to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(true),
+                "false" | "no" | "off" | "0" => Some(false),
+                _ => None,
+            },
+            Value::Number(n) if coerce => match n.as_i64() {
+                Some(1) => Some(true),
+                Some(0) => Some(false),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Returns the integer at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not an integer within the range of `i64` and cannot be converted to
+    ///   one, with [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_i64(&self, path: &str) -> Result<Option<i64>> {
+        self.get_typed(path, "an integer", |value, coerce| match value {
+            Value::Number(n) => n.as_i64().or_else(|| {
+                n.as_f64()
+                    .filter(|f| coerce && f.fract() == 0.0 && f.abs() < i64::MAX as f64)
+                    .map(|f| f as i64)
+            }),
+            Value::String(s) if coerce => s.trim().parse().ok(),
+            _ => None,
+        })
+    }
+
+    /// Returns the number at the given path.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The value is not a number and cannot be converted to one, with
+    ///   [`Error::InvalidValue`].
+    /// * `path` is not a valid dot path.
+    pub fn get_f64(&self, path: &str) -> Result<Option<f64>> {
+        self.get_typed(path, "a number", |value, coerce| match value {
+            Value::Number(n) => n.as_f64(),
+            Value::String(s) if coerce => s.trim().parse().ok().filter(|f: &f64| f.is_finite()),
+            _ => None,
+        })
+    }
+
+    /// Returns the value at the given path converted by `convert`, which is given whether
+    /// values of other types are coerced and returns `None` if the value has the wrong type.
+    ///
+    /// # Errors
+    ///
+    /// Errors if the value cannot be read or converted.
+    fn get_typed<T, F>(&self, path: &str, expected: &str, convert: F) -> Result<Option<T>>
+    where
+        F: FnOnce(Value, bool) -> Option<T>,
+    {
+        let value = match self.get(path)? {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        let found = value.to_string();
+        match convert(value, self.coerce_types) {
+            Some(converted) => Ok(Some(converted)),
+            None => Err(Error::InvalidValue {
+                path: path.to_string(),
+                reason: format!("expected {}, found {}", expected, found),
+            }),
+        }
+    }
+}
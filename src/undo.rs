@@ -0,0 +1,79 @@
+use crate::{Result, Store};
+use serde_json::Value;
+use std::collections::VecDeque;
+
+impl Store {
+    /// Sets how many `set` and `delete` calls can be undone, undo is disabled by default.
+    ///
+    /// Lowering the depth drops the oldest entries of the undo log.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use bland::Store;
+    /// let mut store = Store::new("undo-app").unwrap();
+    /// store.set_undo_depth(10);
+    /// store.set("theme", "light").unwrap();
+    /// store.set("theme", "dark").unwrap();
+    /// assert!(store.undo().unwrap());
+    /// assert_eq!(store.get("theme").unwrap().unwrap(), "light");
+    /// # store.delete_store_dir().unwrap();
+    /// ```
+    pub fn set_undo_depth(&mut self, depth: usize) {
+        self.undo_depth = depth;
+        let undo_log = self.undo_log.get_mut().unwrap_or_else(|e| e.into_inner());
+        truncate_log(undo_log, depth);
+    }
+
+    pub fn get_undo_depth(&self) -> usize {
+        self.undo_depth
+    }
+
+    /// Reverts the most recent `set` or `delete`, returning `false` if there was nothing to undo.
+    ///
+    /// The undo log is kept in memory and only covers mutations made through this `Store`.
+    ///
+    /// # Errors
+    ///
+    /// Errors if
+    /// * The store does not exist.
+    /// * It fails to read the store file.
+    /// * The store cannot be deserialized.
+    /// * The store file fails to be written to.
+    pub fn undo(&self) -> Result<bool> {
+        let mut undo_log = self.undo_log.lock().unwrap_or_else(|e| e.into_inner());
+        let previous = match undo_log.back() {
+            Some(previous) => previous.clone(),
+            None => return Ok(false),
+        };
+        let lock = self.lock_store_file()?;
+        let current = match self.observers.is_empty() || !self.store_exists() {
+            true => None,
+            false => Some(self.get_store_as_parsed_json()?),
+        };
+        self.write_or_defer(previous.clone())?;
+        drop(lock);
+        if let Some(current) = current {
+            self.notify_observers(&current, &previous);
+        }
+        undo_log.pop_back();
+        Ok(true)
+    }
+
+    /// Records the contents of the store before a mutation in the undo log.
+    pub(crate) fn record_undo(&self, previous: Value) {
+        if self.undo_depth == 0 {
+            return;
+        }
+        let mut undo_log = self.undo_log.lock().unwrap_or_else(|e| e.into_inner());
+        undo_log.push_back(previous);
+        truncate_log(&mut undo_log, self.undo_depth);
+    }
+}
+
+/// Drops the oldest entries of the undo log until it holds at most `depth` entries.
+fn truncate_log(undo_log: &mut VecDeque<Value>, depth: usize) {
+    while undo_log.len() > depth {
+        undo_log.pop_front();
+    }
+}